@@ -1,8 +1,8 @@
-/// Simple OMEGA Node Example
-///
-/// Demonstrates basic message transmission and reception using the OMEGA Protocol.
+//! Simple OMEGA Node Example
+//!
+//! Demonstrates basic message transmission and reception using the OMEGA Protocol.
 
-use omega_protocol::{OmegaNode, NodeConfig, OmegaParams};
+use omega_protocol::{OmegaNode, NodeConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -13,7 +13,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let freq = 1.5;
     let config = NodeConfig {
         omega: freq,
-        params: OmegaParams::default(),
+        ..Default::default()
     };
 
     let mut alice = OmegaNode::new(config.clone())?;
@@ -51,7 +51,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a third node with different frequency
     let config_charlie = NodeConfig {
         omega: 2.5,
-        params: OmegaParams::default(),
+        ..Default::default()
     };
     let mut charlie = OmegaNode::new(config_charlie)?;
 
@@ -93,8 +93,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Sweep Operator (Ŝ): Lipschitz constant = 1.0 (non-expansive)");
     println!("Pfadinvarianz Operator (P̂): Lipschitz constant = 1.0 (idempotent)");
     println!("Weight Transfer Operator (Ŵ): Lipschitz constant = 1.0 (convex)");
-    println!("DoubleKick Operator (D̂): Lipschitz constant ≈ 1.08 (near-isometry)");
-    println!("\nComposite Ω: Lipschitz constant < 1.1 (approximately contractive)");
+    println!("DoubleKick Operator (D̂): Lipschitz constant ≈ 1 + Σ|α| (near-isometry)");
+    println!(
+        "\nComposite Ω: Lipschitz constant = {:.4} (contractive: {})",
+        alice.composite_lipschitz(),
+        alice.is_contractive()
+    );
 
     Ok(())
 }
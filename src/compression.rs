@@ -0,0 +1,216 @@
+//! Payload compression applied before masking
+//!
+//! A sender may compress a message before it is masked (compress, then
+//! encrypt); the receiver decompresses after unmasking. The choice travels
+//! in the frame header as a one-byte code and is bound into the
+//! authentication tag, so it cannot be flipped in transit.
+//!
+//! Compression leaks information through the frame length: how well a
+//! payload compresses depends on its content. Leave it off for payloads
+//! that mix secrets with attacker-influenced data.
+//!
+//! `Deflate` is raw DEFLATE (RFC 1951) through `miniz_oxide`, which is pure
+//! Rust and builds for wasm. `Zstd` (behind the `zstd` feature) links the C
+//! zstd library, so it is for native targets; a receiver built without the
+//! feature rejects zstd frames as an unknown compression. Both decoders stop
+//! at `MAX_DECOMPRESSED_LEN`, so a small frame cannot expand without bound.
+
+use crate::types::*;
+
+/// Largest payload `decompress` will produce
+pub const MAX_DECOMPRESSED_LEN: usize = 16 * 1024 * 1024;
+
+/// Compression applied to a payload before masking
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Payload sent as is
+    #[default]
+    None,
+    /// Raw DEFLATE
+    Deflate,
+    /// Zstandard frame
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    #[cfg(not(feature = "zstd"))]
+    pub const ALL: [Compression; 2] = [Compression::None, Compression::Deflate];
+    #[cfg(feature = "zstd")]
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Deflate, Compression::Zstd];
+
+    /// Header code
+    pub fn code(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 2,
+        }
+    }
+
+    /// Compression for a header code; unknown codes are a `CodecError`
+    pub fn from_code(code: u8) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.code() == code)
+            .ok_or_else(|| OmegaError::CodecError(format!("unknown compression {}", code)))
+    }
+}
+
+/// Compress `data` with `method`, falling back to `Compression::None` when
+/// that would not make it smaller; returns the method actually used
+pub fn compress(data: &[u8], method: Compression) -> (Compression, Vec<u8>) {
+    let packed = match method {
+        Compression::None => return (Compression::None, data.to_vec()),
+        Compression::Deflate => deflate(data),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd_compress(data),
+    };
+    if packed.len() < data.len() {
+        (method, packed)
+    } else {
+        (Compression::None, data.to_vec())
+    }
+}
+
+/// Undo `compress`
+pub fn decompress(data: &[u8], method: Compression) -> Result<Vec<u8>> {
+    match method {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Deflate => inflate(data),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd_decompress(data),
+    }
+}
+
+/// miniz_oxide's default compression level
+const DEFLATE_LEVEL: u8 = 6;
+
+/// zstd's default compression level
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec(data, DEFLATE_LEVEL)
+}
+
+/// Decode a raw DEFLATE stream, failing once the output would pass
+/// `MAX_DECOMPRESSED_LEN`
+fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, MAX_DECOMPRESSED_LEN)
+        .map_err(|e| OmegaError::CodecError(format!("deflate: {}", e)))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(data: &[u8]) -> Vec<u8> {
+    zstd::bulk::compress(data, ZSTD_LEVEL).expect("zstd compresses any input in memory")
+}
+
+/// Decode a zstd frame, failing once the output would pass
+/// `MAX_DECOMPRESSED_LEN`
+#[cfg(feature = "zstd")]
+fn zstd_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let invalid = |e: std::io::Error| OmegaError::CodecError(format!("zstd: {}", e));
+    let mut out = Vec::new();
+    zstd::stream::read::Decoder::new(data)
+        .map_err(invalid)?
+        .take(MAX_DECOMPRESSED_LEN as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(invalid)?;
+    if out.len() > MAX_DECOMPRESSED_LEN {
+        return Err(OmegaError::CodecError("zstd: output too large".to_string()));
+    }
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{RngCore, SeedableRng};
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let text = b"the quick brown fox jumps over the lazy dog; ".repeat(40);
+        let mut random = vec![0u8; 3000];
+        StdRng::seed_from_u64(7).fill_bytes(&mut random);
+
+        for data in [&b""[..], b"a", b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", &text, &random] {
+            assert_eq!(inflate(&deflate(data)).unwrap(), data);
+        }
+        assert!(deflate(&text).len() < text.len() / 10);
+
+        // Incompressible input is sent as is
+        let (method, payload) = compress(&random, Compression::Deflate);
+        assert_eq!(method, Compression::None);
+        assert_eq!(payload, random);
+    }
+
+    #[test]
+    fn test_inflate_foreign_streams() {
+        // zlib.compressobj(9, zlib.DEFLATED, -15) output for 150 words drawn
+        // with a linear congruential generator: a dynamic block
+        let words = ["omega", "resonance", "sweep", "kick", "frame", "mask", "vector", "node"];
+        let mut x: u64 = 1;
+        let expected = (0..150)
+            .map(|_| {
+                x = (x * 1103515245 + 12345) % (1 << 31);
+                words[(x >> 16) as usize % 8]
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into_bytes();
+        let dynamic = [
+            0x7d, 0x53, 0xe1, 0x0e, 0x83, 0x11, 0x0c, 0x7c, 0x15, 0xaf, 0x26, 0xd6, 0x2d, 0x8b,
+            0x60, 0xe1, 0x8b, 0xbd, 0xfe, 0x92, 0x9e, 0xc5, 0x29, 0xdb, 0x9f, 0x43, 0x5b, 0xd7,
+            0x6b, 0x4b, 0x97, 0x70, 0x95, 0xea, 0x3a, 0x96, 0x2a, 0xad, 0x64, 0x9f, 0x83, 0xb8,
+            0xf8, 0x0c, 0x91, 0xa0, 0xbd, 0x45, 0x5e, 0xd8, 0xde, 0xab, 0x4f, 0xf2, 0xbd, 0x90,
+            0x7c, 0x8b, 0x2e, 0x97, 0x9b, 0x0c, 0x33, 0x70, 0xd2, 0xa8, 0xeb, 0xe7, 0xb1, 0x2f,
+            0xc9, 0x91, 0x43, 0x43, 0x26, 0x0c, 0x9f, 0xb9, 0x88, 0xd0, 0x92, 0xe4, 0xe1, 0xb7,
+            0x94, 0xf0, 0x8d, 0x7b, 0xb6, 0x02, 0xd5, 0x3b, 0x63, 0x99, 0x01, 0x38, 0x0b, 0xe2,
+            0xf4, 0x6a, 0x25, 0x1a, 0xe0, 0x99, 0x87, 0xc2, 0xd6, 0xfa, 0x4c, 0x0d, 0xa6, 0x2b,
+            0x5b, 0x4e, 0xae, 0x00, 0xfc, 0xaa, 0x42, 0xe1, 0x44, 0x0f, 0xdb, 0x32, 0x9d, 0xb1,
+            0xd8, 0x0e, 0x29, 0x05, 0x8b, 0xda, 0xa6, 0x8f, 0x7c, 0x40, 0x15, 0x60, 0xb4, 0x93,
+            0x1e, 0xda, 0x4e, 0x3f, 0x75, 0x82, 0xf4, 0x52, 0x29, 0x76, 0x5c, 0x76, 0x44, 0xac,
+            0x8e, 0xcc, 0xfb, 0x6c, 0xcf, 0x74, 0xdb, 0x13, 0x83, 0x20, 0x7e, 0x36, 0x56, 0xec,
+            0x42, 0x6d, 0xca, 0x61, 0x35, 0xe7, 0x86, 0xb1, 0xe8, 0xfd, 0x71, 0x52, 0xa3, 0x60,
+            0xa0, 0x1f, 0x63, 0xbe, 0xdc, 0x9f, 0xcc, 0x56, 0xf7, 0x3a, 0x93, 0x0f,
+        ];
+        assert_eq!(inflate(&dynamic).unwrap(), expected);
+
+        // A stored block
+        assert_eq!(inflate(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c']).unwrap(), b"abc");
+
+        assert!(inflate(&[0x01, 0x03, 0x00, 0xfc, 0xfe, b'a', b'b', b'c']).is_err());
+        assert!(inflate(&[0x07]).is_err());
+        assert!(inflate(&deflate(&expected)[..10]).is_err());
+        assert!(Compression::from_code(9).is_err());
+    }
+
+    #[test]
+    fn test_decompressed_size_is_capped() {
+        let bomb = deflate(&vec![0u8; MAX_DECOMPRESSED_LEN + 1]);
+        assert!(bomb.len() < 64 * 1024);
+        assert!(inflate(&bomb).is_err());
+        assert_eq!(inflate(&deflate(&vec![0u8; MAX_DECOMPRESSED_LEN])).unwrap().len(), MAX_DECOMPRESSED_LEN);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip_and_cap() {
+        let text = b"the quick brown fox jumps over the lazy dog; ".repeat(40);
+        let (method, packed) = compress(&text, Compression::Zstd);
+        assert_eq!(method, Compression::Zstd);
+        assert!(packed.len() < text.len() / 10);
+        assert_eq!(decompress(&packed, Compression::Zstd).unwrap(), text);
+        assert_eq!(Compression::from_code(2).unwrap(), Compression::Zstd);
+
+        let bomb = zstd_compress(&vec![0u8; MAX_DECOMPRESSED_LEN + 1]);
+        assert!(decompress(&bomb, Compression::Zstd).is_err());
+        assert!(decompress(&packed[..packed.len() / 2], Compression::Zstd).is_err());
+    }
+}
@@ -0,0 +1,410 @@
+//! Conformance vectors for alternate implementations
+//!
+//! `ConformanceSuite::generate` records, for masking, vectorization, each
+//! deterministic vector operator and a complete lossless frame, the
+//! configuration, the input and the output of this reference implementation.
+//! The suite serializes to JSON; another implementation computes one
+//! `CaseOutput` per case and `verify` checks them against the recorded
+//! outputs.
+//!
+//! DoubleKick is not covered: its impulses come from a random generator whose
+//! stream is not part of the protocol.
+
+use crate::frame::{Frame, FrameHeader};
+use crate::operators::{
+    masking::MaskingOperator,
+    pfadinvarianz::Pfadinvarianz,
+    resonance::ResonanceOperator,
+    sweep::Sweep,
+    weight_transfer::WeightTransfer,
+};
+use crate::types::*;
+use crate::utils;
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+
+/// Version of the case format
+pub const CONFORMANCE_VERSION: u32 = 1;
+
+/// Largest absolute difference tolerated between vector components, to allow
+/// for a different floating-point evaluation order
+pub const VECTOR_TOLERANCE: f64 = 1e-9;
+
+/// One conformance case: configuration, input and expected output
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConformanceCase {
+    /// Authenticated masking under the ephemeral key for `(omega, epoch)`
+    Masking {
+        omega: f64,
+        epoch: u64,
+        counter: u32,
+        /// Per-message nonce (all zero if absent)
+        #[serde(default)]
+        nonce: [u8; 12],
+        message: Vec<u8>,
+        expected: Vec<u8>,
+    },
+    /// `utils::vectorize`
+    Vectorize { input: Vec<u8>, expected: Vec<f64> },
+    /// One sweep transform at `tick`
+    Sweep {
+        tau0: f64,
+        beta: f64,
+        schedule: String,
+        period: f64,
+        delta_tau: f64,
+        tick: f64,
+        input: Vec<f64>,
+        expected: Vec<f64>,
+    },
+    /// Pfadinvarianz over the representative subset or the full group
+    Pfadinvarianz {
+        dimension: usize,
+        full_group: bool,
+        input: Vec<f64>,
+        expected: Vec<f64>,
+    },
+    /// One weight transfer with micro, meso and macro weights (targets equal
+    /// the initial weights)
+    WeightTransfer {
+        gamma: f64,
+        weights: [f64; 3],
+        input: Vec<f64>,
+        expected: Vec<f64>,
+    },
+    /// Resonance decision with a rectangular window
+    Resonance {
+        omega: f64,
+        epsilon: f64,
+        input: Vec<f64>,
+        expected: bool,
+    },
+    /// First frame a fresh lossless node sends under `nonce`, encoded with
+    /// `Frame::to_bytes`
+    Frame {
+        omega: f64,
+        epoch: u64,
+        /// Per-message nonce (all zero if absent)
+        #[serde(default)]
+        nonce: [u8; 12],
+        message: Vec<u8>,
+        expected: Vec<u8>,
+    },
+}
+
+/// Output an implementation produced for one case
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum CaseOutput {
+    Bytes(Vec<u8>),
+    Vector(Vec<f64>),
+    Flag(bool),
+}
+
+impl ConformanceCase {
+    /// Short name of the case kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ConformanceCase::Masking { .. } => "masking",
+            ConformanceCase::Vectorize { .. } => "vectorize",
+            ConformanceCase::Sweep { .. } => "sweep",
+            ConformanceCase::Pfadinvarianz { .. } => "pfadinvarianz",
+            ConformanceCase::WeightTransfer { .. } => "weight_transfer",
+            ConformanceCase::Resonance { .. } => "resonance",
+            ConformanceCase::Frame { .. } => "frame",
+        }
+    }
+
+    /// Recorded reference output
+    pub fn expected(&self) -> CaseOutput {
+        match self {
+            ConformanceCase::Masking { expected, .. } | ConformanceCase::Frame { expected, .. } => {
+                CaseOutput::Bytes(expected.clone())
+            }
+            ConformanceCase::Vectorize { expected, .. }
+            | ConformanceCase::Sweep { expected, .. }
+            | ConformanceCase::Pfadinvarianz { expected, .. }
+            | ConformanceCase::WeightTransfer { expected, .. } => CaseOutput::Vector(expected.clone()),
+            ConformanceCase::Resonance { expected, .. } => CaseOutput::Flag(*expected),
+        }
+    }
+
+    /// Compute the case's output with this implementation
+    pub fn run_reference(&self) -> Result<CaseOutput> {
+        let vector = |v: &[f64]| Array1::from_vec(v.to_vec());
+
+        Ok(match self {
+            ConformanceCase::Masking { omega, epoch, counter, nonce, message, .. } => {
+                let mut params = MaskingParams::ephemeral_from_frequency(*omega, *epoch);
+                params.counter = *counter;
+                params.nonce = *nonce;
+                CaseOutput::Bytes(MaskingOperator::new().mask(message, &params)?)
+            }
+            ConformanceCase::Vectorize { input, .. } => {
+                CaseOutput::Vector(utils::vectorize(input)?.to_vec())
+            }
+            ConformanceCase::Sweep { tau0, beta, schedule, period, delta_tau, tick, input, .. } => {
+                let mut sweep = Sweep::new(*tau0, *beta, schedule.parse()?)
+                    .with_cycle(*period, *delta_tau)?
+                    .with_tick(*tick);
+                CaseOutput::Vector(sweep.transform(&vector(input)).to_vec())
+            }
+            ConformanceCase::Pfadinvarianz { dimension, full_group, input, .. } => {
+                let pfad = if *full_group {
+                    Pfadinvarianz::full_group(*dimension)?
+                } else {
+                    Pfadinvarianz::subset(*dimension)
+                };
+                CaseOutput::Vector(pfad.apply(&vector(input)).to_vec())
+            }
+            ConformanceCase::WeightTransfer { gamma, weights, input, .. } => {
+                let levels = ScaleLevel::ALL.iter().cloned().zip(weights.iter().copied()).collect();
+                let mut transfer = WeightTransfer::new(*gamma, levels);
+                CaseOutput::Vector(transfer.transform(&vector(input)).to_vec())
+            }
+            ConformanceCase::Resonance { omega, epsilon, input, .. } => {
+                let resonance = ResonanceOperator::with_epsilon(*omega, *epsilon);
+                CaseOutput::Flag(resonance.is_resonant(&vector(input)))
+            }
+            ConformanceCase::Frame { omega, epoch, nonce, message, .. } => {
+                let mut params = MaskingParams::ephemeral_from_frequency(*omega, *epoch);
+                params.nonce = *nonce;
+                let header = FrameHeader { epoch: *epoch, nonce: *nonce, ..Default::default() };
+                let masked = MaskingOperator::new().mask_with_aad(message, &params, &header.associated_data())?;
+                let frame = Frame { header, vector: utils::pack_bytes(&masked) };
+                CaseOutput::Bytes(frame.to_bytes())
+            }
+        })
+    }
+
+    /// Why `output` does not match the expected output, if it does not
+    pub fn mismatch(&self, output: &CaseOutput) -> Option<String> {
+        match (self.expected(), output) {
+            (CaseOutput::Vector(expected), CaseOutput::Vector(actual)) => {
+                if expected.len() != actual.len() {
+                    return Some(format!("expected {} components, got {}", expected.len(), actual.len()));
+                }
+                expected
+                    .iter()
+                    .zip(actual)
+                    .position(|(e, a)| (e - a).is_nan() || (e - a).abs() > VECTOR_TOLERANCE)
+                    .map(|i| format!("component {}: expected {}, got {}", i, expected[i], actual[i]))
+            }
+            (expected, actual) if expected == *actual => None,
+            (expected, actual) => Some(format!("expected {:?}, got {:?}", expected, actual)),
+        }
+    }
+}
+
+/// Result of checking an implementation against a suite
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConformanceReport {
+    /// Cases whose output matched
+    pub passed: usize,
+    /// Index, kind and reason of every failed case
+    pub failures: Vec<(usize, &'static str, String)>,
+}
+
+impl ConformanceReport {
+    /// Whether every case passed
+    pub fn is_conformant(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Ordered set of conformance cases
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceSuite {
+    pub version: u32,
+    pub cases: Vec<ConformanceCase>,
+}
+
+impl ConformanceSuite {
+    /// Generate the reference suite
+    pub fn generate() -> Result<Self> {
+        let samples = |len: usize, seed: usize| -> Vec<f64> {
+            (0..len).map(|i| ((i * 37 + seed * 11) % 23) as f64 / 11.0 - 1.0).collect()
+        };
+        let bytes = |len: usize| -> Vec<u8> { (0..len).map(|i| (i * 53 + 7) as u8).collect() };
+        let tone = |omega: f64, len: usize| -> Vec<f64> {
+            (0..len).map(|i| (omega * i as f64).sin()).collect()
+        };
+
+        let mut cases = Vec::new();
+
+        for (omega, epoch, counter, nonce, len) in [
+            (1.0, 0, 0, [0; 12], 0),
+            (1.0, 0, 1, [0; 12], 13),
+            (1.0, 0, 1, *b"omega-nonce!", 13),
+            (2.5, 7, 0, [0; 12], 64),
+        ] {
+            cases.push(ConformanceCase::Masking {
+                omega,
+                epoch,
+                counter,
+                nonce,
+                message: bytes(len),
+                expected: Vec::new(),
+            });
+        }
+        for len in [0, 1, 5, 13] {
+            cases.push(ConformanceCase::Vectorize { input: bytes(len), expected: Vec::new() });
+        }
+        for (schedule, tick) in [
+            (SweepSchedule::Cosine, 0.0),
+            (SweepSchedule::Cosine, 17.0),
+            (SweepSchedule::Linear, 130.0),
+            (SweepSchedule::Constant, 3.0),
+        ] {
+            cases.push(ConformanceCase::Sweep {
+                tau0: 0.2,
+                beta: 0.1,
+                schedule: schedule.to_string(),
+                period: 100.0,
+                delta_tau: 0.2,
+                tick,
+                input: samples(10, tick as usize).iter().map(|x| x + 0.3).collect(),
+                expected: Vec::new(),
+            });
+        }
+        for (dimension, full_group) in [(5, false), (5, true), (7, false)] {
+            cases.push(ConformanceCase::Pfadinvarianz {
+                dimension,
+                full_group,
+                input: samples(dimension, dimension),
+                expected: Vec::new(),
+            });
+        }
+        for (gamma, weights) in [(0.3, [0.2, 0.5, 0.3]), (0.0, [1.0, 0.0, 0.0])] {
+            cases.push(ConformanceCase::WeightTransfer {
+                gamma,
+                weights,
+                input: samples(12, 4),
+                expected: Vec::new(),
+            });
+        }
+        for (omega, tone_omega) in [(1.0, 1.0), (1.0, 2.5)] {
+            cases.push(ConformanceCase::Resonance {
+                omega,
+                epsilon: 0.1,
+                input: tone(tone_omega, 256),
+                expected: false,
+            });
+        }
+        for (omega, epoch, nonce, len) in [(1.0, 0, [0; 12], 11), (1.5, 3, *b"omega-nonce!", 40)] {
+            cases.push(ConformanceCase::Frame { omega, epoch, nonce, message: bytes(len), expected: Vec::new() });
+        }
+
+        // Record the reference outputs
+        for case in cases.iter_mut() {
+            match (case.run_reference()?, case) {
+                (CaseOutput::Bytes(out), ConformanceCase::Masking { expected, .. })
+                | (CaseOutput::Bytes(out), ConformanceCase::Frame { expected, .. }) => *expected = out,
+                (CaseOutput::Vector(out), ConformanceCase::Vectorize { expected, .. })
+                | (CaseOutput::Vector(out), ConformanceCase::Sweep { expected, .. })
+                | (CaseOutput::Vector(out), ConformanceCase::Pfadinvarianz { expected, .. })
+                | (CaseOutput::Vector(out), ConformanceCase::WeightTransfer { expected, .. }) => *expected = out,
+                (CaseOutput::Flag(out), ConformanceCase::Resonance { expected, .. }) => *expected = out,
+                _ => unreachable!("run_reference returns the output type of its case"),
+            }
+        }
+
+        Ok(Self { version: CONFORMANCE_VERSION, cases })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Outputs of this implementation for every case, in order
+    pub fn run_reference(&self) -> Result<Vec<CaseOutput>> {
+        self.cases.iter().map(ConformanceCase::run_reference).collect()
+    }
+
+    /// Check an implementation's outputs, one per case in order
+    pub fn verify(&self, outputs: &[CaseOutput]) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+        for (index, case) in self.cases.iter().enumerate() {
+            let failure = match outputs.get(index) {
+                Some(output) => case.mismatch(output),
+                None => Some("no output".to_string()),
+            };
+            match failure {
+                Some(reason) => report.failures.push((index, case.kind(), reason)),
+                None => report.passed += 1,
+            }
+        }
+        if outputs.len() > self.cases.len() {
+            report.failures.push((self.cases.len(), "extra", format!(
+                "{} outputs for {} cases", outputs.len(), self.cases.len()
+            )));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_passes_own_suite() {
+        let suite = ConformanceSuite::generate().unwrap();
+        for kind in ["masking", "vectorize", "sweep", "pfadinvarianz", "weight_transfer", "resonance", "frame"] {
+            assert!(suite.cases.iter().any(|c| c.kind() == kind), "no {} case", kind);
+        }
+
+        // Through JSON, as another implementation would read it
+        let suite = ConformanceSuite::from_json(&suite.to_json().unwrap()).unwrap();
+        let report = suite.verify(&suite.run_reference().unwrap());
+        assert!(report.is_conformant(), "{:?}", report.failures);
+        assert_eq!(report.passed, suite.cases.len());
+
+        // The resonance cases cover both decisions
+        let flags: Vec<CaseOutput> = suite.cases.iter()
+            .filter(|c| c.kind() == "resonance")
+            .map(|c| c.expected())
+            .collect();
+        assert_eq!(flags, vec![CaseOutput::Flag(true), CaseOutput::Flag(false)]);
+    }
+
+    #[test]
+    fn test_verify_reports_deviations() {
+        let suite = ConformanceSuite::generate().unwrap();
+        let mut outputs = suite.run_reference().unwrap();
+        let index = suite.cases.iter().position(|c| c.kind() == "sweep").unwrap();
+        if let CaseOutput::Vector(v) = &mut outputs[index] {
+            v[0] += 1e-6;
+        }
+        outputs.pop();
+
+        let report = suite.verify(&outputs);
+        assert_eq!(report.passed, suite.cases.len() - 2);
+        assert_eq!(report.failures[0].0, index);
+        assert_eq!(report.failures[0].1, "sweep");
+    }
+
+    #[tokio::test]
+    async fn test_frame_case_matches_node() {
+        let suite = ConformanceSuite::generate().unwrap();
+        let Some(ConformanceCase::Frame { omega, epoch, message, .. }) =
+            suite.cases.iter().find(|c| c.kind() == "frame").cloned()
+        else {
+            panic!("suite has a frame case");
+        };
+
+        let mut node = crate::node::OmegaNode::new(NodeConfig::lossless()).unwrap();
+        node.set_epoch(epoch);
+        node.send_message(&message, omega).await.unwrap();
+        let frame = node.take_frame().unwrap();
+
+        // The node draws its own nonce; the case reproduces the frame given it
+        let case = ConformanceCase::Frame { omega, epoch, nonce: frame.header.nonce, message, expected: Vec::new() };
+        assert_eq!(case.run_reference().unwrap(), CaseOutput::Bytes(frame.to_bytes()));
+    }
+}
@@ -0,0 +1,576 @@
+//! Transmitted frames and per-stream reassembly
+//!
+//! A frame pairs the operator-transformed vector with a small header that
+//! travels alongside it unchanged. The header carries the logical stream id
+//! and a per-stream sequence number, letting one receiver demultiplex several
+//! independent conversations sharing a frequency. A message too large for
+//! one frame is split across several; its fragments share a message id and
+//! are joined again by a `FragmentAssembler` before decoding. Conversely,
+//! several small messages may share one frame as length-prefixed records
+//! (see `pack_records`).
+
+use crate::types::*;
+use crate::replay::ReplayKind;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+/// Stream used by plain `send_message` traffic; delivered without ordering
+pub const DEFAULT_STREAM: u16 = 0;
+
+/// `masking_mode` header code of an unmasked debug frame (see
+/// `OmegaNode::set_debug_plaintext_insecure`); not a `MaskingMode`, so nodes
+/// outside debug plaintext mode reject such frames
+pub const PLAINTEXT_MASKING_CODE: u8 = 0xFF;
+
+/// How long the fragments of an incomplete message are kept by default
+pub const DEFAULT_FRAGMENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Header sent alongside each frame vector
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameHeader {
+    /// Logical stream the frame belongs to
+    pub stream_id: u16,
+    /// Position of the frame within its stream, starting at 0
+    pub sequence: u32,
+    /// Masking nonce counter the payload was encrypted under
+    pub counter: u32,
+    /// Key epoch of the sender
+    pub epoch: u64,
+    /// `MaskingMode` code of the payload; part of the authenticated data
+    pub masking_mode: u8,
+    /// `Compression` code of the payload; part of the authenticated data
+    pub compression: u8,
+    /// Identifier shared by the fragments of one message
+    pub message_id: u32,
+    /// Position of this fragment within its message, starting at 0
+    pub fragment_index: u16,
+    /// Number of fragments the message was split into; 0 or 1 for a frame
+    /// carrying a whole message
+    pub fragment_count: u16,
+    /// Number of records packed into the payload; 0 for a frame carrying a
+    /// single plain message. Part of the authenticated data
+    pub record_count: u16,
+    /// Random per-message masking nonce; part of the authenticated data
+    pub nonce: [u8; 12],
+    /// Whether the payload starts with a destination `NodeId` (absent for
+    /// broadcasts); part of the authenticated data
+    pub addressed: bool,
+}
+
+impl FrameHeader {
+    /// Encode the header fields, in the order and byte layout of
+    /// `Frame::to_bytes`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ENCODED_HEADER_LEN - 4);
+        out.extend_from_slice(&self.stream_id.to_le_bytes());
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+        out.extend_from_slice(&self.counter.to_le_bytes());
+        out.extend_from_slice(&self.epoch.to_le_bytes());
+        out.push(self.masking_mode);
+        out.push(self.compression);
+        out.extend_from_slice(&self.message_id.to_le_bytes());
+        out.extend_from_slice(&self.fragment_index.to_le_bytes());
+        out.extend_from_slice(&self.fragment_count.to_le_bytes());
+        out.extend_from_slice(&self.record_count.to_le_bytes());
+        out.extend_from_slice(&self.nonce);
+        out.push(self.addressed as u8);
+        out
+    }
+
+    /// Associated data the payload's authentication tag covers: the encoded
+    /// header with the fragmentation fields cleared
+    ///
+    /// Those are rewritten when a message is split into fragments; the
+    /// fragments are authenticated together, by the tag of the reassembled
+    /// message.
+    pub fn associated_data(&self) -> Vec<u8> {
+        FrameHeader { message_id: 0, fragment_index: 0, fragment_count: 0, ..*self }.encode()
+    }
+
+    /// Whether the frame carries only part of a message
+    pub fn is_fragment(&self) -> bool {
+        self.fragment_count > 1
+    }
+}
+
+/// Unit of transmission on the (simulated) medium
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub header: FrameHeader,
+    pub vector: OmegaVector,
+}
+
+/// Bytes of the encoded header: stream id, sequence, counter, epoch,
+/// masking mode, compression, message id, fragment index and count, record
+/// count, nonce, addressed flag, length
+const ENCODED_HEADER_LEN: usize = 2 + 4 + 4 + 8 + 1 + 1 + 4 + 2 + 2 + 2 + 12 + 1 + 4;
+
+/// Bytes of the length prefix of each packed record
+pub const RECORD_PREFIX_LEN: usize = 4;
+
+impl Frame {
+    /// Encode for a byte transport: header fields then the vector length
+    /// and components, all little-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ENCODED_HEADER_LEN + 8 * self.vector.len());
+        out.extend_from_slice(&self.header.encode());
+        out.extend_from_slice(&(self.vector.len() as u32).to_le_bytes());
+        for x in self.vector.iter() {
+            out.extend_from_slice(&x.to_le_bytes());
+        }
+        out
+    }
+
+    /// Decode a frame produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = |msg: &str| OmegaError::CodecError(format!("frame: {}", msg));
+        if bytes.len() < ENCODED_HEADER_LEN {
+            return Err(invalid("truncated header"));
+        }
+        let field = |range: std::ops::Range<usize>| &bytes[range];
+        let header = FrameHeader {
+            stream_id: u16::from_le_bytes(field(0..2).try_into().expect("2 bytes")),
+            sequence: u32::from_le_bytes(field(2..6).try_into().expect("4 bytes")),
+            counter: u32::from_le_bytes(field(6..10).try_into().expect("4 bytes")),
+            epoch: u64::from_le_bytes(field(10..18).try_into().expect("8 bytes")),
+            masking_mode: bytes[18],
+            compression: bytes[19],
+            message_id: u32::from_le_bytes(field(20..24).try_into().expect("4 bytes")),
+            fragment_index: u16::from_le_bytes(field(24..26).try_into().expect("2 bytes")),
+            fragment_count: u16::from_le_bytes(field(26..28).try_into().expect("2 bytes")),
+            record_count: u16::from_le_bytes(field(28..30).try_into().expect("2 bytes")),
+            nonce: field(30..42).try_into().expect("12 bytes"),
+            addressed: match bytes[42] {
+                0 => false,
+                1 => true,
+                _ => return Err(invalid("addressed flag must be 0 or 1")),
+            },
+        };
+        let len = u32::from_le_bytes(field(43..47).try_into().expect("4 bytes")) as usize;
+
+        let body = &bytes[ENCODED_HEADER_LEN..];
+        if Some(body.len()) != len.checked_mul(8) {
+            return Err(invalid("vector length does not match its length field"));
+        }
+        let vector = body
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().expect("8 bytes")))
+            .collect();
+
+        Ok(Self { header, vector })
+    }
+
+    /// Split into fragments of at most `max_len` components, tagged with
+    /// `message_id`; a frame that already fits is returned whole
+    pub fn split(self, max_len: usize, message_id: u32) -> Result<Vec<Frame>> {
+        if max_len == 0 {
+            return Err(OmegaError::ParameterError("fragment size must be positive".to_string()));
+        }
+        let count = self.vector.len().div_ceil(max_len).max(1);
+        let count = u16::try_from(count).map_err(|_| {
+            OmegaError::ParameterError(format!("message needs {} fragments, more than {}", count, u16::MAX))
+        })?;
+        if count == 1 {
+            return Ok(vec![self]);
+        }
+
+        let components = self.vector.to_vec();
+        Ok(components
+            .chunks(max_len)
+            .enumerate()
+            .map(|(index, piece)| Frame {
+                header: FrameHeader {
+                    message_id,
+                    fragment_index: index as u16,
+                    fragment_count: count,
+                    ..self.header
+                },
+                vector: OmegaVector::from(piece.to_vec()),
+            })
+            .collect())
+    }
+}
+
+/// Pack `records` into one payload, each prefixed by its length as a
+/// little-endian `u32`
+pub fn pack_records<R: AsRef<[u8]>>(records: &[R]) -> Vec<u8> {
+    let total = records.iter().map(|r| RECORD_PREFIX_LEN + r.as_ref().len()).sum();
+    let mut out = Vec::with_capacity(total);
+    for record in records {
+        let record = record.as_ref();
+        out.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        out.extend_from_slice(record);
+    }
+    out
+}
+
+/// Split a payload produced by `pack_records` back into its `count` records
+///
+/// A truncated record, or bytes left over after the last one, is a
+/// `CodecError`.
+pub fn unpack_records(payload: &[u8], count: u16) -> Result<Vec<Vec<u8>>> {
+    let invalid = |msg: &str| OmegaError::CodecError(format!("records: {}", msg));
+    let mut records = Vec::with_capacity(count as usize);
+    let mut rest = payload;
+    for _ in 0..count {
+        if rest.len() < RECORD_PREFIX_LEN {
+            return Err(invalid("truncated length prefix"));
+        }
+        let (prefix, tail) = rest.split_at(RECORD_PREFIX_LEN);
+        let len = u32::from_le_bytes(prefix.try_into().expect("4 bytes")) as usize;
+        if tail.len() < len {
+            return Err(invalid("record longer than the remaining payload"));
+        }
+        let (record, tail) = tail.split_at(len);
+        records.push(record.to_vec());
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        return Err(invalid("trailing bytes after the last record"));
+    }
+    Ok(records)
+}
+
+/// Message delivered on a logical stream
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamMessage {
+    pub stream_id: u16,
+    pub sequence: u32,
+    /// Subscribed frequency whose key authenticated the frame
+    pub frequency: f64,
+    pub payload: Vec<u8>,
+}
+
+/// Result of processing one receive step
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReceiveOutcome {
+    /// A message was delivered
+    Message(StreamMessage),
+    /// No frame was pending
+    Empty,
+    /// The frame was not resonant with any listen frequency (off-frequency traffic)
+    NotResonant,
+    /// The frame failed authentication under every candidate key (wrong key or tampering)
+    AuthFailed,
+    /// The frame decoded but is held until earlier frames of its stream arrive
+    Held,
+    /// The frame decoded but is addressed to another `NodeId`
+    NotAddressed,
+    /// The frame was rejected by the replay guard
+    Replayed(ReplayKind),
+    /// The frame is a fragment, buffered until the rest of its message arrives
+    Fragment,
+}
+
+impl ReceiveOutcome {
+    /// The delivered message, if any
+    pub fn into_message(self) -> Option<StreamMessage> {
+        match self {
+            ReceiveOutcome::Message(message) => Some(message),
+            _ => None,
+        }
+    }
+}
+
+/// What a node does with a polled frame that is not for it (not resonant,
+/// not authenticated under any of its keys, or addressed to another node)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonResonantPolicy {
+    /// Discard the frame
+    #[default]
+    Drop,
+    /// Put the frame back at the far end of the local buffer, where it can
+    /// be handed on (e.g. with `transfer_message_to`); a frame that comes
+    /// back to the same node a second time is dropped
+    Requeue,
+}
+
+/// Reordering state of one stream
+#[derive(Clone, Debug, Default)]
+struct StreamState {
+    next_sequence: u32,
+    pending: BTreeMap<u32, (f64, Vec<u8>)>,
+}
+
+/// Per-stream in-order reassembly
+///
+/// Each stream releases its messages strictly in sequence order, holding
+/// early arrivals until the gap before them is filled. Streams never wait on
+/// one another. Frames on `DEFAULT_STREAM` are released immediately.
+#[derive(Clone, Debug, Default)]
+pub struct StreamReassembler {
+    streams: HashMap<u16, StreamState>,
+    ready: VecDeque<StreamMessage>,
+}
+
+impl StreamReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept a payload decoded under `frequency`; duplicates of
+    /// already-released frames are dropped
+    pub fn accept(&mut self, header: FrameHeader, frequency: f64, payload: Vec<u8>) {
+        if header.stream_id == DEFAULT_STREAM {
+            self.ready.push_back(StreamMessage {
+                stream_id: header.stream_id,
+                sequence: header.sequence,
+                frequency,
+                payload,
+            });
+            return;
+        }
+
+        let state = self.streams.entry(header.stream_id).or_default();
+        if header.sequence < state.next_sequence {
+            return;
+        }
+        state.pending.insert(header.sequence, (frequency, payload));
+
+        while let Some((frequency, payload)) = state.pending.remove(&state.next_sequence) {
+            self.ready.push_back(StreamMessage {
+                stream_id: header.stream_id,
+                sequence: state.next_sequence,
+                frequency,
+                payload,
+            });
+            state.next_sequence += 1;
+        }
+    }
+
+    /// Next message released for delivery, across all streams
+    pub fn next_ready(&mut self) -> Option<StreamMessage> {
+        self.ready.pop_front()
+    }
+
+    /// Number of frames held back waiting for earlier ones
+    pub fn pending(&self) -> usize {
+        self.streams.values().map(|s| s.pending.len()).sum()
+    }
+}
+
+/// Fragments of one message received so far
+#[derive(Clone, Debug)]
+struct PartialMessage {
+    pieces: Vec<Option<OmegaVector>>,
+    missing: usize,
+    first_seen: Instant,
+}
+
+/// Joins fragmented messages back into whole frames
+///
+/// Fragments are grouped by their header with the fragment index left out,
+/// so fragments of different senders that happen to share a message id are
+/// kept apart. A message still incomplete `timeout` after its first fragment
+/// arrived is dropped by `expire`.
+#[derive(Clone, Debug)]
+pub struct FragmentAssembler {
+    partial: HashMap<FrameHeader, PartialMessage>,
+    timeout: Duration,
+}
+
+impl Default for FragmentAssembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAGMENT_TIMEOUT)
+    }
+}
+
+impl FragmentAssembler {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            partial: HashMap::new(),
+            timeout,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Accept a received frame at time `now`
+    ///
+    /// A whole frame is returned unchanged. A fragment is buffered, and once
+    /// the last one of its message arrives the message is returned as a single
+    /// unfragmented frame. Duplicate fragments are ignored; a fragment index
+    /// outside its message is a `CodecError`.
+    pub fn accept(&mut self, frame: Frame, now: Instant) -> Result<Option<Frame>> {
+        let header = frame.header;
+        if !header.is_fragment() {
+            return Ok(Some(frame));
+        }
+        if header.fragment_index >= header.fragment_count {
+            return Err(OmegaError::CodecError(format!(
+                "frame: fragment {} of a message with {} fragments",
+                header.fragment_index, header.fragment_count
+            )));
+        }
+
+        let key = FrameHeader { fragment_index: 0, ..header };
+        let partial = self.partial.entry(key).or_insert_with(|| PartialMessage {
+            pieces: vec![None; header.fragment_count as usize],
+            missing: header.fragment_count as usize,
+            first_seen: now,
+        });
+        let slot = &mut partial.pieces[header.fragment_index as usize];
+        if slot.is_none() {
+            *slot = Some(frame.vector);
+            partial.missing -= 1;
+        }
+        if partial.missing > 0 {
+            return Ok(None);
+        }
+
+        let partial = self.partial.remove(&key).expect("entry just completed");
+        let components: Vec<f64> = partial.pieces.into_iter().flatten().flat_map(|piece| piece.to_vec()).collect();
+        Ok(Some(Frame {
+            header: FrameHeader { fragment_count: 1, ..key },
+            vector: OmegaVector::from(components),
+        }))
+    }
+
+    /// Drop messages still incomplete `timeout` after their first fragment,
+    /// returning how many were dropped
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let before = self.partial.len();
+        let timeout = self.timeout;
+        self.partial.retain(|_, partial| now.saturating_duration_since(partial.first_seen) < timeout);
+        before - self.partial.len()
+    }
+
+    /// Number of messages with fragments still missing
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(stream_id: u16, sequence: u32) -> FrameHeader {
+        FrameHeader { stream_id, sequence, ..Default::default() }
+    }
+
+    #[test]
+    fn test_out_of_order_frames_held_until_gap_filled() {
+        let mut reassembler = StreamReassembler::new();
+
+        reassembler.accept(header(1, 1), 1.0, b"b".to_vec());
+        assert!(reassembler.next_ready().is_none());
+        assert_eq!(reassembler.pending(), 1);
+
+        reassembler.accept(header(1, 0), 1.0, b"a".to_vec());
+        assert_eq!(reassembler.next_ready().unwrap().payload, b"a");
+        assert_eq!(reassembler.next_ready().unwrap().payload, b"b");
+        assert_eq!(reassembler.pending(), 0);
+
+        // Replayed frame is ignored
+        reassembler.accept(header(1, 0), 1.0, b"a".to_vec());
+        assert!(reassembler.next_ready().is_none());
+    }
+
+    #[test]
+    fn test_frame_bytes_round_trip() {
+        let frame = Frame {
+            header: FrameHeader {
+                stream_id: 3,
+                sequence: 7,
+                counter: 11,
+                epoch: 2,
+                masking_mode: 1,
+                compression: 1,
+                message_id: 0xDEAD_BEEF,
+                fragment_index: 2,
+                fragment_count: 5,
+                record_count: 3,
+                nonce: *b"twelve bytes",
+                addressed: true,
+            },
+            vector: ndarray::arr1(&[0.5, -1.25, f64::MIN_POSITIVE]),
+        };
+        let bytes = frame.to_bytes();
+        assert_eq!(Frame::from_bytes(&bytes).unwrap(), frame);
+
+        let mut bad_flag = bytes.clone();
+        bad_flag[42] = 2;
+        assert!(Frame::from_bytes(&bad_flag).is_err());
+
+        assert!(Frame::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Frame::from_bytes(&bytes[..5]).is_err());
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let records: [&[u8]; 3] = [b"one", b"", b"three"];
+        let payload = pack_records(&records);
+        assert_eq!(payload.len(), 3 * RECORD_PREFIX_LEN + 8);
+        assert_eq!(unpack_records(&payload, 3).unwrap(), records.map(<[u8]>::to_vec));
+
+        assert!(unpack_records(&payload, 4).is_err());
+        assert!(unpack_records(&payload, 2).is_err());
+        assert!(unpack_records(&payload[..payload.len() - 1], 3).is_err());
+    }
+
+    #[test]
+    fn test_default_stream_is_unordered() {
+        let mut reassembler = StreamReassembler::new();
+        reassembler.accept(header(DEFAULT_STREAM, 5), 1.0, b"x".to_vec());
+        reassembler.accept(header(DEFAULT_STREAM, 5), 1.0, b"y".to_vec());
+
+        assert_eq!(reassembler.next_ready().unwrap().payload, b"x");
+        assert_eq!(reassembler.next_ready().unwrap().payload, b"y");
+    }
+
+    #[test]
+    fn test_fragments_joined_in_any_order() {
+        let frame = Frame {
+            header: header(0, 4),
+            vector: OmegaVector::from((0..10).map(f64::from).collect::<Vec<_>>()),
+        };
+        let fragments = frame.clone().split(4, 9).unwrap();
+        assert_eq!(fragments.len(), 3);
+        assert!(fragments.iter().all(|f| f.header.fragment_count == 3 && f.header.message_id == 9));
+
+        let mut assembler = FragmentAssembler::default();
+        let now = Instant::now();
+        assert!(assembler.accept(fragments[2].clone(), now).unwrap().is_none());
+        assert!(assembler.accept(fragments[0].clone(), now).unwrap().is_none());
+        // Duplicates do not complete the message
+        assert!(assembler.accept(fragments[0].clone(), now).unwrap().is_none());
+        assert_eq!(assembler.pending(), 1);
+
+        let joined = assembler.accept(fragments[1].clone(), now).unwrap().unwrap();
+        assert_eq!(joined.vector, frame.vector);
+        assert!(!joined.header.is_fragment());
+        assert_eq!(assembler.pending(), 0);
+
+        // A small frame is not split
+        assert_eq!(frame.clone().split(10, 9).unwrap(), vec![frame]);
+    }
+
+    #[test]
+    fn test_incomplete_message_expires() {
+        let frame = Frame { header: header(0, 0), vector: OmegaVector::zeros(6) };
+        let fragments = frame.split(2, 1).unwrap();
+
+        let mut assembler = FragmentAssembler::new(Duration::from_secs(5));
+        let start = Instant::now();
+        assembler.accept(fragments[0].clone(), start).unwrap();
+        assembler.accept(fragments[1].clone(), start).unwrap();
+
+        assert_eq!(assembler.expire(start + Duration::from_secs(4)), 0);
+        assert_eq!(assembler.expire(start + Duration::from_secs(5)), 1);
+        assert_eq!(assembler.pending(), 0);
+
+        // The straggler starts a new partial message instead of completing one
+        assert!(assembler.accept(fragments[2].clone(), start).unwrap().is_none());
+    }
+}
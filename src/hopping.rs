@@ -0,0 +1,108 @@
+//! Frequency hopping over key epochs
+//!
+//! A `FrequencyHopper` maps each key epoch to one frequency of a fixed set,
+//! chosen by a keyed hash of a shared seed and the epoch. Nodes configured
+//! with the same seed and set derive the same sequence, so a sender hopping
+//! with `HopTarget` and a receiver listening on the hop frequency meet on
+//! every epoch without exchanging anything.
+
+use crate::types::*;
+use sha2::{Digest, Sha256};
+
+/// Deterministic epoch → frequency schedule shared by sender and receiver
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrequencyHopper {
+    seed: u64,
+    frequencies: Vec<f64>,
+}
+
+impl FrequencyHopper {
+    /// Hop over `frequencies` in an order derived from `seed`; the set must
+    /// be non-empty and every frequency positive and finite
+    pub fn new(seed: u64, frequencies: Vec<f64>) -> Result<Self> {
+        if frequencies.is_empty() {
+            return Err(OmegaError::ParameterError(
+                "Frequency hopper needs at least one frequency".to_string()
+            ));
+        }
+        if let Some(omega) = frequencies.iter().find(|omega| !(omega.is_finite() && **omega > 0.0)) {
+            return Err(OmegaError::ParameterError(format!(
+                "Hop frequency {} must be positive and finite", omega
+            )));
+        }
+        Ok(Self { seed, frequencies })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Frequencies hopped over
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    /// Frequency in use during `epoch`
+    ///
+    /// The index into the set is the first 8 bytes of
+    /// SHA-256("omega-hop" ‖ seed ‖ epoch), both little-endian, modulo the
+    /// set size.
+    pub fn frequency_at(&self, epoch: u64) -> f64 {
+        let digest = Sha256::new()
+            .chain_update(b"omega-hop")
+            .chain_update(self.seed.to_le_bytes())
+            .chain_update(epoch.to_le_bytes())
+            .finalize();
+        let word = u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"));
+        self.frequencies[(word % self.frequencies.len() as u64) as usize]
+    }
+}
+
+/// Marker target: send on the hop frequency of the current epoch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HopTarget;
+
+/// Where a message is sent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SendTarget {
+    /// A fixed frequency
+    Frequency(f64),
+    /// The node's hop frequency for its current epoch
+    Hop,
+}
+
+impl From<f64> for SendTarget {
+    fn from(omega: f64) -> Self {
+        SendTarget::Frequency(omega)
+    }
+}
+
+impl From<HopTarget> for SendTarget {
+    fn from(_: HopTarget) -> Self {
+        SendTarget::Hop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_is_shared_by_seed() {
+        let set = vec![1.0, 2.0, 3.0, 4.0];
+        let a = FrequencyHopper::new(7, set.clone()).unwrap();
+        let b = FrequencyHopper::new(7, set.clone()).unwrap();
+        let other = FrequencyHopper::new(8, set.clone()).unwrap();
+
+        let hops = |h: &FrequencyHopper| (0..64).map(|epoch| h.frequency_at(epoch)).collect::<Vec<_>>();
+        assert_eq!(hops(&a), hops(&b));
+        assert_ne!(hops(&a), hops(&other));
+        // Every frequency of the set gets used
+        assert!(set.iter().all(|omega| hops(&a).contains(omega)));
+        assert_eq!(a.frequency_at(u64::MAX), b.frequency_at(u64::MAX));
+
+        assert!(FrequencyHopper::new(7, Vec::new()).is_err());
+        assert!(FrequencyHopper::new(7, vec![1.0, f64::NAN]).is_err());
+        assert!(FrequencyHopper::new(7, vec![0.0]).is_err());
+    }
+}
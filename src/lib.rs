@@ -7,7 +7,7 @@ Operator-driven Meta-architecture for Encrypted Ghost-like Autonomous Networks
 
 OMEGA is a revolutionary network protocol built upon six fundamental operator classes:
 
-1. **Masking Operator (M̂_θ,σ)**: Information-theoretic encryption via permutation-rotation
+1. **Masking Operator (M̂_θ,σ)**: Keyed byte permutation and ChaCha20 keystream encryption
 2. **Resonance Operator (R̂_ω)**: Spectral coupling for address-free communication
 3. **Sweep Operator (Ŝ_τ)**: Adaptive threshold filtering with temporal scheduling
 4. **Pfadinvarianz Operator (P̂_Γ)**: Path-equivalence projection ensuring determinism
@@ -16,7 +16,7 @@ OMEGA is a revolutionary network protocol built upon six fundamental operator cl
 
 ## Key Properties
 
-- **Information-theoretic privacy** through masking involutions
+- **Confidentiality** through ChaCha20-based masking
 - **Provable convergence** via contractive operator sequences
 - **Adaptive resilience** through multi-scale weight transfer
 - **Path independence** via idempotent projections
@@ -25,14 +25,14 @@ OMEGA is a revolutionary network protocol built upon six fundamental operator cl
 ## Example Usage
 
 ```rust
-use omega_protocol::{OmegaNode, NodeConfig, OmegaParams};
+use omega_protocol::{OmegaNode, NodeConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a node with frequency 1.5
     let config = NodeConfig {
         omega: 1.5,
-        params: OmegaParams::default(),
+        ..Default::default()
     };
 
     let mut node = OmegaNode::new(config)?;
@@ -59,22 +59,54 @@ pub mod types;
 pub mod operators;
 pub mod node;
 pub mod utils;
+pub mod simulation;
+pub mod audit;
+pub mod pipeline;
+pub mod frame;
+pub mod metrics;
+pub mod throttle;
+pub mod logging;
+pub mod replay;
+pub mod transport;
+pub mod conformance;
+pub mod compression;
+pub mod hopping;
+pub mod split;
 
 // Re-export main types
 pub use types::{
-    OmegaVector, OmegaParams, OmegaError, Result,
-    NodeConfig, MaskingParams, ResonanceParams,
-    SweepParams, PfadinvarianzParams, WeightTransferParams,
-    DoubleKickParams, ScaleLevel,
+    OmegaVector, OmegaVectorF32, OmegaVectorF64, Float, OmegaParams, OmegaError, Result,
+    NodeConfig, NodeConfigBuilder, MaskingParams, Sigma, MaskingMode, KeystreamCipher, Capabilities, ResonanceParams,
+    SweepParams, SweepSchedule, SweepMode, PfadinvarianzParams, WeightTransferParams,
+    DoubleKickParams, ScaleLevel, NodeId, NodeDescriptor, NegotiatedSession, negotiate_capabilities,
+    FREQUENCY_QUANTUM, quantize_frequency,
 };
 
 pub use node::OmegaNode;
+pub use simulation::{Simulation, FrameRecord};
+pub use audit::{AuditEntry, AuditLog, AuditOperation};
+pub use pipeline::{OmegaPipeline, VectorStage, LinearStage, Operator};
+pub use metrics::{NodeMetrics, NodeStats};
+pub use throttle::TokenBucket;
+pub use logging::{LogConfig, OperatorKind, Verbosity};
+pub use replay::{ReplayGuard, ReplayKind, ReplayPolicy};
+pub use compression::Compression;
+pub use hopping::{FrequencyHopper, HopTarget, SendTarget};
+pub use split::{OmegaSink, OmegaStream};
+pub use transport::{Transport, InMemoryTransport, PeerHandle, PeerId};
+#[cfg(feature = "native")]
+pub use transport::TcpTransport;
+pub use conformance::{CaseOutput, ConformanceCase, ConformanceReport, ConformanceSuite};
+pub use frame::{
+    FragmentAssembler, Frame, FrameHeader, NonResonantPolicy, ReceiveOutcome, StreamMessage, DEFAULT_FRAGMENT_TIMEOUT,
+    DEFAULT_STREAM, PLAINTEXT_MASKING_CODE,
+};
 
 pub use operators::{
     OmegaOperator,
-    masking::MaskingOperator,
-    resonance::ResonanceOperator,
-    sweep::Sweep,
+    masking::{MaskingOperator, MaskingStream},
+    resonance::{ResonanceOperator, DetectionMethod, AutoThresholds, WindowKind},
+    sweep::{Sweep, SweepStats, ThresholdFn},
     pfadinvarianz::Pfadinvarianz,
     weight_transfer::WeightTransfer,
     doublekick::DoubleKick,
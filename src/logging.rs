@@ -0,0 +1,127 @@
+//! Per-operator logging verbosity
+//!
+//! Operators emit `tracing` events tagged with `operator = <name>`. A
+//! `LogConfig` decides, per operator, which of those events are emitted, so
+//! one operator can be traced in detail while the others stay silent.
+
+use std::collections::HashMap;
+
+/// Operators that emit log events
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperatorKind {
+    Masking,
+    Resonance,
+    Sweep,
+    Pfadinvarianz,
+    WeightTransfer,
+    DoubleKick,
+}
+
+impl OperatorKind {
+    pub const ALL: [OperatorKind; 6] = [
+        OperatorKind::Masking,
+        OperatorKind::Resonance,
+        OperatorKind::Sweep,
+        OperatorKind::Pfadinvarianz,
+        OperatorKind::WeightTransfer,
+        OperatorKind::DoubleKick,
+    ];
+
+    /// Value of the `operator` field on emitted events
+    pub fn name(&self) -> &'static str {
+        match self {
+            OperatorKind::Masking => "masking",
+            OperatorKind::Resonance => "resonance",
+            OperatorKind::Sweep => "sweep",
+            OperatorKind::Pfadinvarianz => "pfadinvarianz",
+            OperatorKind::WeightTransfer => "weight_transfer",
+            OperatorKind::DoubleKick => "doublekick",
+        }
+    }
+}
+
+/// How much an operator logs; each level includes the ones below it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// No events
+    #[default]
+    Off,
+    /// One event per operator application
+    Info,
+    /// Adds intermediate values
+    Debug,
+    /// Adds full vectors
+    Trace,
+}
+
+/// Per-operator verbosity; operators without an entry are `Off`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogConfig {
+    levels: HashMap<OperatorKind, Verbosity>,
+}
+
+impl LogConfig {
+    /// Everything off
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every operator at `verbosity`
+    pub fn all(verbosity: Verbosity) -> Self {
+        let mut config = Self::new();
+        for operator in OperatorKind::ALL {
+            config.set(operator, verbosity);
+        }
+        config
+    }
+
+    /// Builder-style `set`
+    pub fn with(mut self, operator: OperatorKind, verbosity: Verbosity) -> Self {
+        self.set(operator, verbosity);
+        self
+    }
+
+    pub fn set(&mut self, operator: OperatorKind, verbosity: Verbosity) {
+        self.levels.insert(operator, verbosity);
+    }
+
+    pub fn level(&self, operator: OperatorKind) -> Verbosity {
+        self.levels.get(&operator).copied().unwrap_or_default()
+    }
+
+    /// Whether `operator` emits events at `verbosity`
+    pub fn enabled(&self, operator: OperatorKind, verbosity: Verbosity) -> bool {
+        verbosity != Verbosity::Off && self.level(operator) >= verbosity
+    }
+
+    /// Emit an event for `operator` if enabled; `message` is only built when it is
+    pub(crate) fn emit<F: FnOnce() -> String>(&self, operator: OperatorKind, verbosity: Verbosity, message: F) {
+        if !self.enabled(operator, verbosity) {
+            return;
+        }
+
+        let operator = operator.name();
+        match verbosity {
+            Verbosity::Off => {}
+            Verbosity::Info => tracing::info!(operator, "{}", message()),
+            Verbosity::Debug => tracing::debug!(operator, "{}", message()),
+            Verbosity::Trace => tracing::trace!(operator, "{}", message()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_are_cumulative() {
+        let config = LogConfig::new().with(OperatorKind::Sweep, Verbosity::Debug);
+
+        assert!(config.enabled(OperatorKind::Sweep, Verbosity::Info));
+        assert!(config.enabled(OperatorKind::Sweep, Verbosity::Debug));
+        assert!(!config.enabled(OperatorKind::Sweep, Verbosity::Trace));
+        assert!(!config.enabled(OperatorKind::Masking, Verbosity::Info));
+        assert!(!LogConfig::all(Verbosity::Trace).enabled(OperatorKind::Masking, Verbosity::Off));
+    }
+}
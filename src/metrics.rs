@@ -0,0 +1,82 @@
+//! Node counters and Prometheus text exposition
+
+use std::fmt::Write;
+
+/// Monotonic counters describing a node's traffic
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeMetrics {
+    /// Frames broadcast by this node
+    pub messages_sent: u64,
+    /// Frames that passed the resonance check and decoded successfully
+    pub messages_received: u64,
+    /// Polled frames dropped as not resonant with the local frequency
+    pub resonance_rejected: u64,
+    /// Resonant frames that failed to devectorize or unmask
+    pub decode_errors: u64,
+    /// Frames whose authentication tag did not verify under any candidate key
+    pub auth_failures: u64,
+    /// Frames rejected by the replay guard
+    pub replays_rejected: u64,
+    /// Fragmented messages dropped because not all fragments arrived in time
+    pub incomplete_dropped: u64,
+}
+
+/// Resettable per-node message statistics
+///
+/// Unlike `NodeMetrics`, which are monotonic for scraping, these are meant
+/// to be read and cleared by an operator, and they separate every way a
+/// receive step can come up empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeStats {
+    /// Messages broadcast
+    pub sent: u64,
+    /// Frames received, authenticated and decoded
+    pub received: u64,
+    /// Frames dropped as not resonant with any subscribed frequency
+    pub dropped_frequency: u64,
+    /// Frames dropped because they failed authentication under every candidate key
+    pub dropped_auth: u64,
+    /// Frames dropped because they are addressed to another `NodeId`
+    pub dropped_address: u64,
+    /// Fragmented messages dropped because not all fragments arrived in time
+    pub dropped_incomplete: u64,
+    /// Frames `receive_fair` dropped because they failed to decode; other
+    /// receive calls return the error instead
+    pub dropped_decode: u64,
+    /// Receive steps that found no frame pending
+    pub poll_empty: u64,
+}
+
+impl NodeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NodeMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render the counters in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let counters = [
+            ("omega_messages_sent_total", "Frames broadcast by the node", self.messages_sent),
+            ("omega_messages_received_total", "Frames received and decoded by the node", self.messages_received),
+            ("omega_resonance_rejected_total", "Frames dropped as not resonant with the local frequency", self.resonance_rejected),
+            ("omega_decode_errors_total", "Resonant frames that failed to decode", self.decode_errors),
+            ("omega_auth_failures_total", "Frames that failed authentication", self.auth_failures),
+            ("omega_replays_rejected_total", "Frames rejected as replays", self.replays_rejected),
+            ("omega_incomplete_dropped_total", "Fragmented messages dropped incomplete", self.incomplete_dropped),
+        ];
+
+        let mut out = String::new();
+        for (name, help, value) in counters {
+            // Writing to a String cannot fail
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+}
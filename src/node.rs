@@ -1,9 +1,76 @@
-/// OMEGA Network Node Implementation
+//! OMEGA Network Node Implementation
 
 use crate::types::*;
 use crate::operators::*;
 use crate::utils;
+use crate::audit::{AuditEntry, AuditLog, AuditOperation};
+use crate::metrics::{NodeMetrics, NodeStats};
+use crate::throttle::TokenBucket;
+use crate::logging::{LogConfig, OperatorKind, Verbosity};
+use crate::replay::{FrameDigest, ReplayGuard, ReplayPolicy};
+use crate::pipeline::OmegaPipeline;
+use crate::transport::{PeerHandle, PeerId, Transport};
+use crate::compression::{self, Compression};
+use crate::hopping::{FrequencyHopper, SendTarget};
+use crate::split::{OmegaSink, OmegaStream};
+use crate::frame::{
+    self as frame, FragmentAssembler, Frame, FrameHeader, NonResonantPolicy, ReceiveOutcome, StreamMessage,
+    StreamReassembler, DEFAULT_STREAM, PLAINTEXT_MASKING_CODE, RECORD_PREFIX_LEN,
+};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
 use ndarray::Array1;
+use rand::rngs::OsRng;
+use rand::Rng;
+use tokio::sync::mpsc;
+
+/// Frames a node remembers having requeued, to drop them when they return
+pub const REQUEUE_MEMORY: usize = 1024;
+
+/// Largest payload `coalesce` packs into one frame, length prefixes included
+pub const MAX_COALESCED_LEN: usize = 4096;
+
+/// Level of the chunk ahead of the payload on the spectral path; the
+/// receiver divides the payload levels by its received level, undoing the
+/// sweep gate
+const PILOT_LEVEL: f64 = 1.0;
+
+/// Interval between polls of the medium while `negotiate` waits for a peer
+#[cfg(feature = "native")]
+const NEGOTIATION_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Source of `link_id`s, unique within the process
+static NEXT_LINK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Poll `future` once; a future that would have to wait is a
+/// `NetworkError("would block")`
+#[cfg(feature = "wasm")]
+fn run_without_waiting<F: std::future::Future>(future: F) -> Result<F::Output> {
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    match std::pin::pin!(future).poll(&mut context) {
+        std::task::Poll::Ready(output) => Ok(output),
+        std::task::Poll::Pending => Err(OmegaError::NetworkError("would block".to_string())),
+    }
+}
+
+/// Chunk-constant layout of the spectral path: a pilot chunk at
+/// `PILOT_LEVEL`, then one chunk per byte of the `vectorize` stream, padded
+/// with zero bytes so the length is a multiple of four (which the weight
+/// transfer inverse needs)
+fn spectral_layout(data: &[u8], dimension: usize) -> Result<OmegaVector> {
+    let mut levels = vec![PILOT_LEVEL];
+    levels.extend(utils::vectorize_with_dim(data, 1)?);
+    while !(levels.len() * dimension).is_multiple_of(4) {
+        levels.push(-1.0);
+    }
+    Ok(utils::spread_chunks(&Array1::from_vec(levels), dimension))
+}
 
 /// OMEGA Network Node
 pub struct OmegaNode {
@@ -18,249 +85,3420 @@ pub struct OmegaNode {
     // State
     local_frequency: f64,
     state_vector: OmegaVector,
+    // Components per chunk (state vector, vectorization, projection)
+    dimension: usize,
     epoch: u64,
 
     // Parameters
     params: OmegaParams,
 
     // Message buffer (simulated network)
-    message_buffer: Vec<OmegaVector>,
+    message_buffer: Vec<Frame>,
+
+    // Real medium replacing the simulated buffer for broadcast and poll
+    transport: Option<Box<dyn Transport>>,
+
+    // Registered peers, each receiving every broadcast
+    peers: BTreeMap<PeerId, PeerHandle>,
+
+    // This node's inbox; the sender half is handed out by `link`
+    link_id: PeerId,
+    link_tx: mpsc::UnboundedSender<Frame>,
+    link_rx: mpsc::UnboundedReceiver<Frame>,
+
+    // Next outgoing sequence number per stream
+    stream_sequences: HashMap<u16, u32>,
+
+    // Per-stream ordering of received frames
+    reassembler: StreamReassembler,
+
+    // Largest outgoing frame vector before a message is fragmented (None =
+    // never fragment), the next message id, and received fragments awaiting
+    // the rest of their message
+    fragment_size: Option<usize>,
+    next_message_id: u32,
+    fragments: FragmentAssembler,
+
+    // Outbound messages queued for `coalesce`, with their target frequency
+    outbox: Vec<(f64, Vec<u8>)>,
+
+    // Decoded messages awaiting `receive_fair`, one queue per frequency in
+    // order of first arrival, and the queue served next
+    fair_queues: Vec<(f64, VecDeque<Vec<u8>>)>,
+    fair_next: usize,
+
+    // Handling of frames not for this node, and digests of frames requeued
+    // so far (oldest first)
+    non_resonant_policy: NonResonantPolicy,
+    requeued: HashSet<FrameDigest>,
+    requeue_order: VecDeque<FrameDigest>,
+
+    // Recently accepted frames, for replay rejection
+    replay_guard: ReplayGuard,
+
+    // Tamper-evident operation log (disabled by default)
+    audit: Option<AuditLog>,
+
+    // Derived masking parameters keyed by (quantized frequency, epoch)
+    key_cache: masking::KeyScheduleCache,
+
+    // Next masking nonce counter per key (quantized frequency, epoch)
+    nonce_counters: HashMap<(i64, u64), u64>,
+
+    // Neighbouring frequency bins tried when unmasking (0 = exact bin only)
+    freq_search_window: usize,
+
+    // Neighbouring key epochs tried when unmasking (0 = local epoch only)
+    epoch_window: u64,
+
+    // Shared hop schedule, and the subscriptions it added (one per epoch
+    // within `epoch_window`)
+    hopper: Option<FrequencyHopper>,
+    hop_subscriptions: Vec<f64>,
+
+    // Logical address accepted besides `NodeId::BROADCAST`
+    node_id: NodeId,
+
+    // Traffic counters
+    metrics: NodeMetrics,
+    stats: NodeStats,
+
+    // Exact-reconstruction mode (masking only)
+    lossless: bool,
+
+    // Masking routine used for outgoing frames
+    masking_mode: MaskingMode,
+
+    // Send and accept unmasked frames (development only)
+    debug_plaintext: bool,
+
+    // Compression applied to outgoing payloads before masking
+    compression: Compression,
+
+    // Outgoing rate limit (disabled by default)
+    send_limiter: Option<TokenBucket>,
+
+    // Per-operator logging verbosity
+    log: LogConfig,
+
+    // User-supplied vector stages replacing the built-in operator sequence
+    pipeline: Option<OmegaPipeline>,
 }
 
 impl OmegaNode {
     pub fn new(config: NodeConfig) -> Result<Self> {
-        Ok(Self {
+        config.validate()?;
+        let doublekick = doublekick::DoubleKick::new(
+            config.params.doublekick.alpha1,
+            config.params.doublekick.alpha2,
+        );
+        let sweep = Self::build_sweep(&config.params.sweep)?;
+        Ok(Self::with_operators(config, sweep, doublekick))
+    }
+
+    /// Create a node whose DoubleKick perturbations are reproducible from
+    /// `seed`; masking nonces still come from the OS
+    pub fn with_seed(config: NodeConfig, seed: u64) -> Result<Self> {
+        config.validate()?;
+        let doublekick = doublekick::DoubleKick::with_seed(
+            config.params.doublekick.alpha1,
+            config.params.doublekick.alpha2,
+            seed,
+        );
+        let sweep = Self::build_sweep(&config.params.sweep)?;
+        Ok(Self::with_operators(config, sweep, doublekick))
+    }
+
+    /// Create a node that broadcasts and polls through `transport` instead of
+    /// the simulated in-process buffer
+    pub fn with_transport(config: NodeConfig, transport: Box<dyn Transport>) -> Result<Self> {
+        let mut node = Self::new(config)?;
+        node.set_transport(transport);
+        Ok(node)
+    }
+
+    /// Sweep operator configured from `params`, validating the schedule cycle
+    fn build_sweep(params: &SweepParams) -> Result<sweep::Sweep> {
+        Ok(sweep::Sweep::new(params.tau0, params.beta, params.schedule)
+            .with_cycle(params.period, params.delta_tau)?
+            .with_max_rate(params.max_rate)?
+            .with_mode(params.mode)
+            .with_warmup(params.warmup_ticks))
+    }
+
+    fn with_operators(config: NodeConfig, sweep: sweep::Sweep, doublekick: doublekick::DoubleKick) -> Self {
+        let (link_tx, link_rx) = mpsc::unbounded_channel();
+        Self {
             masking: masking::MaskingOperator::new(),
-            resonance: resonance::ResonanceOperator::new(config.omega),
-            sweep: sweep::Sweep::new(
-                config.params.sweep.tau0,
-                config.params.sweep.beta,
-                config.params.sweep.schedule.clone(),
-            ),
-            pfadinvarianz: pfadinvarianz::Pfadinvarianz::default(),
+            resonance: resonance::ResonanceOperator::with_epsilon(
+                config.omega,
+                config.params.resonance.epsilon,
+            )
+            .with_window(resonance::WindowKind::default()),
+            sweep,
+            pfadinvarianz: pfadinvarianz::Pfadinvarianz::subset(config.dimension),
             weight_transfer: weight_transfer::WeightTransfer::default(),
-            doublekick: doublekick::DoubleKick::new(
-                config.params.doublekick.alpha1,
-                config.params.doublekick.alpha2,
-            ),
+            doublekick,
 
             local_frequency: config.omega,
-            state_vector: Array1::zeros(5),
+            state_vector: Array1::zeros(config.dimension),
+            dimension: config.dimension,
             epoch: 0,
             params: config.params,
 
             message_buffer: Vec::new(),
-        })
+            transport: None,
+            peers: BTreeMap::new(),
+            link_id: NEXT_LINK_ID.fetch_add(1, Ordering::Relaxed),
+            link_tx,
+            link_rx,
+            stream_sequences: HashMap::new(),
+            reassembler: StreamReassembler::new(),
+            fragment_size: None,
+            next_message_id: rand::random(),
+            fragments: FragmentAssembler::default(),
+            outbox: Vec::new(),
+            fair_queues: Vec::new(),
+            fair_next: 0,
+            non_resonant_policy: NonResonantPolicy::default(),
+            requeued: HashSet::new(),
+            requeue_order: VecDeque::new(),
+            replay_guard: ReplayGuard::default(),
+
+            audit: None,
+
+            key_cache: masking::KeyScheduleCache::with_salt(config.key_salt).with_theta_steps(config.theta_steps),
+            nonce_counters: HashMap::new(),
+
+            freq_search_window: 0,
+            epoch_window: config.epoch_window,
+            hopper: None,
+            hop_subscriptions: Vec::new(),
+
+            node_id: NodeId::random(),
+
+            metrics: NodeMetrics::new(),
+            stats: NodeStats::new(),
+
+            lossless: config.lossless,
+
+            masking_mode: MaskingMode::default(),
+            debug_plaintext: false,
+            compression: Compression::default(),
+
+            send_limiter: None,
+
+            log: config.log,
+
+            pipeline: None,
+        }
+    }
+
+    /// New node with this node's configuration, frequency, subscriptions,
+    /// node ID and key epoch, but its own empty buffers, counters and fresh DoubleKick RNG
+    /// (e.g. to spawn a pool of receivers for one frequency)
+    pub fn fork(&self) -> OmegaNode {
+        let doublekick = doublekick::DoubleKick::new(
+            self.params.doublekick.alpha1,
+            self.params.doublekick.alpha2,
+        );
+        self.fork_with_doublekick(doublekick)
+    }
+
+    /// Like `fork`, with DoubleKick perturbations reproducible from `seed`
+    pub fn fork_with_seed(&self, seed: u64) -> OmegaNode {
+        let doublekick = doublekick::DoubleKick::with_seed(
+            self.params.doublekick.alpha1,
+            self.params.doublekick.alpha2,
+            seed,
+        );
+        self.fork_with_doublekick(doublekick)
+    }
+
+    fn fork_with_doublekick(&self, doublekick: doublekick::DoubleKick) -> OmegaNode {
+        let config = NodeConfig {
+            omega: self.local_frequency,
+            params: self.params.clone(),
+            lossless: self.lossless,
+            log: self.log.clone(),
+            key_salt: self.key_cache.salt().to_vec(),
+            epoch_window: self.epoch_window,
+            theta_steps: self.key_cache.theta_steps(),
+            dimension: self.dimension,
+        };
+        let mut sweep = self.sweep.clone();
+        sweep.reset();
+        let mut node = Self::with_operators(config, sweep, doublekick);
+
+        node.resonance.remove_listen_frequency(self.local_frequency);
+        for &(omega, epsilon) in self.resonance.listen_frequencies() {
+            node.resonance.add_listen_frequency_with_epsilon(omega, epsilon);
+        }
+        node.epoch = self.epoch;
+        node.node_id = self.node_id;
+        node.freq_search_window = self.freq_search_window;
+        node.masking_mode = self.masking_mode;
+        node.debug_plaintext = self.debug_plaintext;
+        node.compression = self.compression;
+        node.fragment_size = self.fragment_size;
+        node.hopper = self.hopper.clone();
+        node.hop_subscriptions = self.hop_subscriptions.clone();
+        node.fragments = FragmentAssembler::new(self.fragments.timeout());
+        node.non_resonant_policy = self.non_resonant_policy;
+        node.replay_guard = ReplayGuard::new(*self.replay_guard.policy());
+        node
+    }
+
+    /// Copy of this node running under `params`, for comparing parameter
+    /// sets on identical input
+    ///
+    /// The branch copies the frequency, subscriptions, node ID, epoch, state vector,
+    /// pending frames, stream, nonce and replay state, the sweep tick and the
+    /// DoubleKick RNG state, then applies `params` to the operators. Both
+    /// branches therefore continue from the same point; note they also reuse
+    /// the same nonce counters, so they are meant for comparison, not for
+    /// sending live traffic side by side. Each branch draws its own masking
+    /// nonces from the OS. Metrics and the audit log start empty.
+    pub fn branch(&self, params: OmegaParams) -> Result<OmegaNode> {
+        params.validate()?;
+        let sweep = Self::build_sweep(&params.sweep)?.with_tick(self.sweep.tick());
+        let doublekick = self
+            .doublekick
+            .reparameterized(vec![params.doublekick.alpha1, params.doublekick.alpha2]);
+        let config = NodeConfig {
+            omega: self.local_frequency,
+            params,
+            lossless: self.lossless,
+            log: self.log.clone(),
+            key_salt: self.key_cache.salt().to_vec(),
+            epoch_window: self.epoch_window,
+            theta_steps: self.key_cache.theta_steps(),
+            dimension: self.dimension,
+        };
+        let mut node = Self::with_operators(config, sweep, doublekick);
+
+        // The local band takes its bandwidth from the new params
+        for &(omega, epsilon) in self.resonance.listen_frequencies() {
+            if omega != self.local_frequency {
+                node.resonance.add_listen_frequency_with_epsilon(omega, epsilon);
+            }
+        }
+        node.state_vector = self.state_vector.clone();
+        node.epoch = self.epoch;
+        node.message_buffer = self.message_buffer.clone();
+        node.stream_sequences = self.stream_sequences.clone();
+        node.reassembler = self.reassembler.clone();
+        node.fragments = self.fragments.clone();
+        node.outbox = self.outbox.clone();
+        node.fair_queues = self.fair_queues.clone();
+        node.fair_next = self.fair_next;
+        node.replay_guard = self.replay_guard.clone();
+        node.nonce_counters = self.nonce_counters.clone();
+        node.freq_search_window = self.freq_search_window;
+        node.masking_mode = self.masking_mode;
+        node.debug_plaintext = self.debug_plaintext;
+        node.compression = self.compression;
+        node.fragment_size = self.fragment_size;
+        node.hopper = self.hopper.clone();
+        node.hop_subscriptions = self.hop_subscriptions.clone();
+        node.non_resonant_policy = self.non_resonant_policy;
+        node.node_id = self.node_id;
+        Ok(node)
     }
 
-    /// Send a message to a target frequency
+    /// Send a message to a target frequency, or with `HopTarget` to the
+    /// hop frequency of the current epoch
     pub async fn send_message(
         &mut self,
         message: &[u8],
-        target_freq: f64,
+        target: impl Into<SendTarget>,
     ) -> Result<()> {
-        // Algorithm 1: OMEGA Message Transmission
+        self.send_message_stream(DEFAULT_STREAM, message, target).await
+    }
 
-        // Step 1: Mask message (Layer 0)
-        let masking_params = self.derive_masking_params(target_freq);
-        let m0 = self.masking.mask(message, &masking_params)?;
+    /// Send a message, failing with `NetworkError("rate limited")` instead of
+    /// waiting when the send rate limit is exhausted
+    pub async fn try_send_message(
+        &mut self,
+        message: &[u8],
+        target: impl Into<SendTarget>,
+    ) -> Result<()> {
+        let target_freq = self.resolve_target(target.into())?;
+        if let Some(limiter) = self.send_limiter.as_mut() {
+            if !limiter.try_acquire() {
+                return Err(OmegaError::NetworkError("rate limited".to_string()));
+            }
+        }
+        self.transmit(DEFAULT_STREAM, NodeId::BROADCAST, message, target_freq).await
+    }
 
-        // Step 2: Vectorize
-        let mut v = utils::vectorize(&m0)?;
+    /// Send a message on logical stream `stream_id` to a target frequency
+    ///
+    /// Messages on the same stream are delivered to the receiver in the order
+    /// they were sent, independently of any other stream. Waits for a token
+    /// if a send rate limit is set.
+    pub async fn send_message_stream(
+        &mut self,
+        stream_id: u16,
+        message: &[u8],
+        target: impl Into<SendTarget>,
+    ) -> Result<()> {
+        let target_freq = self.resolve_target(target.into())?;
+        self.wait_for_send_token().await?;
+        self.transmit(stream_id, NodeId::BROADCAST, message, target_freq).await
+    }
 
-        // Step 3: Set resonance frequency (Layer 1)
-        v = utils::set_frequency(v, target_freq)?;
+    /// Send a message that only the node with ID `destination` (or every
+    /// node, for `NodeId::BROADCAST`) accepts among those resonant with
+    /// `target`
+    ///
+    /// Resonance still selects the candidate receivers; the address is
+    /// checked after unmasking. Waits for a token if a send rate limit is set.
+    pub async fn send_message_to(
+        &mut self,
+        message: &[u8],
+        destination: NodeId,
+        target: impl Into<SendTarget>,
+    ) -> Result<()> {
+        let target_freq = self.resolve_target(target.into())?;
+        self.wait_for_send_token().await?;
+        self.transmit(DEFAULT_STREAM, destination, message, target_freq).await
+    }
 
-        // Step 4: Apply operator sequence
-        // Layer 2: Sweep filtering
-        let mut v2 = self.sweep.transform(&v);
+    /// Logical address of this node
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
 
-        // Layer 3: Path-invariant projection
-        v2 = self.pfadinvarianz.apply(&v2);
+    /// Change the logical address of this node (nodes start with a random one)
+    pub fn set_node_id(&mut self, id: NodeId) {
+        self.node_id = id;
+    }
 
-        // Layer 4: Multi-scale transfer
-        v2 = self.weight_transfer.transform(&v2);
+    /// Whether a message addressed to `destination` is for this node
+    fn accepts_destination(&self, destination: NodeId) -> bool {
+        destination.is_broadcast() || destination == self.node_id
+    }
+
+    /// Frequency a message to `target` goes out on; hopping without a hop
+    /// schedule is a `ParameterError`
+    fn resolve_target(&self, target: SendTarget) -> Result<f64> {
+        match target {
+            SendTarget::Frequency(omega) => Ok(omega),
+            SendTarget::Hop => self.hop_frequency().ok_or_else(|| {
+                OmegaError::ParameterError("HopTarget needs a frequency hopper".to_string())
+            }),
+        }
+    }
+
+    /// Queue a message to `target_freq` for the next `coalesce`
+    pub fn queue_message(&mut self, message: &[u8], target_freq: f64) {
+        self.outbox.push((target_freq, message.to_vec()));
+    }
+
+    /// Number of messages queued for `coalesce`
+    pub fn queued_messages(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Send every queued message, merging small messages to the same
+    /// frequency into shared frames; returns the number of frames sent
+    ///
+    /// Each frame carries its messages as length-prefixed records, at most
+    /// `MAX_COALESCED_LEN` bytes in all, and the receiver delivers them as
+    /// separate messages on `DEFAULT_STREAM`. Messages to one frequency keep
+    /// their queue order. A message too large to share a frame is sent
+    /// alone. Every frame waits for a token if a send rate limit is set. On
+    /// error, messages not yet sent are dropped.
+    pub async fn coalesce(&mut self) -> Result<usize> {
+        let mut groups: Vec<(f64, Vec<Vec<u8>>)> = Vec::new();
+        for (target_freq, message) in std::mem::take(&mut self.outbox) {
+            match groups.iter_mut().find(|(freq, _)| *freq == target_freq) {
+                Some((_, messages)) => messages.push(message),
+                None => groups.push((target_freq, vec![message])),
+            }
+        }
+
+        let mut frames = 0;
+        for (target_freq, messages) in groups {
+            let mut batch: Vec<Vec<u8>> = Vec::new();
+            let mut batch_len = 0;
+            for message in messages {
+                let len = RECORD_PREFIX_LEN + message.len();
+                if !batch.is_empty() && batch_len + len > MAX_COALESCED_LEN {
+                    self.send_records(&std::mem::take(&mut batch), target_freq).await?;
+                    frames += 1;
+                    batch_len = 0;
+                }
+                batch_len += len;
+                batch.push(message);
+            }
+            if !batch.is_empty() {
+                self.send_records(&batch, target_freq).await?;
+                frames += 1;
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Send `records` in one frame; a single record goes as a plain message
+    async fn send_records(&mut self, records: &[Vec<u8>], target_freq: f64) -> Result<()> {
+        self.wait_for_send_token().await?;
+        match records {
+            [message] => self.transmit(DEFAULT_STREAM, NodeId::BROADCAST, message, target_freq).await,
+            _ => {
+                let payload = frame::pack_records(records);
+                self.transmit_payload(DEFAULT_STREAM, NodeId::BROADCAST, &payload, records.len() as u16, target_freq)
+                    .await
+            }
+        }
+    }
 
-        // Layer 5: DoubleKick perturbation
-        v = self.doublekick.apply(&v2);
+    /// Wait until the send rate limit, if any, grants a token
+    #[cfg(feature = "native")]
+    async fn wait_for_send_token(&mut self) -> Result<()> {
+        if let Some(limiter) = self.send_limiter.as_mut() {
+            while !limiter.try_acquire() {
+                tokio::time::sleep(limiter.time_until_available()).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Without a timer to wait on, an exhausted rate limit fails the send
+    /// with `NetworkError("rate limited")`, as in `try_send_message`
+    #[cfg(not(feature = "native"))]
+    async fn wait_for_send_token(&mut self) -> Result<()> {
+        if let Some(limiter) = self.send_limiter.as_mut() {
+            if !limiter.try_acquire() {
+                return Err(OmegaError::NetworkError("rate limited".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn transmit(
+        &mut self,
+        stream_id: u16,
+        destination: NodeId,
+        message: &[u8],
+        target_freq: f64,
+    ) -> Result<()> {
+        self.transmit_payload(stream_id, destination, message, 0, target_freq).await
+    }
+
+    /// Transmit `payload`, holding `record_count` packed records (0 = one
+    /// plain message)
+    async fn transmit_payload(
+        &mut self,
+        stream_id: u16,
+        destination: NodeId,
+        message: &[u8],
+        record_count: u16,
+        target_freq: f64,
+    ) -> Result<()> {
+        let frame = self.encode_frame(stream_id, destination, message, record_count, target_freq)?;
 
         // Step 5: Broadcast to network (simulated)
-        self.broadcast(v).await?;
+        match self.fragment_size {
+            Some(max_len) if frame.vector.len() > max_len => {
+                let message_id = self.next_message_id;
+                self.next_message_id = self.next_message_id.wrapping_add(1);
+                for fragment in frame.split(max_len, message_id)? {
+                    self.broadcast(fragment).await?;
+                }
+            }
+            _ => self.broadcast(frame).await?,
+        }
+        self.metrics.messages_sent += 1;
+        self.stats.sent += 1;
 
         Ok(())
     }
 
-    /// Receive a message if one is resonant with local frequency
-    pub async fn receive_message(&mut self) -> Result<Option<Vec<u8>>> {
-        // Algorithm 2: OMEGA Message Reception
+    /// Steps 1-4 of transmission: the frame carrying `message` for
+    /// `destination` as the next frame of `stream_id`
+    ///
+    /// Unless it is a broadcast, the destination ID is prepended to the
+    /// message, so it is compressed and masked along with it.
+    fn encode_frame(
+        &mut self,
+        stream_id: u16,
+        destination: NodeId,
+        message: &[u8],
+        record_count: u16,
+        target_freq: f64,
+    ) -> Result<Frame> {
+        let addressed = !destination.is_broadcast();
+        let with_destination;
+        let message = match addressed {
+            true => {
+                with_destination = [&destination.as_bytes()[..], message].concat();
+                &with_destination[..]
+            }
+            false => message,
+        };
 
-        // Poll network
-        let v_received = match self.poll_network().await? {
-            Some(v) => v,
-            None => return Ok(None),
+        // Algorithm 1: OMEGA Message Transmission
+        if self.debug_plaintext {
+            return self.encode_plaintext(stream_id, message, record_count, addressed, target_freq);
+        }
+
+        // Compress before masking; kept uncompressed if that would not shrink it
+        let (compression, payload) = compression::compress(message, self.compression);
+
+        // Step 1: Mask message (Layer 0) under a fresh nonce
+        let mut masking_params = self.derive_masking_params(target_freq);
+        self.masking_mode.apply(&mut masking_params);
+        masking_params.counter = self.next_nonce_counter(target_freq)?;
+        masking_params.nonce = OsRng.gen();
+
+        // The tag binds the header, which travels unmasked
+        let header = FrameHeader {
+            stream_id,
+            sequence: self.stream_sequences.get(&stream_id).copied().unwrap_or(0),
+            counter: masking_params.counter,
+            epoch: self.epoch,
+            masking_mode: self.masking_mode.code(),
+            compression: compression.code(),
+            record_count,
+            nonce: masking_params.nonce,
+            addressed,
+            ..Default::default()
         };
+        let m0 = masking::MaskingOperator::for_mode(self.masking_mode).mask_with_aad(
+            &payload,
+            &masking_params,
+            &header.associated_data(),
+        )?;
+        self.log.emit(OperatorKind::Masking, Verbosity::Info, || {
+            format!("masked {} bytes ({} before {:?} compression) into {}", payload.len(), message.len(), compression, m0.len())
+        });
+        self.log.emit(OperatorKind::Masking, Verbosity::Debug, || {
+            format!("rounds {}, authenticated {}", masking_params.rounds, masking_params.authenticated)
+        });
 
-        // Apply inverse operators (where applicable)
-        let mut v = v_received;
+        let v = if self.lossless {
+            // Exact packing; the spectral layers are bypassed
+            utils::pack_bytes(&m0)
+        } else {
+            self.spectral_encode(&m0, target_freq)?
+        };
+
+        *self.stream_sequences.entry(stream_id).or_insert(0) += 1;
+
+        self.record_audit(AuditOperation::Send, target_freq, &v);
+        Ok(Frame { header, vector: v })
+    }
 
-        // Layer 5: DoubleKick (approximately removed by subsequent operations)
-        // No explicit inverse needed
+    /// Encoding without compression or masking: the message bytes go
+    /// straight into the vector, under the plaintext header code
+    fn encode_plaintext(
+        &mut self,
+        stream_id: u16,
+        message: &[u8],
+        record_count: u16,
+        addressed: bool,
+        target_freq: f64,
+    ) -> Result<Frame> {
+        let v = if self.lossless {
+            utils::pack_bytes(message)
+        } else {
+            self.spectral_encode(message, target_freq)?
+        };
 
-        // Layer 4: Weight transfer (convergence property)
-        // No explicit inverse needed
+        let sequence = self.stream_sequences.entry(stream_id).or_insert(0);
+        let header = FrameHeader {
+            stream_id,
+            sequence: *sequence,
+            epoch: self.epoch,
+            masking_mode: PLAINTEXT_MASKING_CODE,
+            record_count,
+            addressed,
+            ..Default::default()
+        };
+        *sequence += 1;
 
-        // Layer 3: Pfadinvarianz (idempotent)
-        v = self.pfadinvarianz.apply(&v);
+        self.record_audit(AuditOperation::Send, target_freq, &v);
+        Ok(Frame { header, vector: v })
+    }
 
-        // Layer 2: Sweep (inverse via threshold)
-        // For simplicity, we skip explicit inverse
+    /// Steps 2-4 of transmission: lay the length-prefixed masked bytes out
+    /// as chunk levels, apply the operator sequence and imprint the target
+    /// frequency
+    ///
+    /// Each chunk of `dimension` components is constant at one byte's level,
+    /// after a pilot chunk at `PILOT_LEVEL`. Chunk-constant vectors are left
+    /// alone by Pfadinvarianz, and a Mean-mode sweep scales the pilot along
+    /// with the payload. The frequency marker is the `set_frequency` tone
+    /// with its chunk means removed, so it lives beside the payload rather
+    /// than on top of it, and the DoubleKick impulses are confined to that
+    /// same zero-mean part. Weight transfer then mixes the whole vector; the
+    /// receiver inverts it exactly (see `spectral_decode`). Little of a tone
+    /// below about π / dimension is left once the chunk means are removed.
+    fn spectral_encode(&mut self, m0: &[u8], target_freq: f64) -> Result<OmegaVector> {
+        // Step 2: Vectorize
+        let mut v = spectral_layout(m0, self.dimension)?;
+        let marker = utils::chunk_shape(&utils::set_frequency(Array1::zeros(v.len()), target_freq)?, self.dimension);
 
-        // Layer 1: Resonance check
-        if !self.is_resonant(&v) {
-            return Ok(None); // Not for us
+        // Step 4: Apply operator sequence
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            return pipeline.forward_chunks(&(v + marker), self.dimension);
         }
 
-        // Convert back to bytes
-        let masked = utils::devectorize(&v)?;
+        // Layer 2: Sweep filtering, harder while frames arrive too fast
+        let mut v2 = self.sweep.transform(&v);
+        self.log_stage(OperatorKind::Sweep, &v, &v2);
+
+        // Layer 3: Path-invariant projection
+        v = self.pfadinvarianz.apply_blocks(&v2);
+        self.log_stage(OperatorKind::Pfadinvarianz, &v2, &v);
+
+        // Step 3: Set resonance frequency (Layer 1)
+        v = v + marker;
+
+        // Layer 4: Multi-scale transfer
+        v2 = self.weight_transfer.transform(&v);
+        self.log_stage(OperatorKind::WeightTransfer, &v, &v2);
 
-        // Layer 0: Unmasking
-        let masking_params = self.derive_masking_params(self.local_frequency);
-        let message = self.masking.unmask(&masked, &masking_params)?;
+        // Layer 5: DoubleKick perturbation, kept off the chunk levels
+        let kick = utils::chunk_shape(&(self.doublekick.try_apply(&v2)? - &v2), self.dimension);
+        v = &v2 + &self.weight_transfer.apply_weights(&kick);
+        self.log_stage(OperatorKind::DoubleKick, &v2, &v);
 
-        Ok(Some(message))
+        Ok(v)
     }
 
-    /// Derive masking parameters from frequency and epoch
-    fn derive_masking_params(&self, omega: f64) -> MaskingParams {
-        MaskingParams::ephemeral_from_frequency(omega, self.epoch)
+    /// Log one application of a vector operator
+    fn log_stage(&self, operator: OperatorKind, input: &OmegaVector, output: &OmegaVector) {
+        self.log.emit(operator, Verbosity::Info, || "applied".to_string());
+        self.log.emit(operator, Verbosity::Debug, || {
+            format!("norm {:.6} -> {:.6}", utils::energy(input).sqrt(), utils::energy(output).sqrt())
+        });
+        self.log.emit(operator, Verbosity::Trace, || format!("output {}", output));
     }
 
-    /// Check if vector is resonant with local frequency
-    fn is_resonant(&self, v: &OmegaVector) -> bool {
-        let v_freq = self.compute_dominant_frequency(v);
-        (v_freq - self.local_frequency).abs() < self.params.resonance.epsilon
+    /// Receive a message if one is resonant with local frequency
+    pub async fn receive_message(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(self.receive_stream_message().await?.map(|m| m.payload))
     }
 
-    /// Compute dominant frequency of vector
-    fn compute_dominant_frequency(&self, v: &OmegaVector) -> f64 {
-        self.resonance.compute_dominant_frequency(v)
+    /// `send_message` for callers without an async runtime (e.g. in the
+    /// browser)
+    ///
+    /// Never blocks: a send that would have to wait (a full bounded
+    /// transport, an exhausted rate limit) fails with a `NetworkError`.
+    #[cfg(feature = "wasm")]
+    pub fn send_message_sync(&mut self, message: &[u8], target: impl Into<SendTarget>) -> Result<()> {
+        let target = target.into();
+        run_without_waiting(self.send_message(message, target))?
     }
 
-    /// Broadcast vector to network (simulated)
-    async fn broadcast(&mut self, v: OmegaVector) -> Result<()> {
-        // In a real implementation, this would send over the network
-        // For simulation, we just store it
-        self.message_buffer.push(v);
-        Ok(())
+    /// `receive_message` for callers without an async runtime; never blocks
+    /// (see `send_message_sync`)
+    #[cfg(feature = "wasm")]
+    pub fn receive_message_sync(&mut self) -> Result<Option<Vec<u8>>> {
+        run_without_waiting(self.receive_message())?
     }
 
-    /// Poll network for messages (simulated)
-    async fn poll_network(&mut self) -> Result<Option<OmegaVector>> {
-        // In a real implementation, this would receive from the network
-        // For simulation, we pop from buffer
-        Ok(self.message_buffer.pop())
+    /// Receive the next in-order message of any stream, tagged with its stream id
+    ///
+    /// Returns `None` when no frame is pending, the frame is not for this node,
+    /// or it arrived ahead of an earlier frame of its stream (it is then held
+    /// and released by a later call once the gap is filled).
+    pub async fn receive_stream_message(&mut self) -> Result<Option<StreamMessage>> {
+        Ok(self.receive_outcome().await?.into_message())
     }
 
-    /// Get the complete OMEGA transformation (composite operator)
-    pub fn omega_transformation(&mut self, v: OmegaVector) -> OmegaVector {
-        // Ω = M̂ ∘ R̂ ∘ Ŝ ∘ P̂ ∘ Ŵ ∘ D̂
-        let mut v1 = self.doublekick.apply(&v);
-        v1 = self.weight_transfer.transform(&v1);
-        let v2 = self.pfadinvarianz.apply(&v1);
-        let v3 = self.sweep.transform(&v2);
-        let v4 = self.resonance.apply(&v3);
-        // Masking operates on bytes, so we skip it in vector composition
-        v4
+    /// Receive every pending message, polling until the network is empty
+    ///
+    /// Messages come back in the order their frames are polled (messages of
+    /// an ordered stream in sequence order). Frames that are not resonant,
+    /// fail authentication, are replayed or do not decode are skipped, as
+    /// are frames held back by their stream; errors of the medium itself
+    /// are returned.
+    pub async fn receive_all(&mut self) -> Result<Vec<Vec<u8>>> {
+        let mut messages = Vec::new();
+        while let Some(message) = self.next_pending_message().await? {
+            messages.push(message);
+        }
+        Ok(messages)
     }
 
-    /// Update epoch (for key rotation)
-    pub fn advance_epoch(&mut self) {
-        self.epoch += 1;
+    /// Next pending message, skipping frames that do not yield one (see
+    /// `receive_all`); `None` once the network is empty
+    pub async fn next_pending_message(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(message) = self.reassembler.next_ready() {
+                return Ok(Some(message.payload));
+            }
+            self.expire_fragments();
+            let Some(frame) = self.poll_network().await? else {
+                self.stats.poll_empty += 1;
+                return Ok(None);
+            };
+            if let Ok(ReceiveOutcome::Message(message)) = self.process_frame(frame) {
+                return Ok(Some(message.payload));
+            }
+        }
     }
 
-    /// Get current state vector
-    pub fn get_state(&self) -> &OmegaVector {
-        &self.state_vector
+    /// Split the node into a sink sending every message to `target` and a
+    /// stream of the messages it receives (see `split`)
+    pub fn into_split(self, target: impl Into<SendTarget>) -> (OmegaSink, OmegaStream) {
+        crate::split::split(self, target.into())
     }
 
-    /// Set local resonance frequency
-    pub fn set_frequency(&mut self, omega: f64) {
-        self.local_frequency = omega;
-        self.resonance = resonance::ResonanceOperator::new(omega);
+    /// Process one receive step and report exactly what happened, telling
+    /// off-frequency traffic (`NotResonant`) apart from wrong-key or tampered
+    /// frames (`AuthFailed`)
+    pub async fn receive_outcome(&mut self) -> Result<ReceiveOutcome> {
+        if let Some(message) = self.reassembler.next_ready() {
+            return Ok(ReceiveOutcome::Message(message));
+        }
+
+        // Algorithm 2: OMEGA Message Reception
+        self.expire_fragments();
+
+        // Poll network
+        match self.poll_network().await? {
+            Some(frame) => self.process_frame(frame),
+            None => {
+                self.stats.poll_empty += 1;
+                Ok(ReceiveOutcome::Empty)
+            }
+        }
     }
 
-    /// Get local frequency
-    pub fn get_frequency(&self) -> f64 {
-        self.local_frequency
+    /// Decode one encoded frame pulled off an external transport (see
+    /// `Frame::to_bytes`), bypassing the node's own medium
+    ///
+    /// The frame goes through the same resonance, replay, authentication and
+    /// stream ordering steps as a polled one. Bytes that are not a frame are
+    /// a `CodecError`.
+    pub fn receive_from_frame(&mut self, bytes: &[u8]) -> Result<ReceiveOutcome> {
+        let frame = Frame::from_bytes(bytes).inspect_err(|_| self.metrics.decode_errors += 1)?;
+        self.expire_fragments();
+        self.process_frame(frame)
     }
 
-    /// Transfer message from this node's buffer to another node's buffer
-    /// (Helper for simulation)
-    pub fn transfer_message_to(&mut self, other: &mut OmegaNode) {
-        if let Some(msg) = self.message_buffer.pop() {
-            other.message_buffer.push(msg);
+    /// `receive_from_frame` for each frame of a batch, in order
+    ///
+    /// Masking keys and FFT plans are cached on the node, so frames of the
+    /// batch sharing a frequency derive them once. One bad frame does not
+    /// stop the others.
+    pub fn receive_from_frames(&mut self, frames: &[Vec<u8>]) -> Vec<Result<ReceiveOutcome>> {
+        frames.iter().map(|bytes| self.receive_from_frame(bytes)).collect()
+    }
+
+    /// Receive the next message round-robin across frequencies, with the
+    /// frequency it arrived on
+    ///
+    /// Every frame pending on the network is decoded first and its message
+    /// queued under the subscribed frequency that authenticated it; each
+    /// call then serves the next frequency in turn that has a message
+    /// waiting, so a backlog on one frequency cannot starve the others.
+    /// A frame that fails to decode is counted in `NodeStats::dropped_decode`
+    /// and skipped, so it cannot hold up the frames behind it. Returns
+    /// `None` once every queue is empty.
+    pub async fn receive_fair(&mut self) -> Result<Option<(f64, Vec<u8>)>> {
+        while let Some(message) = self.reassembler.next_ready() {
+            self.enqueue_fair(message);
+        }
+        while let Some(frame) = self.poll_network().await? {
+            match self.process_frame(frame) {
+                Ok(ReceiveOutcome::Message(message)) => self.enqueue_fair(message),
+                Ok(_) => {}
+                Err(_) => self.stats.dropped_decode += 1,
+            }
+            while let Some(message) = self.reassembler.next_ready() {
+                self.enqueue_fair(message);
+            }
         }
+
+        let n = self.fair_queues.len();
+        for offset in 0..n {
+            let index = (self.fair_next + offset) % n;
+            let (omega, queue) = &mut self.fair_queues[index];
+            if let Some(payload) = queue.pop_front() {
+                self.fair_next = index + 1;
+                return Ok(Some((*omega, payload)));
+            }
+        }
+        self.stats.poll_empty += 1;
+        Ok(None)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn enqueue_fair(&mut self, message: StreamMessage) {
+        match self.fair_queues.iter_mut().find(|(omega, _)| *omega == message.frequency) {
+            Some((_, queue)) => queue.push_back(message.payload),
+            None => self.fair_queues.push((message.frequency, VecDeque::from([message.payload]))),
+        }
+    }
 
-    #[tokio::test]
-    async fn test_send_receive() {
-        let config = NodeConfig {
-            omega: 1.5,
-            params: OmegaParams::default(),
+    /// Steps 2-5 of reception for one frame, requeueing it afterwards if it
+    /// was not for this node and the policy asks for that
+    ///
+    /// A fragment is buffered until its message is complete; the joined
+    /// message is then processed as one frame.
+    fn process_frame(&mut self, frame: Frame) -> Result<ReceiveOutcome> {
+        if self.sweep.max_rate().is_some() {
+            self.sweep.record_arrival(Instant::now());
+        }
+        let frame = match frame.header.is_fragment() {
+            false => frame,
+            true => match self.fragments.accept(frame, Instant::now()) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Ok(ReceiveOutcome::Fragment),
+                Err(e) => {
+                    self.metrics.decode_errors += 1;
+                    return Err(e);
+                }
+            },
+        };
+        let copy = match self.non_resonant_policy {
+            NonResonantPolicy::Requeue => Some(frame.clone()),
+            NonResonantPolicy::Drop => None,
         };
+        let outcome = self.decode_frame(frame)?;
+        if let (
+            Some(frame),
+            ReceiveOutcome::NotResonant | ReceiveOutcome::AuthFailed | ReceiveOutcome::NotAddressed,
+        ) = (copy, &outcome)
+        {
+            self.requeue(frame);
+        }
+        Ok(outcome)
+    }
 
-        let mut sender = OmegaNode::new(config.clone()).unwrap();
-        let mut receiver = OmegaNode::new(config).unwrap();
+    /// Put a frame that is not for this node back into the local buffer,
+    /// unless it was requeued here before
+    fn requeue(&mut self, frame: Frame) {
+        let digest = ReplayGuard::digest(&frame.to_bytes());
+        if self.requeued.remove(&digest) {
+            // Came back around: drop it for good
+            self.requeue_order.retain(|d| *d != digest);
+            return;
+        }
 
-        let message = b"Hello OMEGA!";
-        let target_freq = 1.5;
+        self.requeued.insert(digest);
+        self.requeue_order.push_back(digest);
+        while self.requeue_order.len() > REQUEUE_MEMORY {
+            if let Some(oldest) = self.requeue_order.pop_front() {
+                self.requeued.remove(&oldest);
+            }
+        }
+        // Polled last, after every frame already buffered
+        self.message_buffer.insert(0, frame);
+    }
 
-        // Send message
-        sender.send_message(message, target_freq).await.unwrap();
+    /// Drop fragmented messages that did not complete within the fragment
+    /// timeout, counting them
+    fn expire_fragments(&mut self) {
+        // Without partial messages there is nothing to time, so the clock is
+        // left alone (it is unavailable on wasm32-unknown-unknown)
+        if self.fragments.pending() == 0 {
+            return;
+        }
+        let dropped = self.fragments.expire(Instant::now()) as u64;
+        self.metrics.incomplete_dropped += dropped;
+        self.stats.dropped_incomplete += dropped;
+    }
 
-        // Transfer message from sender to receiver (simulated network)
-        sender.transfer_message_to(&mut receiver);
+    /// Split outgoing messages whose frame vector has more than `max_len`
+    /// components into several frames; `None` sends every message whole
+    pub fn set_fragment_size(&mut self, max_len: Option<usize>) -> Result<()> {
+        if max_len == Some(0) {
+            return Err(OmegaError::ParameterError("fragment size must be positive".to_string()));
+        }
+        self.fragment_size = max_len;
+        Ok(())
+    }
 
-        // Receive message
-        let received = receiver.receive_message().await.unwrap();
+    pub fn fragment_size(&self) -> Option<usize> {
+        self.fragment_size
+    }
 
-        // Should receive the message
-        assert!(received.is_some());
+    /// Drop a fragmented message still incomplete `timeout` after its first
+    /// fragment arrived (see `NodeStats::dropped_incomplete`)
+    pub fn set_fragment_timeout(&mut self, timeout: Duration) {
+        self.fragments.set_timeout(timeout);
     }
 
-    #[tokio::test]
-    async fn test_frequency_filtering() {
-        let config1 = NodeConfig {
-            omega: 1.0,
-            params: OmegaParams::default(),
-        };
-        let config2 = NodeConfig {
-            omega: 2.0,
-            params: OmegaParams::default(),
-        };
+    pub fn fragment_timeout(&self) -> Duration {
+        self.fragments.timeout()
+    }
 
-        let mut sender = OmegaNode::new(config1).unwrap();
-        let mut receiver = OmegaNode::new(config2).unwrap();
+    /// What to do with polled frames that are not for this node
+    pub fn set_non_resonant_policy(&mut self, policy: NonResonantPolicy) {
+        self.non_resonant_policy = policy;
+    }
 
-        let message = b"Not for you";
-        let target_freq = 1.0; // Different from receiver's frequency
+    pub fn non_resonant_policy(&self) -> NonResonantPolicy {
+        self.non_resonant_policy
+    }
 
-        // Send message at freq 1.0
-        sender.send_message(message, target_freq).await.unwrap();
+    fn decode_frame(&mut self, frame: Frame) -> Result<ReceiveOutcome> {
+        let Frame { header, vector: v_received } = frame;
+        let plaintext = header.masking_mode == PLAINTEXT_MASKING_CODE;
+        if plaintext && !self.debug_plaintext {
+            self.metrics.decode_errors += 1;
+            return Err(OmegaError::CodecError(
+                "frame: unmasked debug frame refused outside debug plaintext mode".to_string(),
+            ));
+        }
+        let masking_mode = match plaintext {
+            true => MaskingMode::default(),
+            false => MaskingMode::from_code(header.masking_mode)
+                .inspect_err(|_| self.metrics.decode_errors += 1)?,
+        };
+        let compression = Compression::from_code(header.compression)
+            .inspect_err(|_| self.metrics.decode_errors += 1)?;
+
+        // Convert back to bytes, noting which subscribed frequencies may have keyed the frame
+        let (masked, key_frequencies) = if self.lossless {
+            // Exact unpacking; no spectral layers and no resonance filtering,
+            // so every subscription is a candidate and authentication decides
+            let subscriptions = self.subscriptions();
+            if subscriptions.is_empty() {
+                self.metrics.resonance_rejected += 1;
+                self.stats.dropped_frequency += 1;
+                return Ok(ReceiveOutcome::NotResonant);
+            }
+            self.record_audit(AuditOperation::Receive, self.local_frequency, &v_received);
+            (utils::unpack_bytes(&v_received), subscriptions)
+        } else {
+            match self.spectral_decode(&v_received).inspect_err(|_| self.metrics.decode_errors += 1)? {
+                Some((v, omega)) => {
+                    self.record_audit(AuditOperation::Receive, omega, &v_received);
+                    (utils::devectorize(&v), vec![omega])
+                }
+                None => {
+                    self.metrics.resonance_rejected += 1;
+                    self.stats.dropped_frequency += 1;
+                    return Ok(ReceiveOutcome::NotResonant); // Not for us
+                }
+            }
+        };
+
+        // Replay protection: stale epochs, then frames accepted before
+        if let Some(kind) = self.replay_guard.check_epoch(header.epoch, self.epoch) {
+            self.metrics.replays_rejected += 1;
+            return Ok(ReceiveOutcome::Replayed(kind));
+        }
+        let masked = match masked {
+            Ok(masked) => masked,
+            Err(e) => {
+                self.metrics.decode_errors += 1;
+                return Err(e);
+            }
+        };
+        let digest = ReplayGuard::digest(&masked);
+        if let Some(kind) = self.replay_guard.check(&digest, &key_frequencies) {
+            self.metrics.replays_rejected += 1;
+            return Ok(ReceiveOutcome::Replayed(kind));
+        }
+
+        // Layer 0: Unmasking (searching neighbouring frequency bins if enabled)
+        let decoded = match plaintext {
+            true => Ok((masked, key_frequencies[0])),
+            false => self.unmask_with_search(&masked, &key_frequencies, masking_mode, &header),
+        };
+        let (records, omega) = match decoded {
+            Ok((message, omega)) => {
+                self.replay_guard.record(digest, omega);
+                let message = compression::decompress(&message, compression)
+                    .inspect_err(|_| self.metrics.decode_errors += 1)?;
+                let message = match header.addressed {
+                    false => message,
+                    true => {
+                        let Some((destination, rest)) = message.split_first_chunk::<{ NodeId::LEN }>() else {
+                            self.metrics.decode_errors += 1;
+                            return Err(OmegaError::CodecError(
+                                "frame: payload shorter than its destination".to_string(),
+                            ));
+                        };
+                        if !self.accepts_destination(NodeId(*destination)) {
+                            self.stats.dropped_address += 1;
+                            return Ok(ReceiveOutcome::NotAddressed);
+                        }
+                        rest.to_vec()
+                    }
+                };
+                let records = match header.record_count {
+                    0 => vec![message],
+                    count => frame::unpack_records(&message, count)
+                        .inspect_err(|_| self.metrics.decode_errors += 1)?,
+                };
+                (records, omega)
+            }
+            Err(e) if e.is_authentication_failure() => {
+                // Tampered, or masked for someone else: not for us
+                self.metrics.auth_failures += 1;
+                self.stats.dropped_auth += 1;
+                return Ok(ReceiveOutcome::AuthFailed);
+            }
+            Err(e) => {
+                self.metrics.decode_errors += 1;
+                return Err(e);
+            }
+        };
+        self.metrics.messages_received += 1;
+        self.stats.received += 1;
+
+        for record in records {
+            self.reassembler.accept(header, omega, record);
+        }
+        Ok(match self.reassembler.next_ready() {
+            Some(message) => ReceiveOutcome::Message(message),
+            None => ReceiveOutcome::Held,
+        })
+    }
+
+    /// Undo the operator sequence and check resonance, returning the
+    /// vectorized bytes with the subscribed frequency they matched; `None`
+    /// if the frame is not for this node
+    ///
+    /// Inverts `spectral_encode`: the weight transfer is undone with this
+    /// node's own weights (or the custom pipeline with its own inverse,
+    /// chunk by chunk), the chunk means are the levels and the rest is
+    /// the marker with the kicks. Only the marker part is checked for
+    /// resonance, so the payload cannot pull the dominant frequency away.
+    /// While frames arrive faster than the sweep's `max_rate`, the levels
+    /// are attenuated by `Sweep::rate_attenuation` once the pilot has
+    /// normalised them, so a burst does not decode. A frame whose length the weight transfer or pipeline cannot invert,
+    /// or whose pilot is not positive, is a decode error.
+    fn spectral_decode(&mut self, v_received: &OmegaVector) -> Result<Option<(OmegaVector, f64)>> {
+        let v = match self.pipeline.as_mut() {
+            // A custom pipeline is undone chunk by chunk
+            Some(pipeline) => pipeline.inverse_chunks(v_received, self.dimension).map_err(|e| {
+                OmegaError::VectorizationError(format!("frame: {}", e))
+            })?,
+            None => {
+                // Layer 5 and 4: the kicks stay in the marker part; weight transfer is inverted exactly
+                let v = self.weight_transfer.invert(v_received).map_err(|e| {
+                    OmegaError::VectorizationError(format!("frame: {}", e))
+                })?;
+                self.log_stage(OperatorKind::WeightTransfer, v_received, &v);
+                v
+            }
+        };
+
+        // Layer 3 and 2: chunk-constant levels, scaled by the sweep gate
+        let levels = utils::chunk_means(&v, self.dimension);
+        let marker = utils::chunk_shape(&v, self.dimension);
+
+        // Layer 1: Resonance check against every subscribed frequency
+        let matched = self.resonance.resonant_frequency(&marker);
+        self.log.emit(OperatorKind::Resonance, Verbosity::Info, || {
+            format!("resonant: {}", matched.is_some())
+        });
+        self.log.emit(OperatorKind::Resonance, Verbosity::Debug, || {
+            format!("dominant frequency {:.6}", self.resonance.compute_dominant_frequency(&marker))
+        });
+        let Some(omega) = matched else { return Ok(None) };
+
+        // Undo the sweep gate with the pilot
+        let pilot = levels.first().copied().unwrap_or(0.0);
+        if !(pilot.is_finite() && pilot > 0.0) {
+            return Err(OmegaError::VectorizationError(format!(
+                "frame: pilot level {} cannot be inverted", pilot
+            )));
+        }
+        let mut payload = levels.slice(ndarray::s![1..]).mapv(|x| x * PILOT_LEVEL / pilot);
+
+        // Layer 2 on arrival: a burst above the sweep's max_rate attenuates
+        // the normalised levels, which no longer decode to the sent bytes
+        let attenuation = self.sweep.rate_attenuation(payload.mean().unwrap_or(0.0));
+        if attenuation < 1.0 {
+            self.log.emit(OperatorKind::Sweep, Verbosity::Info, || {
+                format!("arrival rate {:.1}/s attenuates by {:.6}", self.sweep.observed_rate(), attenuation)
+            });
+            payload.mapv_inplace(|x| x * attenuation);
+        }
+        Ok(Some((payload, omega)))
+    }
+
+    /// Unmask with keys derived from each candidate frequency in turn,
+    /// accepting the first candidate that unmasks successfully; returns the
+    /// message with the frequency whose key unmasked it
+    ///
+    /// Keys of the local epoch are tried first, then those of the epochs
+    /// within `epoch_window` at increasing distance.
+    fn unmask_with_search(
+        &mut self,
+        masked: &[u8],
+        frequencies: &[f64],
+        mode: MaskingMode,
+        header: &FrameHeader,
+    ) -> Result<(Vec<u8>, f64)> {
+        let operator = masking::MaskingOperator::for_mode(mode);
+        let mut last_error = None;
+        let candidates: Vec<f64> = frequencies
+            .iter()
+            .flat_map(|&omega| self.candidate_frequencies(omega))
+            .collect();
+
+        for epoch in self.candidate_epochs() {
+            for &omega in &candidates {
+                let mut masking_params = self.key_cache.get(omega, epoch);
+                mode.apply(&mut masking_params);
+                masking_params.counter = header.counter;
+                masking_params.nonce = header.nonce;
+                match operator.unmask_with_aad(masked, &masking_params, &header.associated_data()) {
+                    Ok(message) => {
+                        self.log.emit(OperatorKind::Masking, Verbosity::Info, || {
+                            format!(
+                                "unmasked {} bytes with key for {:.6} at epoch {}",
+                                message.len(),
+                                omega,
+                                epoch
+                            )
+                        });
+                        return Ok((message, omega));
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            OmegaError::MaskingError("No candidate frequency to unmask with".to_string())
+        }))
+    }
+
+    /// Candidate key frequencies around `omega`: the exact bin first, then
+    /// neighbouring bins at increasing distance, staying within epsilon
+    fn candidate_frequencies(&self, omega: f64) -> Vec<f64> {
+        let center = quantize_frequency(omega);
+        let mut candidates = vec![omega];
+
+        for offset in 1..=self.freq_search_window as i64 {
+            for bin in [center - offset, center + offset] {
+                let candidate = bin as f64 * FREQUENCY_QUANTUM;
+                if (candidate - omega).abs() < self.resonance.epsilon() {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        candidates
+    }
+
+    /// Candidate key epochs: the local epoch first, then neighbouring epochs
+    /// at increasing distance within `epoch_window`
+    fn candidate_epochs(&self) -> Vec<u64> {
+        let mut epochs = vec![self.epoch];
+        for offset in 1..=self.epoch_window {
+            epochs.extend(self.epoch.checked_sub(offset));
+            epochs.extend(self.epoch.checked_add(offset));
+        }
+        epochs
+    }
+
+    /// Try unmasking with keys from up to `n_bins` neighbouring frequency bins
+    /// on each side of the local frequency (tolerates frequency quantization mismatch)
+    pub fn set_freq_search_window(&mut self, n_bins: usize) {
+        self.freq_search_window = n_bins;
+    }
+
+    /// Derive masking parameters from frequency and epoch (cached)
+    fn derive_masking_params(&mut self, omega: f64) -> MaskingParams {
+        self.key_cache.get(omega, self.epoch)
+    }
+
+    /// Claim the next nonce counter for the key of `omega` at the current epoch
+    ///
+    /// Counters are tracked per key on this node; once all 2^32 are used the
+    /// key refuses to encrypt further until the epoch advances.
+    fn next_nonce_counter(&mut self, omega: f64) -> Result<u32> {
+        let next = self
+            .nonce_counters
+            .entry((quantize_frequency(omega), self.epoch))
+            .or_insert(0);
+        let counter = u32::try_from(*next)
+            .map_err(|_| OmegaError::MaskingError(NONCE_REUSE.to_string()))?;
+        *next += 1;
+        Ok(counter)
+    }
+
+    /// Replace the replay protection policy, forgetting remembered frames
+    pub fn set_replay_policy(&mut self, policy: ReplayPolicy) {
+        self.replay_guard = ReplayGuard::new(policy);
+    }
+
+    /// Number of masking key derivations performed (key cache misses)
+    pub fn key_derivations(&self) -> u64 {
+        self.key_cache.derivations()
+    }
+
+    /// Check if vector is resonant with any subscribed frequency
+    pub fn is_resonant(&self, v: &OmegaVector) -> bool {
+        self.resonance.is_resonant(v)
+    }
+
+    /// Also accept frames on `omega`, with the configured bandwidth;
+    /// frames are unmasked with keys for whichever subscription they match
+    pub fn subscribe(&mut self, omega: f64) {
+        if !self.subscriptions().contains(&omega) {
+            self.resonance.add_listen_frequency(omega);
+        }
+    }
+
+    /// Stop accepting frames on `omega`; returns whether it was subscribed
+    pub fn unsubscribe(&mut self, omega: f64) -> bool {
+        self.resonance.remove_listen_frequency(omega)
+    }
+
+    /// Frequencies this node currently accepts frames on
+    pub fn subscriptions(&self) -> Vec<f64> {
+        self.resonance.listen_frequencies().iter().map(|&(omega, _)| omega).collect()
+    }
+
+    /// Additionally accept frames resonant with `omega`, within bandwidth `epsilon`
+    pub fn add_listen_frequency_with_epsilon(&mut self, omega: f64, epsilon: f64) {
+        self.resonance.add_listen_frequency_with_epsilon(omega, epsilon);
+    }
+
+    /// Broadcast frame to network: a copy to every registered peer, then the
+    /// transport; with neither, the frame stays in the local buffer (simulated)
+    ///
+    /// A peer that cannot be reached is logged and skipped; the others still
+    /// receive the frame.
+    async fn broadcast(&mut self, frame: Frame) -> Result<()> {
+        if self.peers.is_empty() && self.transport.is_none() {
+            // Simulated medium: keep the frame in the local buffer
+            self.message_buffer.push(frame);
+            return Ok(());
+        }
+
+        for (&peer_id, peer) in &self.peers {
+            if let Err(e) = peer.deliver(&frame).await {
+                tracing::warn!(peer_id, error = %e, "skipping unreachable peer");
+            }
+        }
+        match &self.transport {
+            Some(transport) => transport.send(&frame.to_bytes()).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Poll network for messages; frames placed in the local buffer (e.g. by
+    /// `transfer_message_to`) come first, then frames from connected nodes,
+    /// then the transport if one is set
+    async fn poll_network(&mut self) -> Result<Option<Frame>> {
+        if let Some(frame) = self.message_buffer.pop() {
+            return Ok(Some(frame));
+        }
+        if let Ok(frame) = self.link_rx.try_recv() {
+            return Ok(Some(frame));
+        }
+        let Some(transport) = &self.transport else {
+            return Ok(None);
+        };
+        match transport.recv().await {
+            Some(bytes) => Frame::from_bytes(&bytes)
+                .map(Some)
+                .inspect_err(|_| self.metrics.decode_errors += 1),
+            None => Ok(None),
+        }
+    }
+
+    /// `Ready` once a frame waits to be received; otherwise `cx` is woken
+    /// when one arrives from a connected node or the transport
+    ///
+    /// A frame taken off the transport here is moved to the local buffer,
+    /// or counted in `decode_errors` if it does not decode.
+    pub(crate) fn poll_arrival(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.message_buffer.is_empty() {
+            return Poll::Ready(());
+        }
+        if let Poll::Ready(Some(frame)) = self.link_rx.poll_recv(cx) {
+            self.message_buffer.push(frame);
+            return Poll::Ready(());
+        }
+        let Some(transport) = &self.transport else {
+            return Poll::Pending;
+        };
+        let bytes = ready!(transport.poll_recv(cx));
+        match Frame::from_bytes(&bytes) {
+            Ok(frame) => self.message_buffer.push(frame),
+            Err(_) => self.metrics.decode_errors += 1,
+        }
+        Poll::Ready(())
+    }
+
+    /// Broadcast and poll through `transport` instead of the simulated buffer
+    pub fn set_transport(&mut self, transport: Box<dyn Transport>) {
+        self.transport = Some(transport);
+    }
+
+    /// Return to the simulated buffer, handing back the transport
+    pub fn clear_transport(&mut self) -> Option<Box<dyn Transport>> {
+        self.transport.take()
+    }
+
+    /// Register a peer that receives every broadcast from now on, replacing
+    /// any peer registered under the same id
+    pub fn add_peer(&mut self, peer_id: PeerId, handle: PeerHandle) -> Option<PeerHandle> {
+        self.peers.insert(peer_id, handle)
+    }
+
+    /// Stop broadcasting to a peer
+    pub fn remove_peer(&mut self, peer_id: PeerId) -> Option<PeerHandle> {
+        self.peers.remove(&peer_id)
+    }
+
+    /// Number of registered peers
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Handle delivering frames into this node's inbox, read by the next
+    /// receive calls
+    pub fn link(&self) -> PeerHandle {
+        PeerHandle::Channel(self.link_tx.clone())
+    }
+
+    /// Id under which `connect` registers this node with its peers
+    pub fn link_id(&self) -> PeerId {
+        self.link_id
+    }
+
+    /// Join this node and `other` by in-process channels
+    ///
+    /// Each node registers the other's `link` under its `link_id`, so every
+    /// frame either node broadcasts is delivered to the other (and to all
+    /// its other peers) instead of staying in the local buffer. Connecting
+    /// the same pair twice has no further effect.
+    pub fn connect(&mut self, other: &mut OmegaNode) {
+        self.add_peer(other.link_id, other.link());
+        other.add_peer(self.link_id, self.link());
+    }
+
+    /// Get the complete OMEGA transformation (composite operator), or the
+    /// custom pipeline if one is set
+    ///
+    /// Fails only if a custom pipeline cannot take `v`.
+    pub fn omega_transformation(&mut self, v: OmegaVector) -> Result<OmegaVector> {
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            return pipeline.apply_forward(&v);
+        }
+
+        // Ω = M̂ ∘ R̂ ∘ Ŝ ∘ P̂ ∘ Ŵ ∘ D̂
+        let mut v1 = self.doublekick.apply(&v);
+        v1 = self.weight_transfer.transform(&v1);
+        let v2 = self.pfadinvarianz.apply(&v1);
+        let v3 = self.sweep.transform(&v2);
+        let v4 = self.resonance.apply(&v3);
+        // Masking operates on bytes, so we skip it in vector composition
+        Ok(v4)
+    }
+
+    /// Lipschitz constant of Ω as currently configured: the product of the
+    /// operators' constants in composition order, with the custom pipeline
+    /// (if set) in place of the built-in sequence
+    ///
+    /// Tracks the live DoubleKick, so retuning its alphas changes the bound.
+    pub fn composite_lipschitz(&self) -> f64 {
+        let stack = match &self.pipeline {
+            Some(pipeline) => pipeline.composite_lipschitz(),
+            None => [
+                OmegaOperator::lipschitz_constant(&self.doublekick),
+                OmegaOperator::lipschitz_constant(&self.weight_transfer),
+                OmegaOperator::lipschitz_constant(&self.pfadinvarianz),
+                OmegaOperator::lipschitz_constant(&self.sweep),
+            ]
+            .iter()
+            .product(),
+        };
+        stack * OmegaOperator::lipschitz_constant(&self.resonance) * OmegaOperator::lipschitz_constant(&self.masking)
+    }
+
+    /// Whether Ω is a contraction (`composite_lipschitz` below 1)
+    pub fn is_contractive(&self) -> bool {
+        self.composite_lipschitz() < 1.0
+    }
+
+    /// Gate outcomes of the send-path sweep (see `Sweep::rejection_stats`)
+    pub fn sweep_stats(&self) -> sweep::SweepStats {
+        self.sweep.rejection_stats()
+    }
+
+    /// Self-test: encode `sample` to the local frequency with the full send
+    /// path and decode it again, failing unless the exact bytes come back
+    ///
+    /// Runs on a `branch` of this node under its own parameters, so no frame
+    /// is broadcast and the node's buffers, counters and nonce state are left
+    /// alone; only a custom pipeline, borrowed for the test, may advance its
+    /// stages. A frame that does not come back as a message, or comes back
+    /// altered, is a `ParameterError` (e.g. a lossy configuration); errors
+    /// while encoding or decoding are returned as they are.
+    pub fn verify_round_trip(&mut self, sample: &[u8]) -> Result<()> {
+        let mut probe = self.branch(self.params.clone())?;
+        probe.pipeline = self.pipeline.take();
+        let outcome = probe
+            .encode_frame(DEFAULT_STREAM, self.node_id, sample, 0, self.local_frequency)
+            .and_then(|frame| probe.decode_frame(frame));
+        self.pipeline = probe.pipeline.take();
+
+        match outcome? {
+            ReceiveOutcome::Message(message) if message.payload == sample => Ok(()),
+            ReceiveOutcome::Message(_) => Err(OmegaError::ParameterError(
+                "round trip self-test: decoded message differs from the sample".to_string(),
+            )),
+            outcome => Err(OmegaError::ParameterError(format!(
+                "round trip self-test: sample came back as {:?}",
+                outcome
+            ))),
+        }
+    }
+
+    /// Replace the built-in sweep → pfadinvarianz → weight transfer →
+    /// DoubleKick sequence with `pipeline`
+    ///
+    /// The pipeline runs on each chunk of `dimension` components, so it must
+    /// map that many components to as many; otherwise this is a
+    /// `ParameterError` and the current sequence stays. Reception undoes the
+    /// pipeline chunk by chunk with `OmegaPipeline::apply_inverse`, so peers
+    /// must install the same pipeline and every stage must be invertible.
+    /// Forks and branches start with the built-in sequence.
+    pub fn set_pipeline(&mut self, pipeline: OmegaPipeline) -> Result<()> {
+        let output = pipeline.output_dim(self.dimension)?;
+        if output != self.dimension {
+            return Err(OmegaError::ParameterError(format!(
+                "Pipeline maps {}-component chunks to {} components",
+                self.dimension, output
+            )));
+        }
+        self.pipeline = Some(pipeline);
+        Ok(())
+    }
+
+    /// Return to the built-in operator sequence
+    pub fn clear_pipeline(&mut self) -> Option<OmegaPipeline> {
+        self.pipeline.take()
+    }
+
+    /// Limit sends to `msgs_per_sec`, allowing bursts of up to one second's worth
+    pub fn set_send_rate_limit(&mut self, msgs_per_sec: f64) -> Result<()> {
+        self.send_limiter = Some(TokenBucket::new(msgs_per_sec, msgs_per_sec.max(1.0))?);
+        Ok(())
+    }
+
+    /// Remove the send rate limit
+    pub fn clear_send_rate_limit(&mut self) {
+        self.send_limiter = None;
+    }
+
+    /// Replace the per-operator logging verbosity
+    pub fn set_log_config(&mut self, log: LogConfig) {
+        self.log = log;
+    }
+
+    /// Traffic counters
+    pub fn metrics(&self) -> &NodeMetrics {
+        &self.metrics
+    }
+
+    /// Message statistics since creation or the last `reset_stats`
+    pub fn stats(&self) -> &NodeStats {
+        &self.stats
+    }
+
+    /// Zero the message statistics (metrics are unaffected)
+    pub fn reset_stats(&mut self) {
+        self.stats = NodeStats::new();
+    }
+
+    /// Traffic counters in the Prometheus text exposition format
+    pub fn metrics_prometheus(&self) -> String {
+        self.metrics.to_prometheus()
+    }
+
+    /// Estimate the probability that a message sent at this node's frequency
+    /// decodes after additive white Gaussian noise of `noise_stddev`
+    ///
+    /// Runs `trials` send → noise → receive round trips between two forks of
+    /// this node (same configuration, epoch and subscriptions) and returns
+    /// the empirical success rate. Messages and noise are seeded, but the
+    /// masking nonces are not, so repeated estimates may differ slightly;
+    /// `trials == 0` yields 0.0.
+    pub async fn estimate_decode_probability(&self, noise_stddev: f64, trials: usize) -> f64 {
+        use rand::{Rng, SeedableRng};
+
+        if trials == 0 {
+            return 0.0;
+        }
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut sender = self.fork_with_seed(rng.gen());
+        let mut receiver = self.fork_with_seed(rng.gen());
+        let mut successes = 0;
+
+        for _ in 0..trials {
+            let message: Vec<u8> = (0..16).map(|_| rng.gen()).collect();
+            if sender.send_message(&message, self.local_frequency).await.is_err() {
+                continue;
+            }
+            let Some(mut frame) = sender.take_frame() else { continue };
+            frame.vector = utils::add_gaussian_noise(&frame.vector, noise_stddev, &mut rng);
+            receiver.push_frame(frame);
+
+            if let Ok(Some(received)) = receiver.receive_message().await {
+                if received == message {
+                    successes += 1;
+                }
+            }
+        }
+
+        successes as f64 / trials as f64
+    }
+
+    /// Descriptor advertised to peers during a handshake
+    pub fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor {
+            frequency: self.local_frequency,
+            epoch: self.epoch,
+            capabilities: Capabilities::SUPPORTED,
+        }
+    }
+
+    /// Handshake with a peer over the transport: broadcast this node's
+    /// descriptor frame, wait up to `timeout` for the peer's, agree on a
+    /// common frequency, epoch and masking mode and apply the result
+    ///
+    /// The peer runs `negotiate` at the same time on the same medium and
+    /// reaches the same session. Regular frames arriving meanwhile are kept
+    /// for `receive_message`. Fails with a `NetworkError` (changing nothing)
+    /// without a transport or if no descriptor arrives in time, and with the
+    /// `negotiate_capabilities` error if the peers share no masking mode.
+    /// Without the `native` timer the medium is polled until it is empty
+    /// instead of waiting.
+    pub async fn negotiate(&mut self, timeout: Duration) -> Result<NegotiatedSession> {
+        if self.transport.is_none() {
+            return Err(OmegaError::NetworkError("negotiation needs a transport".to_string()));
+        }
+        let local = self.descriptor();
+        let token: u64 = rand::random();
+        let hello = [&token.to_le_bytes()[..], &local.to_bytes()?].concat();
+
+        #[cfg(feature = "native")]
+        let remote = tokio::time::timeout(timeout, self.exchange_descriptors(&hello))
+            .await
+            .map_err(|_| OmegaError::NetworkError("negotiation timed out".to_string()))??;
+        #[cfg(not(feature = "native"))]
+        let remote = {
+            let _ = timeout;
+            self.exchange_descriptors(&hello).await?
+        };
+
+        let session = NegotiatedSession::agree(&local, &remote)?;
+        self.apply_session(&session);
+        Ok(session)
+    }
+
+    /// Send `hello` (a random token ahead of our descriptor) and return the
+    /// first descriptor another node sends back
+    ///
+    /// On a shared bus the node may pull its own hello; it is put back for
+    /// the peer. Anything that is not a descriptor is buffered as a frame.
+    async fn exchange_descriptors(&mut self, hello: &[u8]) -> Result<NodeDescriptor> {
+        let Some(transport) = self.transport.as_deref() else {
+            return Err(OmegaError::NetworkError("negotiation needs a transport".to_string()));
+        };
+        transport.send(hello).await?;
+
+        // Whether the last poll returned our own hello
+        let mut echoed = false;
+        loop {
+            match transport.recv().await {
+                Some(bytes) if bytes == hello => {
+                    transport.send(hello).await?;
+                    if !std::mem::replace(&mut echoed, true) {
+                        continue;
+                    }
+                }
+                Some(bytes) => {
+                    echoed = false;
+                    if let Some(remote) = bytes.get(8..).and_then(|json| NodeDescriptor::from_bytes(json).ok()) {
+                        return Ok(remote);
+                    }
+                    // Behind any frames already buffered, in arrival order
+                    match Frame::from_bytes(&bytes) {
+                        Ok(frame) => self.message_buffer.insert(0, frame),
+                        Err(_) => self.metrics.decode_errors += 1,
+                    }
+                    continue;
+                }
+                None => echoed = false,
+            }
+            Self::negotiation_pause().await?;
+        }
+    }
+
+    /// Wait before polling the medium again during a negotiation
+    #[cfg(feature = "native")]
+    async fn negotiation_pause() -> Result<()> {
+        tokio::time::sleep(NEGOTIATION_POLL_INTERVAL).await;
+        Ok(())
+    }
+
+    /// Without a timer to wait on, a drained medium ends the negotiation
+    #[cfg(not(feature = "native"))]
+    async fn negotiation_pause() -> Result<()> {
+        Err(OmegaError::NetworkError("no descriptor from a peer".to_string()))
+    }
+
+    fn apply_session(&mut self, session: &NegotiatedSession) {
+        self.set_frequency(session.frequency);
+        self.set_epoch(session.epoch);
+        self.masking_mode = session.masking_mode;
+    }
+
+    /// Mask outgoing frames with `mode`; received frames are unmasked with
+    /// whichever mode their header names
+    pub fn set_masking_mode(&mut self, mode: MaskingMode) {
+        self.masking_mode = mode;
+    }
+
+    /// Masking mode of outgoing frames
+    pub fn masking_mode(&self) -> MaskingMode {
+        self.masking_mode
+    }
+
+    /// INSECURE: send frames unmasked and uncompressed, and accept such
+    /// frames, so the vectorized payload can be inspected on the wire while
+    /// developing the spectral operators
+    ///
+    /// Frames carry `PLAINTEXT_MASKING_CODE` in place of a masking mode and
+    /// are refused by nodes not in this mode. Only compiled into debug
+    /// builds, or release builds with the `insecure-plaintext` feature.
+    #[cfg(any(debug_assertions, feature = "insecure-plaintext"))]
+    pub fn set_debug_plaintext_insecure(&mut self, enabled: bool) {
+        if enabled {
+            tracing::warn!("debug plaintext mode: frames are sent and accepted unmasked");
+        }
+        self.debug_plaintext = enabled;
+    }
+
+    /// Whether frames are sent and accepted unmasked
+    pub fn debug_plaintext(&self) -> bool {
+        self.debug_plaintext
+    }
+
+    /// Compress outgoing payloads with `compression` before masking;
+    /// payloads it would not shrink are sent uncompressed. Received frames
+    /// are decompressed according to their header.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Compression requested for outgoing payloads
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Adopt the epoch advertised in a peer's descriptor frame (one-way,
+    /// unlike `negotiate`; frequency is left unchanged)
+    pub fn sync_from_beacon(&mut self, beacon: &[u8]) -> Result<()> {
+        let descriptor = NodeDescriptor::from_bytes(beacon)?;
+        self.set_epoch(descriptor.epoch);
+        Ok(())
+    }
+
+    /// Start recording sends and receives in a hash-chained audit log
+    pub fn enable_audit(&mut self) {
+        if self.audit.is_none() {
+            self.audit = Some(AuditLog::new());
+        }
+    }
+
+    /// Audit entries recorded so far (empty if auditing is disabled)
+    pub fn audit_entries(&self) -> &[AuditEntry] {
+        match &self.audit {
+            Some(log) => log.entries(),
+            None => &[],
+        }
+    }
+
+    fn record_audit(&mut self, operation: AuditOperation, frequency: f64, frame: &OmegaVector) {
+        let epoch = self.epoch;
+        if let Some(log) = self.audit.as_mut() {
+            log.record(operation, frequency, epoch, frame);
+        }
+    }
+
+    /// Update epoch (for key rotation)
+    pub fn advance_epoch(&mut self) {
+        self.set_epoch(self.epoch + 1);
+    }
+
+    /// Place the node at an arbitrary key epoch (e.g. to simulate clock drift)
+    pub fn set_epoch(&mut self, epoch: u64) {
+        self.epoch = epoch;
+        self.key_cache.clear();
+        self.update_hop_subscriptions();
+    }
+
+    /// Hop with `hopper` (None = stop hopping)
+    ///
+    /// `send_message(.., HopTarget)` then sends on the hop frequency of the
+    /// current epoch, and the node listens on the hop frequencies of every
+    /// epoch within `epoch_window`, following each epoch change. Nodes
+    /// sharing the hopper's seed and frequency set meet on every epoch.
+    pub fn set_frequency_hopper(&mut self, hopper: Option<FrequencyHopper>) {
+        self.hopper = hopper;
+        self.update_hop_subscriptions();
+    }
+
+    pub fn frequency_hopper(&self) -> Option<&FrequencyHopper> {
+        self.hopper.as_ref()
+    }
+
+    /// Hop frequency of the current epoch, if hopping
+    pub fn hop_frequency(&self) -> Option<f64> {
+        self.hopper.as_ref().map(|hopper| hopper.frequency_at(self.epoch))
+    }
+
+    /// Listen on the hop frequencies around the current epoch, dropping
+    /// those only earlier epochs needed; subscriptions made explicitly are
+    /// left alone
+    fn update_hop_subscriptions(&mut self) {
+        let wanted: Vec<f64> = match &self.hopper {
+            Some(hopper) => self.candidate_epochs().into_iter().map(|epoch| hopper.frequency_at(epoch)).collect(),
+            None => Vec::new(),
+        };
+        for omega in std::mem::take(&mut self.hop_subscriptions) {
+            if wanted.contains(&omega) {
+                self.hop_subscriptions.push(omega);
+            } else {
+                self.unsubscribe(omega);
+            }
+        }
+        for omega in wanted {
+            if !self.subscriptions().contains(&omega) {
+                self.subscribe(omega);
+                self.hop_subscriptions.push(omega);
+            }
+        }
+    }
+
+    /// Current key epoch
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Parameters the node was configured with
+    pub fn params(&self) -> &OmegaParams {
+        &self.params
+    }
+
+    /// Get current state vector
+    pub fn get_state(&self) -> &OmegaVector {
+        &self.state_vector
+    }
+
+    /// Set local resonance frequency
+    pub fn set_frequency(&mut self, omega: f64) {
+        self.local_frequency = omega;
+        self.resonance.set_frequency(omega);
+    }
+
+    /// Get local frequency
+    pub fn get_frequency(&self) -> f64 {
+        self.local_frequency
+    }
+
+    /// Components per chunk of this node's vector space
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Take the most recently buffered frame (simulation helper)
+    pub(crate) fn take_frame(&mut self) -> Option<Frame> {
+        self.message_buffer.pop()
+    }
+
+    /// Deliver a frame into this node's buffer (simulation helper)
+    pub(crate) fn push_frame(&mut self, frame: Frame) {
+        self.message_buffer.push(frame);
+    }
+
+    /// Transfer message from this node's buffer to another node's buffer
+    /// (Helper for simulation)
+    pub fn transfer_message_to(&mut self, other: &mut OmegaNode) {
+        if let Some(msg) = self.message_buffer.pop() {
+            other.message_buffer.push(msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::ReplayKind;
+
+    #[tokio::test]
+    async fn test_send_receive() {
+        let config = NodeConfig {
+            omega: 1.5,
+            ..Default::default()
+        };
+
+        let mut sender = OmegaNode::new(config.clone()).unwrap();
+        let mut receiver = OmegaNode::new(config).unwrap();
+
+        let message = b"Hello OMEGA!";
+        let target_freq = 1.5;
+
+        // Send message
+        sender.send_message(message, target_freq).await.unwrap();
+
+        // Transfer message from sender to receiver (simulated network)
+        sender.transfer_message_to(&mut receiver);
+
+        // Receive message
+        let received = receiver.receive_message().await.unwrap();
+
+        // Should receive the message
+        assert!(received.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_frequency_filtering() {
+        let config1 = NodeConfig {
+            omega: 1.0,
+            ..Default::default()
+        };
+        let config2 = NodeConfig {
+            omega: 2.0,
+            ..Default::default()
+        };
+
+        let mut sender = OmegaNode::new(config1).unwrap();
+        let mut receiver = OmegaNode::new(config2).unwrap();
+
+        let message = b"Not for you";
+        let target_freq = 1.0; // Different from receiver's frequency
+
+        // Send message at freq 1.0
+        sender.send_message(message, target_freq).await.unwrap();
 
         // Transfer message
         sender.transfer_message_to(&mut receiver);
 
-        // Receiver at freq 2.0 should not receive
-        let _received = receiver.receive_message().await.unwrap();
+        // Receiver at freq 2.0 should not receive
+        let _received = receiver.receive_message().await.unwrap();
+
+        // Due to frequency mismatch, might not receive
+        // (This is a simplified test; actual behavior depends on resonance parameters)
+    }
+
+    // Both sides wait on the medium at once, which needs the timer
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_negotiate_common_frequency() {
+        let bus = crate::transport::InMemoryTransport::new();
+        let mut a = OmegaNode::with_transport(NodeConfig {
+            omega: 2.0,
+            ..Default::default()
+        }, Box::new(bus.clone())).unwrap();
+        let mut b = OmegaNode::with_transport(NodeConfig {
+            omega: 1.5,
+            ..Default::default()
+        }, Box::new(bus.clone())).unwrap();
+        for _ in 0..3 {
+            b.advance_epoch();
+        }
+
+        let timeout = Duration::from_secs(1);
+        let (session_a, session_b) = tokio::join!(a.negotiate(timeout), b.negotiate(timeout));
+        let session = session_a.unwrap();
+
+        assert_eq!(session, session_b.unwrap());
+        assert_eq!(session.frequency, 1.5);
+        assert_eq!(session.epoch, 3);
+        assert_eq!(a.get_frequency(), b.get_frequency());
+        assert_eq!(a.descriptor(), b.descriptor());
+        assert_eq!(bus.pending(), 0);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_negotiate_keeps_frames_on_the_medium() {
+        let bus = crate::transport::InMemoryTransport::new();
+        let mut sender = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+        let mut a = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+        let mut b = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+        sender.send_message(b"early", 1.0).await.unwrap();
+
+        let timeout = Duration::from_secs(1);
+        let (session_a, session_b) = tokio::join!(a.negotiate(timeout), b.negotiate(timeout));
+        assert_eq!(session_a.unwrap(), session_b.unwrap());
+        let received = [a.receive_message().await.unwrap(), b.receive_message().await.unwrap()];
+        assert!(received.contains(&Some(b"early".to_vec())));
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_needs_a_peer() {
+        let mut offline = OmegaNode::new(NodeConfig::default()).unwrap();
+        assert!(matches!(offline.negotiate(Duration::from_secs(1)).await, Err(OmegaError::NetworkError(_))));
+
+        // Nobody answers: the node is left unchanged
+        let mut alone = OmegaNode::with_transport(
+            NodeConfig { omega: 2.0, ..Default::default() },
+            Box::new(crate::transport::InMemoryTransport::new()),
+        ).unwrap();
+        let expected = if cfg!(feature = "native") { "negotiation timed out" } else { "no descriptor from a peer" };
+        match alone.negotiate(Duration::from_millis(20)).await {
+            Err(OmegaError::NetworkError(msg)) => assert_eq!(msg, expected),
+            other => panic!("expected a network error, got {:?}", other),
+        }
+        assert_eq!(alone.get_frequency(), 2.0);
+    }
+
+    #[test]
+    fn test_invalid_params_rejected() {
+        let node_error = |edit: fn(&mut OmegaParams)| {
+            let mut params = OmegaParams::default();
+            edit(&mut params);
+            match OmegaNode::new(NodeConfig { params, ..Default::default() }) {
+                Err(OmegaError::ParameterError(msg)) => msg,
+                Err(e) => panic!("unexpected error {}", e),
+                Ok(_) => panic!("invalid params accepted"),
+            }
+        };
+
+        assert_eq!(
+            node_error(|p| p.weight_transfer.gamma = 1.5),
+            "weight_transfer.gamma must be in [0, 1], got 1.5"
+        );
+        assert_eq!(
+            node_error(|p| p.resonance.epsilon = -0.1),
+            "resonance.epsilon must be positive, got -0.1"
+        );
+        assert_eq!(
+            node_error(|p| p.sweep.beta = 0.0),
+            "sweep.beta must be finite and nonzero, got 0"
+        );
+        assert_eq!(
+            node_error(|p| p.weight_transfer.levels = vec![(ScaleLevel::Micro, 1.0), (ScaleLevel::Macro, 1.0)]),
+            "weight_transfer.levels must sum to 1, got 2"
+        );
+        assert!(OmegaParams::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_builder_overrides_only_given_fields() {
+        let config = NodeConfig::builder().sweep_schedule(SweepSchedule::Linear).build().unwrap();
+        let mut expected = NodeConfig::default();
+        expected.params.sweep.schedule = SweepSchedule::Linear;
+        assert_eq!(config, expected);
+
+        let config = NodeConfig::builder().omega(1.5).gamma(0.4).resonance_epsilon(0.05).build().unwrap();
+        assert_eq!((config.omega, config.params.weight_transfer.gamma), (1.5, 0.4));
+        assert_eq!(config.params.resonance.epsilon, 0.05);
+        assert_eq!(config.params.sweep, SweepParams::default());
+        assert!(OmegaNode::new(config).is_ok());
+
+        assert!(matches!(
+            NodeConfig::builder().omega(1.5).gamma(1.5).build(),
+            Err(OmegaError::ParameterError(msg)) if msg.contains("gamma")
+        ));
+    }
+
+    #[test]
+    fn test_descriptor_round_trip() {
+        let descriptor = NodeDescriptor {
+            frequency: 1.25,
+            epoch: 9,
+            capabilities: Capabilities::MASKING_STREAM | Capabilities::SCHEDULE_COSINE,
+        };
+        let bytes = descriptor.to_bytes().unwrap();
+        assert_eq!(NodeDescriptor::from_bytes(&bytes).unwrap(), descriptor);
+        assert!(NodeDescriptor::from_bytes(b"not a descriptor").is_err());
+
+        // Descriptors without capabilities only support the original mode
+        let legacy = NodeDescriptor::from_bytes(br#"{"frequency":1.0,"epoch":0}"#).unwrap();
+        assert_eq!(legacy.capabilities.masking_modes(), vec![MaskingMode::Diffused]);
+
+        let full = OmegaNode::new(NodeConfig::default()).unwrap().descriptor();
+        assert_eq!(NegotiatedSession::agree(&full, &descriptor).unwrap().masking_mode, MaskingMode::Stream);
+        assert_eq!(NegotiatedSession::agree(&legacy, &full).unwrap().masking_mode, MaskingMode::Diffused);
+        assert!(NegotiatedSession::agree(&legacy, &descriptor).is_err());
+    }
+
+    #[test]
+    fn test_capability_negotiation() {
+        let a = Capabilities::MASKING_DIFFUSED
+            | Capabilities::MASKING_STREAM
+            | Capabilities::SCHEDULE_COSINE
+            | Capabilities::CODEC_VECTOR_V1;
+        let b = Capabilities::MASKING_STREAM
+            | Capabilities::SCHEDULE_COSINE
+            | Capabilities::SCHEDULE_LINEAR
+            | Capabilities::from_bits(1 << 30);
+
+        let common = negotiate_capabilities(a, b).unwrap();
+        assert_eq!(common, Capabilities::MASKING_STREAM | Capabilities::SCHEDULE_COSINE);
+        assert_eq!(common, negotiate_capabilities(b, a).unwrap());
+        assert_eq!(common.masking_modes(), vec![MaskingMode::Stream]);
+        assert_eq!(common.schedules(), vec![SweepSchedule::Cosine]);
+
+        // Shared schedules and codecs are not enough without a masking mode
+        let diffused_only = Capabilities::MASKING_DIFFUSED | Capabilities::SCHEDULE_COSINE;
+        assert!(matches!(
+            negotiate_capabilities(diffused_only, b),
+            Err(OmegaError::ParameterError(msg)) if msg.contains("masking mode")
+        ));
+        assert!(negotiate_capabilities(Capabilities::empty(), Capabilities::SUPPORTED).is_err());
+    }
+
+    #[test]
+    fn test_malformed_frame_is_codec_error() {
+        match NodeDescriptor::from_bytes(br#"{"frequency": 1.0, "epoch": "#) {
+            Err(OmegaError::CodecError(msg)) => {
+                assert!(msg.contains("EOF"), "unhelpful message: {}", msg);
+                assert!(msg.contains("line 1"), "unhelpful message: {}", msg);
+            }
+            other => panic!("expected a codec error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_records_sends() {
+        let mut node = OmegaNode::new(NodeConfig::default()).unwrap();
+        node.send_message(b"unaudited", 1.0).await.unwrap();
+        assert!(node.audit_entries().is_empty());
+
+        node.enable_audit();
+        node.send_message(b"first", 1.0).await.unwrap();
+        node.advance_epoch();
+        node.send_message(b"second", 1.5).await.unwrap();
+
+        let entries = node.audit_entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].epoch, 1);
+        assert_eq!(entries[1].frequency, 1.5);
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+        assert!(crate::audit::verify_chain(entries));
+    }
+
+    #[tokio::test]
+    async fn test_key_schedule_cached_per_epoch() {
+        let mut node = OmegaNode::new(NodeConfig::default()).unwrap();
+
+        node.send_message(b"one", 1.5).await.unwrap();
+        node.send_message(b"two", 1.5).await.unwrap();
+        assert_eq!(node.key_derivations(), 1);
+
+        node.advance_epoch();
+        node.send_message(b"three", 1.5).await.unwrap();
+        assert_eq!(node.key_derivations(), 2);
+    }
+
+    #[test]
+    fn test_freq_search_candidates() {
+        let mut node = OmegaNode::new(NodeConfig {
+            omega: 1.5,
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(node.candidate_frequencies(1.5), vec![1.5]);
+
+        node.set_freq_search_window(2);
+        let candidates = node.candidate_frequencies(1.5);
+        let bins: Vec<i64> = candidates.iter().map(|&f| quantize_frequency(f)).collect();
+        assert_eq!(bins, vec![1500, 1499, 1501, 1498, 1502]);
+
+        // Candidates never leave the resonance bandwidth
+        node.set_freq_search_window(1000);
+        let epsilon = node.resonance.epsilon();
+        assert!(node.candidate_frequencies(1.5).iter().all(|f| (f - 1.5).abs() < epsilon));
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_streams_reassemble_independently() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        for (stream, msg) in [(1, "a0"), (2, "b0"), (1, "a1"), (2, "b1"), (1, "a2")] {
+            sender.send_message_stream(stream, msg.as_bytes(), 1.0).await.unwrap();
+        }
+
+        // Deliver the interleaved frames with each stream's first frame last
+        let mut frames = Vec::new();
+        while let Some(frame) = sender.take_frame() {
+            frames.push(frame);
+        }
+        frames.sort_by_key(|f| (f.header.sequence != 0, f.header.stream_id));
+        for frame in frames {
+            receiver.push_frame(frame);
+        }
+
+        let mut received: HashMap<u16, Vec<(u32, String)>> = HashMap::new();
+        loop {
+            match receiver.receive_stream_message().await.unwrap() {
+                Some(m) => received
+                    .entry(m.stream_id)
+                    .or_default()
+                    .push((m.sequence, String::from_utf8(m.payload).unwrap())),
+                None if receiver.message_buffer.is_empty() => break,
+                None => {}
+            }
+        }
+
+        let expected = |msgs: &[&str]| -> Vec<(u32, String)> {
+            msgs.iter().enumerate().map(|(i, m)| (i as u32, m.to_string())).collect()
+        };
+        assert_eq!(received[&1], expected(&["a0", "a1", "a2"]));
+        assert_eq!(received[&2], expected(&["b0", "b1"]));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_header_fails_authentication() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.send_message_stream(3, b"first", 1.0).await.unwrap();
+        sender.send_message_stream(3, b"second", 1.0).await.unwrap();
+        let frame = sender.take_frame().unwrap();
+        assert_eq!((frame.header.stream_id, frame.header.sequence), (3, 1));
+
+        // Moved to another stream, or to another place in its stream
+        let mut moved = frame.clone();
+        moved.header.stream_id = 4;
+        assert_eq!(receiver.receive_from_frame(&moved.to_bytes()).unwrap(), ReceiveOutcome::AuthFailed);
+        let mut reordered = frame.clone();
+        reordered.header.sequence = 0;
+        assert_eq!(receiver.receive_from_frame(&reordered.to_bytes()).unwrap(), ReceiveOutcome::AuthFailed);
+        assert_eq!(receiver.metrics().auth_failures, 2);
+
+        // The untouched frame still decodes (held until sequence 0 arrives)
+        assert_eq!(receiver.receive_from_frame(&frame.to_bytes()).unwrap(), ReceiveOutcome::Held);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_frame_is_not_for_us() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        sender.send_message(b"integrity matters", 1.0).await.unwrap();
+        let mut frame = sender.take_frame().unwrap();
+        frame.vector[1] += 1.0;
+        receiver.push_frame(frame);
+
+        assert_eq!(receiver.receive_message().await.unwrap(), None);
+        assert_eq!(receiver.metrics().auth_failures, 1);
+        assert_eq!(receiver.metrics().decode_errors, 0);
+    }
+
+    #[tokio::test]
+    async fn test_receive_from_frames_mixed_batch() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        receiver.subscribe(2.0);
+
+        let mut batch = Vec::new();
+        for (message, omega) in [(&b"one"[..], 1.0), (b"elsewhere", 3.0), (b"two", 2.0), (b"one again", 1.0)] {
+            sender.send_message(message, omega).await.unwrap();
+            batch.push(sender.take_frame().unwrap().to_bytes());
+        }
+        batch.push(b"not a frame".to_vec());
+
+        let payloads: Vec<_> = receiver
+            .receive_from_frames(&batch)
+            .into_iter()
+            .map(|r| r.map(|outcome| outcome.into_message().map(|m| m.payload)))
+            .collect();
+        assert_eq!(payloads[0].as_ref().unwrap().as_deref(), Some(&b"one"[..]));
+        assert_eq!(payloads[1].as_ref().unwrap(), &None);
+        assert_eq!(payloads[2].as_ref().unwrap().as_deref(), Some(&b"two"[..]));
+        assert_eq!(payloads[3].as_ref().unwrap().as_deref(), Some(&b"one again"[..]));
+        assert!(matches!(payloads[4], Err(OmegaError::CodecError(_))));
+        assert_eq!(receiver.stats().dropped_auth, 1);
+
+        // One key derivation per frequency for the whole batch
+        assert_eq!(receiver.key_derivations(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_masking_mode_read_from_header() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.set_masking_mode(MaskingMode::Stream);
+
+        sender.send_message(b"streamed", 1.0).await.unwrap();
+        let frame = sender.take_frame().unwrap();
+        assert_eq!(frame.header.masking_mode, MaskingMode::Stream.code());
+        let outcome = receiver.receive_from_frame(&frame.to_bytes()).unwrap();
+        assert_eq!(outcome.into_message().unwrap().payload, b"streamed");
+
+        // Relabelling the mode breaks authentication
+        sender.send_message(b"relabelled", 1.0).await.unwrap();
+        let mut frame = sender.take_frame().unwrap();
+        frame.header.masking_mode = MaskingMode::Diffused.code();
+        assert_eq!(receiver.receive_from_frame(&frame.to_bytes()).unwrap(), ReceiveOutcome::AuthFailed);
+
+        frame.header.masking_mode = 200;
+        assert!(matches!(receiver.receive_from_frame(&frame.to_bytes()), Err(OmegaError::CodecError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_aes_ctr_frame_decoded_from_header() {
+        let mut sender = OmegaNode::new(NodeConfig::default()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::default()).unwrap();
+        sender.set_masking_mode(MaskingMode::AesCtr);
+        assert_eq!(receiver.masking_mode(), MaskingMode::Diffused);
+
+        sender.send_message(b"counter mode", 1.0).await.unwrap();
+        let frame = sender.take_frame().unwrap();
+        assert_eq!(frame.header.masking_mode, MaskingMode::AesCtr.code());
+        let outcome = receiver.receive_from_frame(&frame.to_bytes()).unwrap();
+        assert_eq!(outcome.into_message().unwrap().payload, b"counter mode");
+
+        // Read as the ChaCha20 stream mode, the frame does not authenticate
+        sender.send_message(b"relabelled", 1.0).await.unwrap();
+        let mut frame = sender.take_frame().unwrap();
+        frame.header.masking_mode = MaskingMode::Stream.code();
+        assert_eq!(receiver.receive_from_frame(&frame.to_bytes()).unwrap(), ReceiveOutcome::AuthFailed);
+    }
+
+    #[tokio::test]
+    async fn test_receive_fair_alternates_frequencies() {
+        let mut gateway = OmegaNode::new(NodeConfig { omega: 1.0, ..NodeConfig::lossless() }).unwrap();
+        gateway.subscribe(2.0);
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        // A deep backlog on 1.0 queued ahead of a shorter one on 2.0
+        for i in 0..4u8 {
+            sender.send_message(&[b'a', i], 1.0).await.unwrap();
+        }
+        for i in 0..2u8 {
+            sender.send_message(&[b'b', i], 2.0).await.unwrap();
+        }
+        // Taken newest first, so the gateway polls them oldest first
+        let frames: Vec<Frame> = std::iter::from_fn(|| sender.take_frame()).collect();
+        for frame in frames {
+            gateway.push_frame(frame);
+        }
+
+        let mut served = Vec::new();
+        while let Some((omega, payload)) = gateway.receive_fair().await.unwrap() {
+            served.push((omega, payload));
+        }
+        let frequencies: Vec<f64> = served.iter().map(|(omega, _)| *omega).collect();
+        assert_eq!(frequencies, [1.0, 2.0, 1.0, 2.0, 1.0, 1.0]);
+        assert!(served.iter().all(|(omega, payload)| (payload[0] == b'a') == (*omega == 1.0)));
+        // Within a frequency messages keep their arrival order
+        let a: Vec<u8> = served.iter().filter(|(omega, _)| *omega == 1.0).map(|(_, p)| p[1]).collect();
+        assert_eq!(a, [0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_inbound_burst_suppresses_decoding() {
+        let mut sender = OmegaNode::new(NodeConfig::default()).unwrap();
+        let mut frames = Vec::new();
+        for i in 0..20u8 {
+            sender.send_message(&[b'm', i], 1.0).await.unwrap();
+            frames.push(sender.take_frame().unwrap().to_bytes());
+        }
+        let decoded = |max_rate: Option<f64>| {
+            let mut config = NodeConfig::default();
+            config.params.sweep.max_rate = max_rate;
+            let mut receiver = OmegaNode::new(config).unwrap();
+            frames
+                .iter()
+                .filter(|bytes| matches!(receiver.receive_from_frame(bytes), Ok(ReceiveOutcome::Message(_))))
+                .count()
+        };
+
+        // Without a rate limit every frame of the burst decodes
+        assert_eq!(decoded(None), 20);
+        // Arrivals past max_rate within one window are attenuated out
+        assert_eq!(decoded(Some(5.0)), 5);
+    }
+
+    #[tokio::test]
+    async fn test_receive_fair_skips_undecodable_frames() {
+        let mut gateway = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        for message in [&b"before"[..], b"broken", b"after"] {
+            sender.send_message(message, 1.0).await.unwrap();
+        }
+        let mut frames: Vec<Frame> = std::iter::from_fn(|| sender.take_frame()).collect();
+        frames[1].header.compression = 9;
+        for frame in frames {
+            gateway.push_frame(frame);
+        }
+
+        let mut served = Vec::new();
+        while let Some((_, payload)) = gateway.receive_fair().await.unwrap() {
+            served.push(payload);
+        }
+        assert_eq!(served, [&b"before"[..], b"after"]);
+        assert_eq!(gateway.stats().dropped_decode, 1);
+        assert_eq!(gateway.metrics().decode_errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_messages_received_in_order() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        for message in [&b"first"[..], b"second", b"third"] {
+            sender.queue_message(message, 1.0);
+        }
+        assert_eq!(sender.queued_messages(), 3);
+        assert_eq!(sender.coalesce().await.unwrap(), 1);
+        assert_eq!(sender.queued_messages(), 0);
+
+        let frame = sender.take_frame().unwrap();
+        assert!(sender.take_frame().is_none());
+        assert_eq!(frame.header.record_count, 3);
+
+        // The record count is authenticated
+        let mut forged = frame.clone();
+        forged.header.record_count = 2;
+        receiver.push_frame(forged);
+        assert_eq!(receiver.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+
+        receiver.push_frame(frame);
+        let mut received = Vec::new();
+        while let Some(message) = receiver.receive_message().await.unwrap() {
+            received.push(message);
+        }
+        assert_eq!(received, [&b"first"[..], b"second", b"third"]);
+        assert_eq!(receiver.metrics().messages_received, 1);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_respects_frame_capacity() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        // Two half-capacity messages cannot share a frame; the oversized one
+        // goes alone as a plain message
+        let half = vec![1u8; MAX_COALESCED_LEN / 2];
+        let oversized = vec![2u8; MAX_COALESCED_LEN + 1];
+        sender.queue_message(&half, 1.0);
+        sender.queue_message(&half, 1.0);
+        sender.queue_message(&oversized, 1.0);
+        sender.queue_message(b"tail", 1.0);
+        assert_eq!(sender.coalesce().await.unwrap(), 4);
+
+        let frames: Vec<Frame> = std::iter::from_fn(|| sender.take_frame()).collect();
+        assert!(frames.iter().all(|f| f.header.record_count == 0));
+        for frame in frames {
+            receiver.push_frame(frame);
+        }
+        let mut received = Vec::new();
+        while let Some(message) = receiver.receive_message().await.unwrap() {
+            received.push(message);
+        }
+        assert_eq!(received, [half.clone(), half, oversized, b"tail".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_fragments_reassembled_out_of_order() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.set_fragment_size(Some(4)).unwrap();
+        assert!(sender.set_fragment_size(Some(0)).is_err());
+
+        let message: Vec<u8> = (0..=255).collect();
+        sender.send_message(&message, 1.0).await.unwrap();
+        let mut fragments: Vec<Frame> = std::iter::from_fn(|| sender.take_frame()).collect();
+        assert!(fragments.len() > 3);
+        assert!(fragments.iter().all(|f| f.vector.len() <= 4));
+        assert_eq!(sender.metrics().messages_sent, 1);
+
+        // Deliver interleaved: odd positions first, then even ones reversed
+        let (odd, mut even): (Vec<_>, Vec<_>) = fragments.drain(..).partition(|f| f.header.fragment_index % 2 == 1);
+        even.reverse();
+        let count = odd.len() + even.len();
+        for (i, fragment) in odd.into_iter().chain(even).enumerate() {
+            let outcome = receiver.receive_from_frame(&fragment.to_bytes()).unwrap();
+            if i + 1 < count {
+                assert_eq!(outcome, ReceiveOutcome::Fragment);
+            } else {
+                assert_eq!(outcome.into_message().unwrap().payload, message);
+            }
+        }
+        assert_eq!(receiver.metrics().messages_received, 1);
+
+        // Small messages still travel in one frame
+        sender.send_message(b"hi", 1.0).await.unwrap();
+        assert!(!sender.take_frame().unwrap().header.is_fragment());
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_fragmented_message_dropped() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.set_fragment_size(Some(4)).unwrap();
+        receiver.set_fragment_timeout(Duration::from_millis(20));
+
+        sender.send_message(&[7u8; 64], 1.0).await.unwrap();
+        // Lose one fragment on the way
+        sender.take_frame().unwrap();
+        while let Some(fragment) = sender.take_frame() {
+            receiver.push_frame(fragment);
+        }
+        while let Ok(outcome) = receiver.receive_outcome().await {
+            if outcome == ReceiveOutcome::Empty {
+                break;
+            }
+            assert_eq!(outcome, ReceiveOutcome::Fragment);
+        }
+        assert_eq!(receiver.stats().dropped_incomplete, 0);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(receiver.receive_outcome().await.unwrap(), ReceiveOutcome::Empty);
+        assert_eq!(receiver.stats().dropped_incomplete, 1);
+        assert_eq!(receiver.metrics().incomplete_dropped, 1);
+        assert_eq!(receiver.metrics().messages_received, 0);
+    }
+
+    #[cfg(any(debug_assertions, feature = "insecure-plaintext"))]
+    #[tokio::test]
+    async fn test_debug_plaintext_frames_unmasked() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut debug_receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.set_debug_plaintext_insecure(true);
+        sender.set_compression(Compression::Deflate);
+        debug_receiver.set_debug_plaintext_insecure(true);
+        assert!(sender.debug_plaintext() && !receiver.debug_plaintext());
+
+        let message = b"inspect me inspect me inspect me";
+        sender.send_message(message, 1.0).await.unwrap();
+        let frame = sender.take_frame().unwrap();
+        assert_eq!(frame.header.masking_mode, PLAINTEXT_MASKING_CODE);
+        assert_eq!(frame.header.compression, Compression::None.code());
+        assert_eq!(frame.vector, utils::pack_bytes(message));
+        assert_eq!(utils::unpack_bytes(&frame.vector).unwrap(), message);
+
+        let outcome = debug_receiver.receive_from_frame(&frame.to_bytes()).unwrap();
+        assert_eq!(outcome.into_message().unwrap().payload, message);
+        // A node outside the mode never takes an unmasked frame for a message
+        assert!(matches!(receiver.receive_from_frame(&frame.to_bytes()), Err(OmegaError::CodecError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_compression_shrinks_frames() {
+        let mut plain = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut compressing = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        compressing.set_compression(Compression::Deflate);
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        let text = b"all work and no play makes a dull node. ".repeat(50);
+        plain.send_message(&text, 1.0).await.unwrap();
+        compressing.send_message(&text, 1.0).await.unwrap();
+        let uncompressed = plain.take_frame().unwrap();
+        let frame = compressing.take_frame().unwrap();
+        assert_eq!(frame.header.compression, Compression::Deflate.code());
+        assert!(frame.vector.len() * 10 < uncompressed.vector.len());
+        let outcome = receiver.receive_from_frame(&frame.to_bytes()).unwrap();
+        assert_eq!(outcome.into_message().unwrap().payload, text);
+
+        // Incompressible data falls back to no compression instead of growing
+        use rand::{RngCore, SeedableRng};
+        let mut noise = vec![0u8; 400];
+        rand::rngs::StdRng::seed_from_u64(5).fill_bytes(&mut noise);
+        plain.send_message(&noise, 1.0).await.unwrap();
+        compressing.send_message(&noise, 1.0).await.unwrap();
+        let uncompressed = plain.take_frame().unwrap();
+        let frame = compressing.take_frame().unwrap();
+        assert_eq!(frame.header.compression, Compression::None.code());
+        assert_eq!(frame.vector.len(), uncompressed.vector.len());
+        let outcome = receiver.receive_from_frame(&frame.to_bytes()).unwrap();
+        assert_eq!(outcome.into_message().unwrap().payload, noise);
+
+        // The compression flag is authenticated
+        compressing.send_message(&text, 1.0).await.unwrap();
+        let mut frame = compressing.take_frame().unwrap();
+        frame.header.compression = Compression::None.code();
+        assert_eq!(receiver.receive_from_frame(&frame.to_bytes()).unwrap(), ReceiveOutcome::AuthFailed);
+    }
+
+    #[tokio::test]
+    async fn test_requeued_frame_reaches_next_node() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut a = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut b = OmegaNode::new(NodeConfig { omega: 2.0, ..NodeConfig::lossless() }).unwrap();
+        a.set_non_resonant_policy(NonResonantPolicy::Requeue);
+
+        sender.send_message(b"for b", 2.0).await.unwrap();
+        sender.transfer_message_to(&mut a);
+        assert_eq!(a.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        assert_eq!(a.message_buffer.len(), 1);
+
+        a.transfer_message_to(&mut b);
+        assert_eq!(b.receive_message().await.unwrap(), Some(b"for b".to_vec()));
+
+        // A frame that keeps coming back is dropped on its second visit
+        sender.send_message(b"for nobody", 3.0).await.unwrap();
+        sender.transfer_message_to(&mut a);
+        assert_eq!(a.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        assert_eq!(a.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        assert_eq!(a.receive_outcome().await.unwrap(), ReceiveOutcome::Empty);
+
+        // The default policy drops at once
+        let mut c = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.send_message(b"for b", 2.0).await.unwrap();
+        sender.transfer_message_to(&mut c);
+        assert_eq!(c.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        assert!(c.message_buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stats_separate_drop_reasons() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        assert_eq!(receiver.receive_message().await.unwrap(), None);
+        sender.send_message(b"matching", 1.0).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(b"matching".to_vec()));
+        sender.send_message(b"other key", 3.0).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_message().await.unwrap(), None);
+
+        // Spectral traffic far from the listener's band
+        let mut listener = OmegaNode::new(NodeConfig::default()).unwrap();
+        let tone = Array1::from_vec((0..1000).map(|i| (2.5 * i as f64).sin()).collect());
+        listener.push_frame(Frame { header: FrameHeader::default(), vector: tone });
+        assert_eq!(listener.receive_message().await.unwrap(), None);
 
-        // Due to frequency mismatch, might not receive
-        // (This is a simplified test; actual behavior depends on resonance parameters)
+        assert_eq!(sender.stats().sent, 2);
+        assert_eq!(
+            *receiver.stats(),
+            NodeStats { received: 1, dropped_auth: 1, poll_empty: 1, ..Default::default() }
+        );
+        assert_eq!(
+            *listener.stats(),
+            NodeStats { dropped_frequency: 1, ..Default::default() }
+        );
+
+        receiver.reset_stats();
+        assert_eq!(*receiver.stats(), NodeStats::default());
+        assert_eq!(receiver.metrics().messages_received, 1);
+    }
+
+    #[tokio::test]
+    async fn test_receive_outcome_distinguishes_auth_from_resonance() {
+        // Same frequency, different key epoch: resonance is fine, the key is not
+        let mut stale = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        stale.advance_epoch();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        stale.send_message(b"wrong secret", 1.0).await.unwrap();
+        stale.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        assert_eq!(receiver.metrics().auth_failures, 1);
+
+        // Off-frequency spectral traffic
+        let mut sender = OmegaNode::new(NodeConfig::default()).unwrap();
+        let mut listener = OmegaNode::new(NodeConfig::default()).unwrap();
+        sender.send_message(b"elsewhere", 2.5).await.unwrap();
+        sender.transfer_message_to(&mut listener);
+        assert_eq!(listener.receive_outcome().await.unwrap(), ReceiveOutcome::NotResonant);
+        assert_eq!(listener.metrics().resonance_rejected, 1);
+        assert_eq!(listener.metrics().auth_failures, 0);
+
+        assert_eq!(listener.receive_outcome().await.unwrap(), ReceiveOutcome::Empty);
+    }
+
+    #[tokio::test]
+    async fn test_spectral_frame_carries_whole_message() {
+        let mut sender = OmegaNode::new(NodeConfig::default()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::default()).unwrap();
+        let message: Vec<u8> = (0..100u8).collect();
+        sender.send_message(&message, 1.0).await.unwrap();
+
+        // Pilot, length prefix, 100 bytes and the 16-byte tag: 121 chunks,
+        // padded to 124 so the length is a multiple of four
+        let frame = sender.take_frame().unwrap();
+        assert_eq!(frame.vector.len(), 124 * 5);
+
+        // The whole message survives the spectral operators byte for byte
+        receiver.push_frame(frame);
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(message));
+    }
+
+    #[test]
+    fn test_subscriptions_match_any_frequency() {
+        let mut node = OmegaNode::new(NodeConfig { omega: 1.0, ..Default::default() }).unwrap();
+        node.subscribe(2.5);
+        node.subscribe(2.5);
+        assert_eq!(node.subscriptions(), vec![1.0, 2.5]);
+
+        // 1024-sample tone at 2.5 matches the second subscription only
+        let tone = Array1::from_vec((0..1024).map(|i| (2.5 * i as f64).sin()).collect());
+        assert_eq!(node.resonance.resonant_frequency(&tone), Some(2.5));
+
+        assert!(node.unsubscribe(2.5));
+        assert!(!node.unsubscribe(2.5));
+        assert!(!node.is_resonant(&tone));
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_frequency_keys_unmasking() {
+        let mut gateway = OmegaNode::new(NodeConfig { omega: 1.0, ..NodeConfig::lossless() }).unwrap();
+        gateway.subscribe(1.5);
+        gateway.subscribe(2.0);
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        sender.send_message(b"on the second", 1.5).await.unwrap();
+        sender.transfer_message_to(&mut gateway);
+        assert_eq!(gateway.receive_message().await.unwrap(), Some(b"on the second".to_vec()));
+
+        gateway.unsubscribe(1.5);
+        sender.send_message(b"on the second", 1.5).await.unwrap();
+        sender.transfer_message_to(&mut gateway);
+        assert_eq!(gateway.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+    }
+
+    #[tokio::test]
+    async fn test_fork_shares_frequency_not_buffer() {
+        let mut parent = OmegaNode::new(NodeConfig { omega: 1.5, ..NodeConfig::lossless() }).unwrap();
+        parent.subscribe(2.0);
+        parent.advance_epoch();
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.advance_epoch();
+
+        // A frame waiting in the parent stays there
+        sender.send_message(b"for the parent", 1.5).await.unwrap();
+        sender.transfer_message_to(&mut parent);
+
+        let mut worker = parent.fork_with_seed(7);
+        assert_eq!(worker.get_frequency(), 1.5);
+        assert_eq!(worker.subscriptions(), parent.subscriptions());
+        assert_eq!(worker.receive_message().await.unwrap(), None);
+
+        sender.send_message(b"for the worker", 2.0).await.unwrap();
+        sender.transfer_message_to(&mut worker);
+        assert_eq!(worker.receive_message().await.unwrap(), Some(b"for the worker".to_vec()));
+        assert_eq!(parent.receive_message().await.unwrap(), Some(b"for the parent".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_never_repeats_within_epoch() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        // Same message, key and epoch twice: distinct nonces, distinct ciphertexts
+        sender.send_message(b"same", 1.0).await.unwrap();
+        sender.send_message(b"same", 1.0).await.unwrap();
+        let second = sender.take_frame().unwrap();
+        let first = sender.take_frame().unwrap();
+        assert_eq!((first.header.counter, second.header.counter), (0, 1));
+        assert_ne!(first.vector, second.vector);
+
+        for frame in [first, second] {
+            receiver.push_frame(frame);
+            assert_eq!(receiver.receive_message().await.unwrap(), Some(b"same".to_vec()));
+        }
+
+        // Once the counter space of a key is used up, sending refuses
+        sender.nonce_counters.insert((quantize_frequency(1.0), 0), u32::MAX as u64 + 1);
+        match sender.send_message(b"again", 1.0).await {
+            Err(OmegaError::MaskingError(msg)) => assert_eq!(msg, NONCE_REUSE),
+            other => panic!("expected nonce reuse, got {:?}", other),
+        }
+
+        sender.advance_epoch();
+        assert!(sender.send_message(b"again", 1.0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_random_nonce_separates_senders() {
+        // Two fresh senders share key, epoch and counter 0; only the random
+        // nonce keeps their keystreams apart
+        let mut a = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut b = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        a.send_message(b"same", 1.0).await.unwrap();
+        b.send_message(b"same", 1.0).await.unwrap();
+        let first = a.take_frame().unwrap();
+        let second = b.take_frame().unwrap();
+        assert_eq!((first.header.counter, second.header.counter), (0, 0));
+        assert_ne!(first.header.nonce, second.header.nonce);
+        assert_ne!(first.vector, second.vector);
+
+        for frame in [first.clone(), second] {
+            receiver.push_frame(frame);
+            assert_eq!(receiver.receive_message().await.unwrap(), Some(b"same".to_vec()));
+        }
+
+        // The nonce is authenticated
+        let mut fresh = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut tampered = first;
+        tampered.header.nonce[0] ^= 1;
+        fresh.push_frame(tampered);
+        assert_eq!(fresh.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+    }
+
+    #[tokio::test]
+    async fn test_beacon_sync_realigns_drifted_epoch() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.set_epoch(5);
+        receiver.set_epoch(3);
+
+        sender.send_message(b"drifted", 1.0).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+
+        receiver.sync_from_beacon(&sender.descriptor().to_bytes().unwrap()).unwrap();
+        assert_eq!(receiver.epoch(), sender.epoch());
+
+        sender.send_message(b"drifted", 1.0).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(b"drifted".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_branches_share_state_and_diverge_on_params() {
+        let mut node = OmegaNode::with_seed(NodeConfig::default(), 9).unwrap();
+        node.set_epoch(3);
+        node.send_message(b"warm up", 1.0).await.unwrap();
+
+        let mut narrow = node.params().clone();
+        narrow.sweep.beta = 0.1;
+        let mut wide = node.params().clone();
+        wide.sweep.beta = 0.5;
+
+        let mut a = node.branch(narrow.clone()).unwrap();
+        let mut twin = node.branch(narrow).unwrap();
+        let mut b = node.branch(wide).unwrap();
+        for branch in [&a, &twin, &b] {
+            assert_eq!(branch.epoch(), 3);
+            assert_eq!(branch.get_frequency(), node.get_frequency());
+            assert_eq!(branch.message_buffer, node.message_buffer);
+        }
+
+        let message = b"same input for every branch";
+        for branch in [&mut a, &mut twin, &mut b] {
+            branch.message_buffer.clear();
+            branch.send_message(message, 1.0).await.unwrap();
+        }
+        let (fa, ft, fb) = (a.take_frame().unwrap(), twin.take_frame().unwrap(), b.take_frame().unwrap());
+
+        // The branches share counters, but each draws its own nonce
+        assert_eq!((fa.header.counter, ft.header.counter, fb.header.counter), (1, 1, 1));
+        assert_ne!(fa.header.nonce, ft.header.nonce);
+        assert_ne!(fa.header.nonce, fb.header.nonce);
+
+        // Identical params reproduce the encoding; a different gate width diverges
+        let masked = [0x5a; 32];
+        let va = a.spectral_encode(&masked, 1.0).unwrap();
+        assert_eq!(va, twin.spectral_encode(&masked, 1.0).unwrap());
+        assert_ne!(va, b.spectral_encode(&masked, 1.0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_custom_pipeline_replaces_operator_sequence() {
+        let message = b"through a custom pipeline";
+
+        // An empty pipeline sends the laid-out, imprinted vector untouched
+        let imprinted = |frame: &Frame| {
+            let mut params = MaskingParams::ephemeral_from_frequency(1.0, 0);
+            params.nonce = frame.header.nonce;
+            params.counter = frame.header.counter;
+            let masked = masking::MaskingOperator::new()
+                .mask_with_aad(message, &params, &frame.header.associated_data())
+                .unwrap();
+            let layout = spectral_layout(&masked, 5).unwrap();
+            let tone = utils::set_frequency(Array1::zeros(layout.len()), 1.0).unwrap();
+            layout + utils::chunk_shape(&tone, 5)
+        };
+        let mut node = OmegaNode::new(NodeConfig::default()).unwrap();
+        node.set_pipeline(OmegaPipeline::new()).unwrap();
+        node.send_message(message, 1.0).await.unwrap();
+        let frame = node.take_frame().unwrap();
+        assert_eq!(frame.vector, imprinted(&frame));
+
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(crate::pipeline::LinearStage::new(ndarray::Array2::eye(5) * 0.5));
+        node.set_pipeline(pipeline).unwrap();
+        node.send_message(message, 1.0).await.unwrap();
+        let frame = node.take_frame().unwrap();
+        assert_eq!(frame.vector, imprinted(&frame) * 0.5);
+
+        // Stages must map the chunk dimension onto itself
+        let mut wrong = OmegaPipeline::new();
+        wrong.push(crate::pipeline::LinearStage::new(ndarray::Array2::eye(frame.vector.len())));
+        assert!(node.set_pipeline(wrong).is_err());
+        let mut narrowing = OmegaPipeline::new();
+        narrowing.push(crate::pipeline::LinearStage::new(ndarray::Array2::ones((3, 5))));
+        assert!(node.set_pipeline(narrowing).is_err());
+
+        assert!(node.clear_pipeline().is_some());
+        assert!(node.clear_pipeline().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_receiver_inverts_the_installed_pipeline() {
+        let mixing = || {
+            let matrix = ndarray::Array2::from_shape_fn((5, 5), |(i, j)| if i == j { 1.0 } else { 0.05 * j as f64 });
+            let mut pipeline = OmegaPipeline::new();
+            pipeline.push(crate::pipeline::LinearStage::new(matrix));
+            pipeline
+        };
+        let mut sender = OmegaNode::new(NodeConfig::default()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::default()).unwrap();
+        sender.set_pipeline(mixing()).unwrap();
+        sender.send_message(b"mixed across each chunk", 1.0).await.unwrap();
+        let frame = sender.take_frame().unwrap();
+
+        // The built-in inverse does not undo the mixing
+        assert!(!matches!(receiver.receive_from_frame(&frame.to_bytes()), Ok(ReceiveOutcome::Message(_))));
+
+        receiver.set_pipeline(mixing()).unwrap();
+        match receiver.receive_from_frame(&frame.to_bytes()).unwrap() {
+            ReceiveOutcome::Message(message) => assert_eq!(message.payload, b"mixed across each chunk"),
+            other => panic!("expected the message, got {:?}", other),
+        }
+        assert!(sender.verify_round_trip(b"self-test").is_ok());
+    }
+
+    #[test]
+    fn test_composite_lipschitz_tracks_doublekick() {
+        // Default alphas 0.05 and -0.03: Ω ≈ 1.08, below the documented 1.1
+        let node = OmegaNode::new(NodeConfig::default()).unwrap();
+        assert!((node.composite_lipschitz() - 1.08).abs() < 1e-12);
+        assert!(node.composite_lipschitz() < 1.1);
+        assert!(!node.is_contractive());
+
+        let mut params = OmegaParams::default();
+        params.doublekick.alpha1 = 0.2;
+        params.doublekick.alpha2 = -0.1;
+        let retuned = node.branch(params).unwrap();
+        assert!((retuned.composite_lipschitz() - 1.3).abs() < 1e-12);
+        assert!(retuned.composite_lipschitz() > node.composite_lipschitz());
+
+        // A halving pipeline replaces the built-in sequence
+        let mut halved = OmegaNode::new(NodeConfig::default()).unwrap();
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(crate::pipeline::LinearStage::new(ndarray::Array2::eye(5) * 0.5));
+        halved.set_pipeline(pipeline).unwrap();
+        assert!((halved.composite_lipschitz() - 0.5).abs() < 1e-9);
+        assert!(halved.is_contractive());
+    }
+
+    #[tokio::test]
+    async fn test_receive_all_keeps_matching_messages_in_order() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.connect(&mut receiver);
+
+        // Interleave messages for the receiver (1.0) with traffic for 2.0
+        for (message, omega) in [(&b"one"[..], 1.0), (b"x", 2.0), (b"two", 1.0), (b"y", 2.0), (b"three", 1.0)] {
+            sender.send_message(message, omega).await.unwrap();
+        }
+
+        let received = receiver.receive_all().await.unwrap();
+        assert_eq!(received, [&b"one"[..], b"two", b"three"]);
+        assert_eq!(receiver.stats().dropped_auth, 2);
+        assert!(receiver.receive_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_node_id_addressing() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut a = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut b = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.connect(&mut a);
+        sender.connect(&mut b);
+        assert_ne!(a.node_id(), b.node_id());
+        b.set_node_id(NodeId([7; 16]));
+
+        // Same frequency, different IDs: only the addressee takes the message
+        sender.send_message_to(b"for b", b.node_id(), 1.0).await.unwrap();
+        assert_eq!(a.receive_outcome().await.unwrap(), ReceiveOutcome::NotAddressed);
+        assert_eq!(a.stats().dropped_address, 1);
+        assert_eq!(b.receive_message().await.unwrap(), Some(b"for b".to_vec()));
+
+        // A broadcast reaches both, as does a plain send
+        sender.send_message_to(b"everyone", NodeId::BROADCAST, 1.0).await.unwrap();
+        sender.send_message(b"plain", 1.0).await.unwrap();
+        for node in [&mut a, &mut b] {
+            assert_eq!(node.receive_all().await.unwrap(), [&b"everyone"[..], b"plain"]);
+        }
+        assert_eq!(b.stats().dropped_address, 0);
+
+        // The destination travels masked; its presence is authenticated
+        sender.send_message_to(b"secret", NodeId([7; 16]), 1.0).await.unwrap();
+        let mut frame = a.poll_network().await.unwrap().unwrap();
+        assert!(frame.header.addressed);
+        assert!(!utils::unpack_bytes(&frame.vector).unwrap().windows(16).any(|w| w == [7; 16]));
+        frame.header.addressed = false;
+        let mut fresh = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        assert_eq!(fresh.receive_from_frame(&frame.to_bytes()).unwrap(), ReceiveOutcome::AuthFailed);
+    }
+
+    #[tokio::test]
+    async fn test_verify_round_trip() {
+        let mut node = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        node.set_compression(Compression::Deflate);
+        node.verify_round_trip(b"startup self-test").unwrap();
+        node.verify_round_trip(&[]).unwrap();
+
+        // The probe leaves the node untouched
+        assert!(node.take_frame().is_none());
+        assert_eq!(node.metrics(), &NodeMetrics::default());
+        node.send_message(b"live", 1.0).await.unwrap();
+        assert_eq!(node.take_frame().unwrap().header.sequence, 0);
+
+        // So does the spectral path, but per-element sweep gating scales each
+        // level differently and cannot be undone with the pilot
+        OmegaNode::new(NodeConfig::default()).unwrap().verify_round_trip(b"startup self-test").unwrap();
+        let mut config = NodeConfig::default();
+        config.params.sweep.mode = SweepMode::PerElement;
+        let mut lossy = OmegaNode::new(config).unwrap();
+        assert!(lossy.verify_round_trip(b"startup self-test").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hopping_nodes_meet_across_epochs() {
+        use crate::hopping::HopTarget;
+
+        let hopper = FrequencyHopper::new(3, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        let mut sender = OmegaNode::new(NodeConfig { omega: 9.0, ..NodeConfig::lossless() }).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig { omega: 9.0, ..NodeConfig::lossless() }).unwrap();
+        assert!(sender.send_message(b"no schedule", HopTarget).await.is_err());
+        sender.set_frequency_hopper(Some(hopper.clone()));
+        receiver.set_frequency_hopper(Some(hopper.clone()));
+
+        let mut hops = Vec::new();
+        for epoch in 0..3u64 {
+            let omega = sender.hop_frequency().unwrap();
+            assert_eq!(omega, hopper.frequency_at(epoch));
+            assert_eq!(receiver.hop_frequency(), Some(omega));
+
+            sender.send_message(format!("epoch {}", epoch).as_bytes(), HopTarget).await.unwrap();
+            sender.transfer_message_to(&mut receiver);
+            let message = receiver.receive_stream_message().await.unwrap().unwrap();
+            assert_eq!(message.payload, format!("epoch {}", epoch).as_bytes());
+            assert_eq!(message.frequency, omega);
+            hops.push(omega);
+
+            sender.advance_epoch();
+            receiver.advance_epoch();
+            // Only the new epoch's hop frequency is listened on
+            let mut subscriptions = receiver.subscriptions();
+            subscriptions.sort_by(f64::total_cmp);
+            assert_eq!(subscriptions, [receiver.hop_frequency().unwrap(), 9.0]);
+        }
+        assert!(hops.windows(2).any(|w| w[0] != w[1]));
+
+        receiver.set_frequency_hopper(None);
+        assert_eq!(receiver.subscriptions(), [9.0]);
+    }
+
+    #[tokio::test]
+    async fn test_connected_nodes_fan_out() {
+        let mut a = OmegaNode::new(NodeConfig { omega: 2.0, ..NodeConfig::lossless() }).unwrap();
+        let mut b = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut c = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        a.connect(&mut b);
+        a.connect(&mut c);
+        b.connect(&mut c);
+        a.connect(&mut b);
+        assert_eq!(a.peer_count(), 2);
+
+        // Both peers on 1.0 get their own copy
+        a.send_message(b"fan out", 1.0).await.unwrap();
+        assert!(a.message_buffer.is_empty());
+        assert_eq!(b.receive_message().await.unwrap(), Some(b"fan out".to_vec()));
+        assert_eq!(c.receive_message().await.unwrap(), Some(b"fan out".to_vec()));
+        assert_eq!(a.receive_outcome().await.unwrap(), ReceiveOutcome::Empty);
+
+        // Only the peer on 2.0 can open a frame for 2.0
+        b.send_message(b"for a", 2.0).await.unwrap();
+        assert_eq!(a.receive_message().await.unwrap(), Some(b"for a".to_vec()));
+        assert_eq!(c.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_skips_closed_peer() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receivers: Vec<OmegaNode> =
+            (0..3).map(|_| OmegaNode::new(NodeConfig::lossless()).unwrap()).collect();
+        for (id, receiver) in receivers.iter().enumerate() {
+            sender.add_peer(id as PeerId, receiver.link());
+        }
+        assert_eq!(sender.peer_count(), 3);
+
+        // Closing one peer's channel does not fail the broadcast
+        drop(receivers.remove(1));
+        sender.send_message(b"to the rest", 1.0).await.unwrap();
+        for receiver in receivers.iter_mut() {
+            assert_eq!(receiver.receive_message().await.unwrap(), Some(b"to the rest".to_vec()));
+        }
+
+        assert!(sender.remove_peer(1).is_some());
+        assert!(sender.remove_peer(1).is_none());
+        assert_eq!(sender.peer_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shared_in_memory_transport() {
+        let bus = crate::transport::InMemoryTransport::new();
+        let mut sender = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+        let mut receiver = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+
+        sender.send_message(b"on the bus", 1.0).await.unwrap();
+        assert!(sender.message_buffer.is_empty());
+        assert_eq!(bus.pending(), 1);
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(b"on the bus".to_vec()));
+
+        // Garbage on the medium is a codec error, counted as a decode error
+        crate::transport::Transport::send(&bus, b"junk").await.unwrap();
+        assert!(matches!(receiver.receive_message().await, Err(OmegaError::CodecError(_))));
+        assert_eq!(receiver.metrics().decode_errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_backpressure_on_bounded_transport() {
+        let bus = crate::transport::InMemoryTransport::bounded(2);
+        let mut sender = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+        let mut receiver = OmegaNode::with_transport(NodeConfig::lossless(), Box::new(bus.clone())).unwrap();
+
+        let burst = async {
+            for i in 0..10u8 {
+                sender.send_message(&[i], 1.0).await.unwrap();
+                assert!(bus.pending() <= 2);
+            }
+        };
+        let drain = async {
+            let mut received = Vec::new();
+            while received.len() < 10 {
+                match receiver.receive_message().await.unwrap() {
+                    Some(message) => received.extend(message),
+                    None => tokio::task::yield_now().await,
+                }
+            }
+            received
+        };
+
+        let ((), received) = tokio::join!(burst, drain);
+        assert_eq!(received, (0..10).collect::<Vec<u8>>());
+    }
+
+    #[tokio::test]
+    async fn test_decode_probability_tracks_noise() {
+        let node = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        assert_eq!(node.estimate_decode_probability(0.0, 20).await, 1.0);
+        assert!(node.estimate_decode_probability(10.0, 20).await < 0.05);
+        assert_eq!(node.estimate_decode_probability(0.0, 0).await, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_guard_rejects_attack_variants() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        // Exact duplicate
+        sender.send_message(b"once", 1.0).await.unwrap();
+        let frame = sender.take_frame().unwrap();
+        receiver.push_frame(frame.clone());
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(b"once".to_vec()));
+        receiver.push_frame(frame.clone());
+        assert_eq!(
+            receiver.receive_outcome().await.unwrap(),
+            ReceiveOutcome::Replayed(ReplayKind::Duplicate)
+        );
+
+        // The recorded frame presented to a listener on another frequency
+        receiver.unsubscribe(1.0);
+        receiver.subscribe(2.0);
+        receiver.push_frame(frame);
+        assert_eq!(
+            receiver.receive_outcome().await.unwrap(),
+            ReceiveOutcome::Replayed(ReplayKind::FrequencyShifted)
+        );
+
+        // A frame from an epoch outside the acceptance window
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        receiver.set_replay_policy(ReplayPolicy { max_epoch_lag: 1, ..Default::default() });
+        receiver.set_epoch(2);
+        sender.send_message(b"old", 1.0).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(
+            receiver.receive_outcome().await.unwrap(),
+            ReceiveOutcome::Replayed(ReplayKind::StaleEpoch)
+        );
+        assert_eq!(receiver.metrics().replays_rejected, 1);
+        assert_eq!(receiver.metrics().auth_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_freq_search_recovers_one_bin_off() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig { omega: 1.5, ..NodeConfig::lossless() }).unwrap();
+        let one_bin_off = 1.5 + FREQUENCY_QUANTUM;
+
+        sender.send_message(b"slightly detuned", one_bin_off).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_message().await.unwrap(), None);
+
+        receiver.set_freq_search_window(1);
+        sender.send_message(b"slightly detuned", one_bin_off).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(b"slightly detuned".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_epoch_window_tolerates_skew() {
+        let skewed = NodeConfig { epoch_window: 1, ..NodeConfig::lossless() };
+        let mut receiver = OmegaNode::new(skewed.clone()).unwrap();
+        receiver.set_epoch(5);
+        let mut strict = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        strict.set_epoch(5);
+
+        // One epoch stale, and one epoch ahead
+        for epoch in [4, 6] {
+            let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+            sender.set_epoch(epoch);
+            sender.send_message(b"out of step", 1.0).await.unwrap();
+            sender.transfer_message_to(&mut receiver);
+            assert_eq!(receiver.receive_message().await.unwrap(), Some(b"out of step".to_vec()));
+
+            sender.send_message(b"out of step", 1.0).await.unwrap();
+            sender.transfer_message_to(&mut strict);
+            assert_eq!(strict.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        }
+
+        // Foreign traffic fails under every epoch tried: too far out of
+        // step, or keyed under another deployment's salt
+        let mut far = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        far.set_epoch(7);
+        let mut foreign = OmegaNode::new(NodeConfig { key_salt: b"elsewhere".to_vec(), ..skewed }).unwrap();
+        foreign.set_epoch(5);
+        for sender in [&mut far, &mut foreign] {
+            sender.send_message(b"not for you", 1.0).await.unwrap();
+            sender.transfer_message_to(&mut receiver);
+            assert_eq!(receiver.receive_outcome().await.unwrap(), ReceiveOutcome::AuthFailed);
+        }
+        assert_eq!(receiver.metrics().auth_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_prometheus_exposition() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        for msg in [&b"one"[..], b"two", b"three"] {
+            sender.send_message(msg, 1.0).await.unwrap();
+        }
+        sender.transfer_message_to(&mut receiver);
+        receiver.receive_message().await.unwrap();
+
+        let mut sample_values = HashMap::new();
+        let mut typed = Vec::new();
+        for line in sender.metrics_prometheus().lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let (name, help) = rest.split_once(' ').unwrap();
+                assert!(name.starts_with("omega_") && !help.is_empty());
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert_eq!(kind, "counter");
+                typed.push(name.to_string());
+            } else {
+                let (name, value) = line.split_once(' ').unwrap();
+                assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+                assert!(typed.iter().any(|t| t == name), "sample before TYPE: {}", name);
+                sample_values.insert(name.to_string(), value.parse::<f64>().unwrap());
+            }
+        }
+
+        assert_eq!(sample_values.len(), 7);
+        assert_eq!(sample_values["omega_messages_sent_total"], 3.0);
+        assert_eq!(sample_values["omega_messages_received_total"], 0.0);
+
+        assert_eq!(receiver.metrics().messages_received, 1);
+        assert!(receiver.metrics_prometheus().contains("\nomega_messages_received_total 1\n"));
+    }
+
+    #[test]
+    fn test_configured_epsilon_sets_boundary() {
+        let tone = |omega: f64| {
+            Array1::from_vec((0..1024).map(|i| (omega * i as f64).sin()).collect())
+        };
+        let node_with_epsilon = |epsilon: f64| {
+            let mut params = OmegaParams::default();
+            params.resonance.epsilon = epsilon;
+            OmegaNode::new(NodeConfig { omega: 1.0, params, ..Default::default() }).unwrap()
+        };
+
+        let mut tight = node_with_epsilon(0.001);
+        let wide = node_with_epsilon(0.5);
+        assert_eq!(tight.resonance.epsilon(), 0.001);
+        assert_eq!(wide.resonance.epsilon(), 0.5);
+
+        assert!(tight.is_resonant(&tone(1.0)));
+        assert!(!tight.is_resonant(&tone(1.2)));
+        assert!(wide.is_resonant(&tone(1.2)));
+        assert!(!wide.is_resonant(&tone(1.6)));
+
+        // Retuning keeps the configured bandwidth
+        let bin_196 = 2.0 * std::f64::consts::PI * 196.0 / 1024.0;
+        tight.set_frequency(bin_196);
+        assert_eq!(tight.resonance.epsilon(), 0.001);
+        assert!(tight.is_resonant(&tone(bin_196)));
+        assert!(!tight.is_resonant(&tone(1.0)));
+    }
+
+    #[test]
+    fn test_zero_sweep_period_rejected() {
+        let mut params = OmegaParams::default();
+        params.sweep.period = 0.0;
+        let result = OmegaNode::new(NodeConfig { params, ..Default::default() });
+        assert!(matches!(result, Err(OmegaError::ParameterError(_))));
+    }
+
+    #[test]
+    fn test_listen_frequency_bandwidths() {
+        let mut params = OmegaParams::default();
+        params.resonance.epsilon = 0.01;
+        let mut node = OmegaNode::new(NodeConfig { omega: 1.0, params, ..Default::default() }).unwrap();
+        node.add_listen_frequency_with_epsilon(2.0, 0.3);
+
+        let tone = |omega: f64| {
+            Array1::from_vec((0..1024).map(|i| (omega * i as f64).sin()).collect())
+        };
+
+        assert!(node.is_resonant(&tone(2.2)));
+        assert!(!node.is_resonant(&tone(1.05)));
+        assert!(node.is_resonant(&tone(1.0)));
+    }
+
+    #[tokio::test]
+    async fn test_lossless_round_trip_is_exact() {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        let mut payloads: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            vec![0],
+            vec![255; 6],
+            (0..=255).collect(),
+        ];
+        for _ in 0..20 {
+            let len = rng.gen_range(1..200);
+            payloads.push((0..len).map(|_| rng.gen()).collect());
+        }
+
+        for payload in &payloads {
+            sender.send_message(payload, 1.0).await.unwrap();
+            sender.transfer_message_to(&mut receiver);
+            let received = receiver.receive_message().await.unwrap();
+            assert_eq!(received.as_ref(), Some(payload));
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_sync_round_trip() {
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.connect(&mut receiver);
+
+        sender.send_message_sync(b"no runtime needed", 1.0).unwrap();
+        assert_eq!(receiver.receive_message_sync().unwrap().unwrap(), b"no runtime needed");
+        assert!(receiver.receive_message_sync().unwrap().is_none());
+
+        // A bounded medium that is full would have to wait
+        let mut bounded = OmegaNode::with_transport(
+            NodeConfig::lossless(),
+            Box::new(crate::transport::InMemoryTransport::bounded(1)),
+        )
+        .unwrap();
+        bounded.send_message_sync(b"fits", 1.0).unwrap();
+        match bounded.send_message_sync(b"full", 1.0) {
+            Err(OmegaError::NetworkError(msg)) => assert_eq!(msg, "would block"),
+            other => panic!("expected would block, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dimension_eight_round_trip() {
+        let config = NodeConfig { dimension: 8, ..NodeConfig::lossless() };
+        let mut sender = OmegaNode::new(config.clone()).unwrap();
+        let mut receiver = OmegaNode::new(config).unwrap();
+        assert_eq!(sender.dimension(), 8);
+        assert_eq!(sender.get_state().len(), 8);
+
+        sender.send_message(b"eight components per chunk", 1.0).await.unwrap();
+        sender.transfer_message_to(&mut receiver);
+        assert_eq!(receiver.receive_message().await.unwrap(), Some(b"eight components per chunk".to_vec()));
+
+        // The spectral path chunks by the configured dimension
+        let mut spectral = OmegaNode::new(NodeConfig { dimension: 8, ..NodeConfig::default() }).unwrap();
+        spectral.send_message(b"chunked", 1.0).await.unwrap();
+        assert_eq!(spectral.take_frame().unwrap().vector.len() % 8, 0);
+        assert_eq!(spectral.fork().dimension(), 8);
+
+        assert!(NodeConfig::builder().dimension(0).build().is_err());
+        assert!(NodeConfig::builder().dimension(1).build().is_err());
+        assert!(NodeConfig::builder().dimension(1).lossless(true).build().is_ok());
+        assert!(OmegaNode::new(NodeConfig { dimension: 0, ..NodeConfig::default() }).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_rate_limit() {
+        let mut node = OmegaNode::new(NodeConfig::lossless()).unwrap();
+
+        // Within the limit
+        node.set_send_rate_limit(1000.0).unwrap();
+        for _ in 0..5 {
+            node.try_send_message(b"ok", 1.0).await.unwrap();
+        }
+
+        // Faster than the limit: the burst is spent, then sends are throttled
+        node.set_send_rate_limit(20.0).unwrap();
+        for _ in 0..20 {
+            node.try_send_message(b"burst", 1.0).await.unwrap();
+        }
+        match node.try_send_message(b"over", 1.0).await {
+            Err(OmegaError::NetworkError(msg)) => assert_eq!(msg, "rate limited"),
+            other => panic!("expected rate limiting, got {:?}", other),
+        }
+
+        #[cfg(feature = "native")]
+        {
+            let start = std::time::Instant::now();
+            node.send_message(b"waits", 1.0).await.unwrap();
+            assert!(start.elapsed() >= std::time::Duration::from_millis(30));
+            assert_eq!(node.metrics().messages_sent, 26);
+        }
+        // Without a timer the send fails instead of waiting for a token
+        #[cfg(not(feature = "native"))]
+        assert!(node.send_message(b"fails", 1.0).await.is_err());
+
+        assert!(node.set_send_rate_limit(0.0).is_err());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_log_config_selects_operators() {
+        let log = LogConfig::new().with(OperatorKind::Sweep, Verbosity::Info);
+        let mut node = OmegaNode::new(NodeConfig { log, ..Default::default() }).unwrap();
+
+        node.send_message(b"trace me", 1.0).await.unwrap();
+
+        assert!(logs_contain("operator=\"sweep\""));
+        assert!(!logs_contain("operator=\"masking\""));
+        assert!(!logs_contain("operator=\"doublekick\""));
+        // Info only: no norms
+        assert!(!logs_contain("norm"));
     }
 
     #[test]
@@ -269,7 +3507,7 @@ mod tests {
         let mut node = OmegaNode::new(config).unwrap();
 
         let v = Array1::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0]);
-        let result = node.omega_transformation(v.clone());
+        let result = node.omega_transformation(v.clone()).unwrap();
 
         // Transformation should produce output
         assert_eq!(result.len(), v.len());
@@ -1,68 +1,146 @@
-/// DoubleKick Operator D̂_α
-///
-/// Dual orthogonal impulse for equilibrium escape.
-/// Enables exploration and prevents local equilibria.
+//! DoubleKick Operator D̂_α
+//!
+//! Dual orthogonal impulse for equilibrium escape.
+//! Enables exploration and prevents local equilibria.
 
 use crate::types::*;
 use crate::operators::OmegaOperator;
 use ndarray::Array1;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::cell::RefCell;
 
 pub struct DoubleKick {
-    alpha1: f64,
-    alpha2: f64,
+    alphas: Vec<f64>,
     pub eta: f64, // Perturbation magnitude bound
+    rng: RefCell<StdRng>,
+    // Kick only inputs whose L2 norm is below this (None = always kick)
+    norm_threshold: Option<f64>,
 }
 
 impl DoubleKick {
+    /// Two impulses of magnitudes `alpha1` and `alpha2`
     pub fn new(alpha1: f64, alpha2: f64) -> Self {
-        let eta = alpha1.abs() + alpha2.abs();
-        Self { alpha1, alpha2, eta }
+        Self::with_impulses(vec![alpha1, alpha2])
     }
 
-    /// Apply dual orthogonal kick
-    pub fn apply(&self, v: &OmegaVector) -> OmegaVector {
-        let dim = v.len();
-        if dim == 0 {
-            return v.clone();
+    /// One impulse per entry of `alphas`, along mutually orthonormal directions
+    pub fn with_impulses(alphas: Vec<f64>) -> Self {
+        Self::with_rng(alphas, StdRng::from_entropy())
+    }
+
+    /// Create a DoubleKick whose impulse directions are drawn from a seeded RNG,
+    /// so the same seed always produces the same sequence of perturbations
+    pub fn with_seed(alpha1: f64, alpha2: f64, seed: u64) -> Self {
+        Self::with_rng(vec![alpha1, alpha2], StdRng::seed_from_u64(seed))
+    }
+
+    /// Two impulses applied only near equilibrium: inputs whose L2 norm is
+    /// at least `norm_threshold` pass through unchanged
+    pub fn conditional(alpha1: f64, alpha2: f64, norm_threshold: f64) -> Self {
+        let mut dk = Self::new(alpha1, alpha2);
+        dk.norm_threshold = Some(norm_threshold);
+        dk
+    }
+
+    fn with_rng(alphas: Vec<f64>, rng: StdRng) -> Self {
+        let eta = alphas.iter().map(|a| a.abs()).sum();
+        Self {
+            alphas,
+            eta,
+            rng: RefCell::new(rng),
+            norm_threshold: None,
         }
+    }
 
-        // Generate two orthonormal vectors
-        let (u1, u2) = self.generate_orthonormal_basis(dim);
+    /// Same RNG state and kick condition with different impulse magnitudes,
+    /// so the copy draws the same directions this instance would draw next
+    pub fn reparameterized(&self, alphas: Vec<f64>) -> Self {
+        let mut dk = Self::with_rng(alphas, self.rng.borrow().clone());
+        dk.norm_threshold = self.norm_threshold;
+        dk
+    }
 
-        // Apply kicks: v' = v + α₁u₁ + α₂u₂
-        v + &(u1 * self.alpha1) + &(u2 * self.alpha2)
+    /// Impulse magnitudes
+    pub fn alphas(&self) -> &[f64] {
+        &self.alphas
     }
 
-    /// Generate two random orthonormal vectors using Gram-Schmidt
-    fn generate_orthonormal_basis(&self, dim: usize) -> (OmegaVector, OmegaVector) {
-        let mut rng = rand::thread_rng();
+    /// Norm below which inputs are kicked, if the kick is conditional
+    pub fn norm_threshold(&self) -> Option<f64> {
+        self.norm_threshold
+    }
 
-        // Generate first random vector and normalize
-        let mut u1 = Array1::from_vec(
-            (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect()
-        );
-        let norm1 = u1.iter().map(|x| x * x).sum::<f64>().sqrt();
-        if norm1 > 1e-10 {
-            u1 = u1 / norm1;
+    /// Whether `v` is close enough to equilibrium to be kicked
+    pub fn is_stagnant<F: Float>(&self, v: &Array1<F>) -> bool {
+        match self.norm_threshold {
+            Some(threshold) => {
+                let norm_sq: f64 = v.iter().map(|x| x.to_f64().map_or(f64::NAN, |x| x * x)).sum();
+                norm_sq.sqrt() < threshold
+            }
+            None => true,
         }
+    }
 
-        // Generate second random vector
-        let mut u2 = Array1::from_vec(
-            (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect()
-        );
+    /// Apply the orthogonal kicks
+    ///
+    /// Impulses beyond the vector dimension have no direction left and are
+    /// dropped; use `try_apply` to reject that case instead. A conditional
+    /// kick leaves inputs that are not stagnant unchanged. The impulse
+    /// directions are drawn in `f64` whatever the component width, so a
+    /// seeded kick perturbs `f32` and `f64` vectors alike.
+    pub fn apply<F: Float>(&self, v: &Array1<F>) -> Array1<F> {
+        let dim = v.len();
+        if dim == 0 || !self.is_stagnant(v) {
+            return v.clone();
+        }
 
-        // Gram-Schmidt orthogonalization: u2 = u2 - (u2·u1)u1
-        let dot_product: f64 = u1.iter().zip(u2.iter()).map(|(a, b)| a * b).sum();
-        u2 = u2 - &(u1.clone() * dot_product);
+        // v' = v + Σ αᵢuᵢ over mutually orthonormal uᵢ
+        let basis = self.generate_orthonormal_basis(dim, self.alphas.len().min(dim));
+        let mut result = v.clone();
+        for (u, &alpha) in basis.iter().zip(&self.alphas) {
+            result.zip_mut_with(u, |x, &ui| *x = *x + component(alpha * ui));
+        }
+        result
+    }
 
-        // Normalize u2
-        let norm2 = u2.iter().map(|x| x * x).sum::<f64>().sqrt();
-        if norm2 > 1e-10 {
-            u2 = u2 / norm2;
+    /// Like `apply`, but errors if there are more impulses than dimensions
+    pub fn try_apply<F: Float>(&self, v: &Array1<F>) -> Result<Array1<F>> {
+        if self.alphas.len() > v.len() {
+            return Err(OmegaError::ParameterError(format!(
+                "{} orthogonal impulses exceed vector dimension {}",
+                self.alphas.len(),
+                v.len()
+            )));
         }
+        Ok(self.apply(v))
+    }
 
-        (u1, u2)
+    /// Generate `count` random orthonormal vectors using Gram-Schmidt
+    fn generate_orthonormal_basis(&self, dim: usize, count: usize) -> Vec<OmegaVector> {
+        let mut rng = self.rng.borrow_mut();
+        let mut basis: Vec<OmegaVector> = Vec::with_capacity(count);
+
+        while basis.len() < count {
+            let mut u = Array1::from_vec(
+                (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect()
+            );
+
+            // Gram-Schmidt orthogonalization: u = u - Σ (u·bⱼ)bⱼ
+            for b in &basis {
+                let dot_product: f64 = u.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+                u.scaled_add(-dot_product, b);
+            }
+
+            // Redraw the rare nearly dependent sample
+            let norm = u.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm > 1e-10 {
+                u /= norm;
+                basis.push(u);
+            }
+        }
+
+        basis
     }
 
     /// Check if two vectors are orthogonal
@@ -92,7 +170,7 @@ impl OmegaOperator for DoubleKick {
     type Params = DoubleKickParams;
 
     fn apply(&self, input: Self::Input, _params: &Self::Params) -> Result<Self::Output> {
-        Ok(self.apply(&input))
+        self.try_apply(&input)
     }
 
     fn name(&self) -> &str {
@@ -131,14 +209,15 @@ mod tests {
     #[test]
     fn test_orthonormal_basis() {
         let dk = DoubleKick::default();
-        let (u1, u2) = dk.generate_orthonormal_basis(5);
+        let basis = dk.generate_orthonormal_basis(5, 2);
+        let (u1, u2) = (&basis[0], &basis[1]);
 
         // Check orthogonality
-        assert!(DoubleKick::are_orthogonal(&u1, &u2));
+        assert!(DoubleKick::are_orthogonal(u1, u2));
 
         // Check normalization
-        assert!(DoubleKick::is_normalized(&u1));
-        assert!(DoubleKick::is_normalized(&u2));
+        assert!(DoubleKick::is_normalized(u1));
+        assert!(DoubleKick::is_normalized(u2));
     }
 
     #[test]
@@ -155,4 +234,55 @@ mod tests {
         let lipschitz = dk.lipschitz_constant();
         assert!(result_norm <= lipschitz * v_norm + 0.1);
     }
+
+    #[test]
+    fn test_seeded_kicks_reproducible() {
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let a = DoubleKick::with_seed(0.05, -0.03, 7);
+        let b = DoubleKick::with_seed(0.05, -0.03, 7);
+        let c = DoubleKick::with_seed(0.05, -0.03, 8);
+
+        for _ in 0..3 {
+            let (x, y, z) = (a.apply(&v), b.apply(&v), c.apply(&v));
+            let bits = |r: &OmegaVector| r.iter().map(|e| e.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&x), bits(&y));
+            assert_ne!(bits(&x), bits(&z));
+        }
+    }
+
+    #[test]
+    fn test_conditional_kicks_only_stagnant_vectors() {
+        let dk = DoubleKick::conditional(0.1, -0.05, 0.5);
+        assert_eq!(dk.norm_threshold(), Some(0.5));
+
+        let healthy = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(dk.apply(&healthy), healthy);
+
+        let stagnant = arr1(&[1e-3, 0.0, -1e-3, 0.0, 0.0]);
+        assert_ne!(dk.apply(&stagnant), stagnant);
+        assert!(DoubleKick::new(0.1, -0.05).is_stagnant(&healthy));
+    }
+
+    #[test]
+    fn test_n_impulse_basis_pairwise_orthogonal() {
+        let dk = DoubleKick::with_impulses(vec![0.1, -0.2, 0.05, 0.3]);
+        assert!((dk.lipschitz_constant() - 1.65).abs() < 1e-12);
+
+        let basis = dk.generate_orthonormal_basis(6, 4);
+        assert_eq!(basis.len(), 4);
+        for (i, u) in basis.iter().enumerate() {
+            assert!(DoubleKick::is_normalized(u));
+            for w in &basis[i + 1..] {
+                assert!(DoubleKick::are_orthogonal(u, w));
+            }
+        }
+
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(dk.try_apply(&v).unwrap().len(), 6);
+        assert!(dk.inverse(v.clone(), &DoubleKickParams::default()).is_err());
+        assert!(matches!(
+            dk.try_apply(&arr1(&[1.0, 2.0, 3.0])),
+            Err(OmegaError::ParameterError(_))
+        ));
+    }
 }
@@ -1,67 +1,349 @@
-/// Masking Operator M̂_θ,σ
-///
-/// Provides information-theoretic encryption via permutation-rotation composition.
-/// The operator is self-inverse: M̂ ∘ M̂ = I
+//! Masking Operator M̂_θ,σ
+//!
+//! Encrypts with a σ-keyed byte permutation and a ChaCha20 or AES-256-CTR
+//! keystream (key derived from σ and the per-message nonce, nonce from θ and
+//! the counter), followed by a configurable number of keyed diffusion
+//! rounds. The toy `MaskingMode::Xor` keeps the keystream alone. Every
+//! layer is undone explicitly by `unmask`. With
+//! `MaskingParams::authenticated`, a truncated HMAC-SHA256 tag over the
+//! masked bytes and any associated data sent in the clear (such as the
+//! frame header) is appended and checked before unmasking.
 
 use crate::types::*;
 use crate::operators::OmegaOperator;
-use rand::{Rng, SeedableRng};
-use rand::rngs::StdRng;
+use aes::Aes256;
+use chacha20::ChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use ctr::Ctr32BE;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::VecDeque;
+
+/// Length of the authentication tag appended in authenticated mode
+pub const TAG_LEN: usize = 16;
 
-pub struct MaskingOperator;
+/// Nonce reserved for deriving the byte permutation from σ
+const PERMUTATION_NONCE: [u8; 12] = *b"omega-perm\0\0";
+
+pub struct MaskingOperator {
+    /// Apply the σ-keyed byte permutation; off only for `MaskingMode::Xor`
+    permute: bool,
+}
 
 impl MaskingOperator {
     pub fn new() -> Self {
-        Self
+        Self { permute: true }
+    }
+
+    /// Operator for frames of `mode`: the toy `MaskingMode::Xor` masks with
+    /// the bare keystream, every other mode with the full layer stack
+    pub fn for_mode(mode: MaskingMode) -> Self {
+        Self { permute: mode != MaskingMode::Xor }
     }
 
     /// Mask (encrypt) a message
     pub fn mask(&self, message: &[u8], params: &MaskingParams) -> Result<Vec<u8>> {
+        self.mask_with_aad(message, params, &[])
+    }
+
+    /// Mask a message whose authentication tag also covers `aad`,
+    /// associated data that travels unmasked next to it
+    pub fn mask_with_aad(&self, message: &[u8], params: &MaskingParams, aad: &[u8]) -> Result<Vec<u8>> {
         let mut result = message.to_vec();
 
-        // Step 1: Apply permutation U_σ
-        self.permute(&mut result, &params.sigma);
+        // Step 1: Apply keyed byte permutation U_σ
+        if self.permute {
+            self.permute(&mut result, &params.sigma);
+        }
+
+        // Step 2: Apply phase rotation R_θ (keystream)
+        self.rotate(&mut result, params);
 
-        // Step 2: Apply phase rotation R_θ (XOR-based)
-        self.rotate(&mut result, params.theta);
+        // Step 3: Diffusion rounds
+        for round in 0..params.rounds {
+            self.diffuse(&mut result, &params.sigma, round);
+        }
+
+        // Step 4: Authentication tag (opt-in)
+        if params.authenticated {
+            let tag = Self::tag_mac(&result, params, aad).finalize().into_bytes();
+            result.extend_from_slice(&tag[..TAG_LEN]);
+        }
 
         Ok(result)
     }
 
-    /// Unmask (decrypt) a message - same as mask due to involution property
+    /// Unmask (decrypt) a message
+    ///
+    /// In authenticated mode the tag is verified first, and a missing or
+    /// mismatching tag yields `MaskingError(AUTHENTICATION_FAILED)`.
     pub fn unmask(&self, masked: &[u8], params: &MaskingParams) -> Result<Vec<u8>> {
-        // M̂ ∘ M̂ = I, so unmask = mask
-        self.mask(masked, params)
+        self.unmask_with_aad(masked, params, &[])
     }
 
-    /// Apply simple XOR-based permutation with seed σ (self-inverse)
-    fn permute(&self, data: &mut [u8], sigma: &[u8; 32]) {
-        if data.is_empty() {
+    /// Unmask a message masked by `mask_with_aad`; the tag only verifies
+    /// with the same `aad`
+    pub fn unmask_with_aad(&self, masked: &[u8], params: &MaskingParams, aad: &[u8]) -> Result<Vec<u8>> {
+        let authentication_failed = || OmegaError::MaskingError(AUTHENTICATION_FAILED.to_string());
+
+        let mut result = if params.authenticated {
+            if masked.len() < TAG_LEN {
+                return Err(authentication_failed());
+            }
+            let (body, tag) = masked.split_at(masked.len() - TAG_LEN);
+            Self::tag_mac(body, params, aad)
+                .verify_truncated_left(tag)
+                .map_err(|_| authentication_failed())?;
+            body.to_vec()
+        } else {
+            masked.to_vec()
+        };
+
+        // Undo diffusion rounds in reverse order
+        for round in (0..params.rounds).rev() {
+            self.undiffuse(&mut result, &params.sigma, round);
+        }
+
+        // The keystream XOR is an involution; the permutation is inverted
+        self.rotate(&mut result, params);
+        if self.permute {
+            self.unpermute(&mut result, &params.sigma);
+        }
+
+        Ok(result)
+    }
+
+    /// One reversible diffusion round: every byte absorbs a keyed S-box of the
+    /// byte `2^round` positions before it, then the buffer is rotated by a keyed offset
+    fn diffuse(&self, data: &mut [u8], sigma: &[u8; 32], round: u8) {
+        let len = data.len();
+        if len < 2 {
             return;
         }
+        let key = sigma[round as usize % 32];
+        let stride = Self::round_stride(round, len);
 
-        // Simple XOR permutation (self-inverse)
-        for (i, byte) in data.iter_mut().enumerate() {
-            *byte ^= sigma[i % 32];
+        // Walk backwards so each source byte is still unmixed when read
+        for i in (stride..len).rev() {
+            data[i] ^= Self::sbox(data[i - stride], key);
         }
+
+        data.rotate_left(key as usize % len);
     }
 
-    /// Apply phase rotation via XOR with pseudo-random stream derived from θ
-    fn rotate(&self, data: &mut [u8], theta: f64) {
-        // Derive seed from theta
-        let theta_bits = theta.to_bits();
-        let mut seed = [0u8; 32];
-        for i in 0..32 {
-            seed[i] = ((theta_bits >> (i % 8)) & 0xFF) as u8;
+    /// Inverse of `diffuse`
+    fn undiffuse(&self, data: &mut [u8], sigma: &[u8; 32], round: u8) {
+        let len = data.len();
+        if len < 2 {
+            return;
+        }
+        let key = sigma[round as usize % 32];
+        let stride = Self::round_stride(round, len);
+
+        data.rotate_right(key as usize % len);
+
+        // Walk forwards so each source byte has already been restored
+        for i in stride..len {
+            data[i] ^= Self::sbox(data[i - stride], key);
         }
+    }
+
+    /// Mixing distance for a round: 1, 2, 4, ... (wrapped to the buffer length)
+    fn round_stride(round: u8, len: usize) -> usize {
+        let stride = 1usize << (round % 8);
+        1 + (stride - 1) % (len - 1)
+    }
+
+    /// Keyed non-linear byte mixing function
+    fn sbox(byte: u8, key: u8) -> u8 {
+        let mut x = (byte ^ key).wrapping_mul(0x9D);
+        x ^= x >> 4;
+        x = x.wrapping_mul(0x3B);
+        x ^ (x >> 3)
+    }
 
-        let mut rng = StdRng::from_seed(seed);
+    /// HMAC-SHA256 keyed by σ over θ, the counter, the number of rounds,
+    /// the per-message nonce, the keystream cipher, the length-prefixed
+    /// associated data and the masked bytes
+    ///
+    /// Frame metadata (masking mode, compression, record count, addressing)
+    /// is authenticated as part of the encoded header passed as `aad`.
+    fn tag_mac(masked: &[u8], params: &MaskingParams, aad: &[u8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&params.sigma[..])
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"omega-auth");
+        mac.update(&params.theta.to_bits().to_le_bytes());
+        mac.update(&params.counter.to_le_bytes());
+        mac.update(&[params.rounds]);
+        mac.update(&params.nonce);
+        mac.update(&[params.cipher as u8]);
+        mac.update(&(aad.len() as u64).to_le_bytes());
+        mac.update(aad);
+        mac.update(masked);
+        mac
+    }
 
-        // XOR each byte with pseudo-random stream
+    /// Substitute every byte through the σ-keyed permutation of byte values
+    fn permute(&self, data: &mut [u8], sigma: &[u8; 32]) {
+        let table = Self::permutation_table(sigma);
         for byte in data.iter_mut() {
-            *byte ^= rng.gen::<u8>();
+            *byte = table[*byte as usize];
         }
     }
+
+    /// Inverse of `permute`
+    fn unpermute(&self, data: &mut [u8], sigma: &[u8; 32]) {
+        let inverse = Self::inverse_table(&Self::permutation_table(sigma));
+        for byte in data.iter_mut() {
+            *byte = inverse[*byte as usize];
+        }
+    }
+
+    /// XOR with the keystream for (σ, θ) (self-inverse)
+    fn rotate(&self, data: &mut [u8], params: &MaskingParams) {
+        Self::keystream(params).apply(data);
+    }
+
+    /// Keystream of `params.cipher` keyed by `message_key`, with the 96-bit
+    /// nonce made of θ's bit pattern and the message counter. θ is itself
+    /// derived from frequency and epoch, so every (frequency, epoch, counter)
+    /// triple gets a distinct keystream from one sender; the random
+    /// per-message nonce in the key keeps senders sharing a frequency and
+    /// epoch apart too. AES-CTR takes the nonce as the top 96 bits of its
+    /// counter block and counts blocks in the low 32.
+    fn keystream(params: &MaskingParams) -> Keystream {
+        let mut nonce = [0u8; 12];
+        nonce[..8].copy_from_slice(&params.theta.to_bits().to_le_bytes());
+        nonce[8..].copy_from_slice(&params.counter.to_le_bytes());
+        let key = Self::message_key(params);
+        match params.cipher {
+            KeystreamCipher::ChaCha20 => Keystream::ChaCha20(ChaCha20::new((&*key).into(), &nonce.into())),
+            KeystreamCipher::Aes256Ctr => {
+                let mut block = [0u8; 16];
+                block[..12].copy_from_slice(&nonce);
+                Keystream::AesCtr(Box::new(Ctr32BE::new((&*key).into(), &block.into())))
+            }
+        }
+    }
+
+    /// Keystream key of one message: HMAC-SHA256(σ, "omega-nonce" ‖ nonce)
+    fn message_key(params: &MaskingParams) -> Sigma {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&params.sigma[..])
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"omega-nonce");
+        mac.update(&params.nonce);
+        Sigma::new(mac.finalize().into_bytes().into())
+    }
+
+    /// Bijection on byte values: Fisher-Yates shuffle driven by a ChaCha20
+    /// keystream under σ and a reserved nonce
+    fn permutation_table(sigma: &[u8; 32]) -> [u8; 256] {
+        let mut randomness = [0u8; 4 * 256];
+        ChaCha20::new(sigma.into(), &PERMUTATION_NONCE.into()).apply_keystream(&mut randomness);
+
+        let mut table = [0u8; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        for i in (1..256).rev() {
+            let word = &randomness[4 * i..4 * i + 4];
+            let j = u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as usize % (i + 1);
+            table.swap(i, j);
+        }
+
+        table
+    }
+
+    fn inverse_table(table: &[u8; 256]) -> [u8; 256] {
+        let mut inverse = [0u8; 256];
+        for (i, &t) in table.iter().enumerate() {
+            inverse[t as usize] = i as u8;
+        }
+        inverse
+    }
+}
+
+/// Keystream generator of one message, by `KeystreamCipher`
+enum Keystream {
+    ChaCha20(ChaCha20),
+    // Boxed: the expanded AES key schedule is large
+    AesCtr(Box<Ctr32BE<Aes256>>),
+}
+
+impl Keystream {
+    /// XOR the next `data.len()` keystream bytes into `data`
+    fn apply(&mut self, data: &mut [u8]) {
+        match self {
+            Keystream::ChaCha20(cipher) => cipher.apply_keystream(data),
+            Keystream::AesCtr(cipher) => cipher.apply_keystream(data),
+        }
+    }
+}
+
+/// Incremental masking of arbitrarily long input
+///
+/// Applies the position-wise layers (byte permutation and keystream),
+/// tracking the keystream position so chunk boundaries do not matter.
+/// Diffusion rounds mix across the whole buffer and cannot be streamed, so
+/// only `rounds = 0` parameters are accepted; the result then equals `mask`
+/// (or `unmask` for a stream created with `unmasking`). Authenticated
+/// parameters are rejected too, since a tag only makes sense for a
+/// complete message.
+pub struct MaskingStream {
+    table: [u8; 256],
+    keystream: Keystream,
+    unmasking: bool,
+    offset: u64,
+}
+
+impl MaskingStream {
+    /// Stream that masks
+    pub fn new(params: &MaskingParams) -> Result<Self> {
+        Self::with_direction(params, false)
+    }
+
+    /// Stream that unmasks
+    pub fn unmasking(params: &MaskingParams) -> Result<Self> {
+        Self::with_direction(params, true)
+    }
+
+    fn with_direction(params: &MaskingParams, unmasking: bool) -> Result<Self> {
+        if params.rounds != 0 {
+            return Err(OmegaError::MaskingError(
+                "Diffusion rounds need the whole buffer; streaming requires rounds = 0".to_string()
+            ));
+        }
+        if params.authenticated {
+            return Err(OmegaError::MaskingError(
+                "Authenticated masking cannot be streamed".to_string()
+            ));
+        }
+
+        let table = MaskingOperator::permutation_table(&params.sigma);
+        Ok(Self {
+            table: if unmasking { MaskingOperator::inverse_table(&table) } else { table },
+            keystream: MaskingOperator::keystream(params),
+            unmasking,
+            offset: 0,
+        })
+    }
+
+    /// Process the next chunk in place
+    pub fn update(&mut self, chunk: &mut [u8]) {
+        if self.unmasking {
+            self.keystream.apply(chunk);
+            chunk.iter_mut().for_each(|b| *b = self.table[*b as usize]);
+        } else {
+            chunk.iter_mut().for_each(|b| *b = self.table[*b as usize]);
+            self.keystream.apply(chunk);
+        }
+        self.offset += chunk.len() as u64;
+    }
+
+    /// Finish the stream, returning the total number of bytes processed
+    pub fn finalize(self) -> u64 {
+        self.offset
+    }
 }
 
 impl Default for MaskingOperator {
@@ -70,6 +352,91 @@ impl Default for MaskingOperator {
     }
 }
 
+/// Number of derived key schedules kept by `KeyScheduleCache`
+pub const KEY_CACHE_CAPACITY: usize = 16;
+
+/// Small LRU cache of ephemeral masking parameters keyed by
+/// `(quantized frequency, epoch)`, avoiding a key derivation per message
+#[derive(Clone, Debug, Default)]
+pub struct KeyScheduleCache {
+    entries: VecDeque<((i64, u64), MaskingParams)>,
+    derivations: u64,
+    salt: Vec<u8>,
+    theta_steps: u32,
+}
+
+impl KeyScheduleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache deriving every schedule under `salt`
+    pub fn with_salt(salt: Vec<u8>) -> Self {
+        Self {
+            salt,
+            ..Self::default()
+        }
+    }
+
+    /// Quantize every derived θ to `steps` discrete phases (0 = continuous)
+    pub fn with_theta_steps(mut self, steps: u32) -> Self {
+        self.theta_steps = steps;
+        self.entries.clear();
+        self
+    }
+
+    /// Salt mixed into the derivations
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// Number of discrete phases θ is quantized to (0 = continuous)
+    pub fn theta_steps(&self) -> u32 {
+        self.theta_steps
+    }
+
+    /// Get the parameters for `omega` at `epoch`, deriving them on a miss
+    pub fn get(&mut self, omega: f64, epoch: u64) -> MaskingParams {
+        let key = (quantize_frequency(omega), epoch);
+
+        if let Some(pos) = self.entries.iter().position(|(k, _)| *k == key) {
+            // Move to front (most recently used)
+            let entry = self.entries.remove(pos).expect("position is in range");
+            let params = entry.1.clone();
+            self.entries.push_front(entry);
+            return params;
+        }
+
+        let mut params = MaskingParams::ephemeral_from_bin_with_salt(key.0, key.1, &self.salt);
+        params.quantize_theta(self.theta_steps);
+        self.derivations += 1;
+
+        self.entries.push_front((key, params.clone()));
+        self.entries.truncate(KEY_CACHE_CAPACITY);
+
+        params
+    }
+
+    /// Drop all cached schedules
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Number of cached schedules
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total number of key derivations performed (cache misses)
+    pub fn derivations(&self) -> u64 {
+        self.derivations
+    }
+}
+
 impl OmegaOperator for MaskingOperator {
     type Input = Vec<u8>;
     type Output = Vec<u8>;
@@ -79,6 +446,10 @@ impl OmegaOperator for MaskingOperator {
         self.mask(&input, params)
     }
 
+    fn inverse(&self, output: Self::Output, params: &Self::Params) -> Result<Self::Input> {
+        self.unmask(&output, params)
+    }
+
     fn name(&self) -> &str {
         "Masking"
     }
@@ -92,14 +463,86 @@ impl OmegaOperator for MaskingOperator {
 mod tests {
     use super::*;
 
+    /// Unauthenticated keystream-only parameters with σ filled by `seed`;
+    /// tests override the fields they exercise
+    fn params(seed: u8) -> MaskingParams {
+        MaskingParams {
+            theta: 0.5,
+            sigma: Sigma::new([seed; 32]),
+            rounds: 0,
+            authenticated: false,
+            counter: 0,
+            nonce: [0; 12],
+            cipher: KeystreamCipher::ChaCha20,
+        }
+    }
+
+    #[test]
+    fn test_stream_matches_one_shot() {
+        let operator = MaskingOperator::new();
+        let mut params = params(9);
+        params.theta = 0.77;
+        let message: Vec<u8> = (0..1000u32).map(|i| (i * 31 % 251) as u8).collect();
+
+        let mut buffer = message.clone();
+        let mut stream = MaskingStream::new(&params).unwrap();
+        let (first, rest) = buffer.split_at_mut(7);
+        let (second, third) = rest.split_at_mut(500);
+        stream.update(first);
+        stream.update(second);
+        stream.update(third);
+        assert_eq!(stream.finalize(), 1000);
+
+        assert_eq!(buffer, operator.mask(&message, &params).unwrap());
+
+        let mut unmasker = MaskingStream::unmasking(&params).unwrap();
+        let (first, second) = buffer.split_at_mut(333);
+        unmasker.update(first);
+        unmasker.update(second);
+        assert_eq!(buffer, message);
+
+        let mut diffused = params.clone();
+        diffused.rounds = 1;
+        assert!(MaskingStream::new(&diffused).is_err());
+    }
+
+    #[test]
+    fn test_aes_ctr_keystream() {
+        let operator = MaskingOperator::new();
+        let message: Vec<u8> = (0..100u8).collect();
+        let mut params = params(5);
+        params.authenticated = true;
+        MaskingMode::AesCtr.apply(&mut params);
+        assert_eq!((params.rounds, params.cipher), (0, KeystreamCipher::Aes256Ctr));
+
+        let masked = operator.mask(&message, &params).unwrap();
+        assert_eq!(operator.unmask(&masked, &params).unwrap(), message);
+
+        // Same key and nonce under ChaCha20: a different keystream, and the
+        // cipher is bound into the tag
+        let mut chacha = params.clone();
+        chacha.cipher = KeystreamCipher::ChaCha20;
+        let chacha_masked = operator.mask(&message, &chacha).unwrap();
+        assert_ne!(chacha_masked[..message.len()], masked[..message.len()]);
+        assert!(operator.unmask(&masked, &chacha).unwrap_err().is_authentication_failure());
+
+        // The counter-mode keystream streams across block boundaries
+        params.authenticated = false;
+        let mut buffer = message.clone();
+        let mut stream = MaskingStream::new(&params).unwrap();
+        let (first, rest) = buffer.split_at_mut(13);
+        stream.update(first);
+        stream.update(rest);
+        assert_eq!(buffer, operator.mask(&message, &params).unwrap());
+    }
+
     #[test]
     fn test_masking_involution() {
         let operator = MaskingOperator::new();
         let message = b"Hello, OMEGA Protocol!";
-        let params = MaskingParams {
-            theta: 1.234,
-            sigma: [42u8; 32],
-        };
+        let mut params = params(42);
+        params.theta = 1.234;
+        params.rounds = 4;
 
         // Encrypt
         let masked = operator.mask(message, &params).unwrap();
@@ -112,6 +555,194 @@ mod tests {
 
         // Verify involution property
         assert_eq!(unmasked, message);
+
+        // The trait inverse undoes the trait apply exactly
+        let masked = OmegaOperator::apply(&operator, message.to_vec(), &params).unwrap();
+        assert_eq!(operator.inverse(masked, &params).unwrap(), message);
+    }
+
+    fn avalanche_ratio(operator: &MaskingOperator, params: &MaskingParams) -> f64 {
+        let message: Vec<u8> = (0..64u8).collect();
+        let base = operator.mask(&message, params).unwrap();
+
+        let mut flipped_bits = 0u32;
+        let mut trials = 0u32;
+        for pos in 0..message.len() {
+            let mut altered = message.clone();
+            altered[pos] ^= 0x01;
+            let masked = operator.mask(&altered, params).unwrap();
+            flipped_bits += base
+                .iter()
+                .zip(masked.iter())
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum::<u32>();
+            trials += 1;
+        }
+
+        flipped_bits as f64 / (trials as f64 * base.len() as f64 * 8.0)
+    }
+
+    #[test]
+    fn test_masking_rounds_invertible() {
+        let operator = MaskingOperator::new();
+        let message: Vec<u8> = (0..200u16).map(|i| (i * 7 % 256) as u8).collect();
+
+        for rounds in [0u8, 1, 3, 8, 20] {
+            let mut params = params(9);
+            params.rounds = rounds;
+            let masked = operator.mask(&message, &params).unwrap();
+            let unmasked = operator.unmask(&masked, &params).unwrap();
+            assert_eq!(unmasked, message, "rounds = {}", rounds);
+        }
+    }
+
+    #[test]
+    fn test_more_rounds_increase_avalanche() {
+        let operator = MaskingOperator::new();
+        let mut sigma = [0u8; 32];
+        for (i, byte) in sigma.iter_mut().enumerate() {
+            *byte = (i as u8).wrapping_mul(37).wrapping_add(11);
+        }
+
+        let ratios: Vec<f64> = [0u8, 1, 3, 8]
+            .iter()
+            .map(|&rounds| {
+                let mut params = params(0);
+                params.sigma = Sigma::new(sigma);
+                params.rounds = rounds;
+                avalanche_ratio(&operator, &params)
+            })
+            .collect();
+
+        for pair in ratios.windows(2) {
+            assert!(pair[1] > pair[0], "avalanche ratios not increasing: {:?}", ratios);
+        }
+        // Without diffusion a flipped bit stays confined to its byte
+        assert!(ratios[0] > 0.0 && ratios[0] <= 8.0 / 512.0);
+    }
+
+    /// Fraction of byte positions where `c1 ^ c2 == m1 ^ m2` over random
+    /// equal-length plaintext pairs masked by `mask` under one fixed key
+    fn xor_relation_rate(mask: impl Fn(&[u8]) -> Vec<u8>) -> f64 {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(17);
+        let (mut hits, mut total) = (0usize, 0usize);
+        for _ in 0..64 {
+            let m1: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+            let m2: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+            let (c1, c2) = (mask(&m1), mask(&m2));
+            for i in 0..m1.len() {
+                hits += usize::from(c1[i] ^ c2[i] == m1[i] ^ m2[i]);
+                total += 1;
+            }
+        }
+        hits as f64 / total as f64
+    }
+
+    #[test]
+    fn test_xor_relation_holds_only_for_xor_mode() {
+        let mut params = MaskingParams::ephemeral_from_frequency(1.0, 0);
+        params.authenticated = false;
+        let rate = |mode: MaskingMode| {
+            let mut params = params.clone();
+            mode.apply(&mut params);
+            let operator = MaskingOperator::for_mode(mode);
+            xor_relation_rate(|m| operator.mask(m, &params).unwrap())
+        };
+
+        // The toy mode is a plain XOR cipher: the relation always holds
+        assert_eq!(rate(MaskingMode::Xor), 1.0);
+
+        // The cipher modes do not leak plaintext XORs (chance level is 1/256)
+        assert!(rate(MaskingMode::AesCtr) < 0.02, "AES-CTR leaks the plaintext XOR");
+        for mode in [MaskingMode::Diffused, MaskingMode::Stream] {
+            assert!(rate(mode) < 0.02, "{:?} leaks the plaintext XOR", mode);
+        }
+    }
+
+    #[test]
+    fn test_different_sigma_diverges() {
+        let operator = MaskingOperator::new();
+        let message = vec![0u8; 8192];
+        let (params_a, params_b) = (params(1), params(2));
+
+        let a = operator.mask(&message, &params_a).unwrap();
+        let b = operator.mask(&message, &params_b).unwrap();
+
+        // Histogram of a ^ b: uniform for independent keystreams, a single
+        // spike at 0 for identical ones
+        let mut histogram = [0usize; 256];
+        for (x, y) in a.iter().zip(b.iter()) {
+            histogram[(x ^ y) as usize] += 1;
+        }
+        let expected = message.len() as f64 / 256.0;
+        let chi_square: f64 = histogram
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // 255 degrees of freedom; a spike would give ~2 million
+        assert!(chi_square < 400.0, "chi-square {}", chi_square);
+
+        // Total variation distance from the identical-key (all-zero) histogram
+        let tv = 1.0 - histogram[0] as f64 / message.len() as f64;
+        assert!(tv > 0.99);
+    }
+
+    #[test]
+    fn test_permutation_is_bijection() {
+        let mut seen = [false; 256];
+        for &t in MaskingOperator::permutation_table(&[7u8; 32]).iter() {
+            seen[t as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_ne!(
+            MaskingOperator::permutation_table(&[7u8; 32]),
+            MaskingOperator::permutation_table(&[8u8; 32])
+        );
+    }
+
+    #[test]
+    fn test_authenticated_masking_detects_tampering() {
+        let operator = MaskingOperator::new();
+        let params = MaskingParams::ephemeral_from_frequency(1.5, 3);
+        assert!(params.authenticated);
+        let message = b"authenticated payload";
+
+        let masked = operator.mask(message, &params).unwrap();
+        assert_eq!(masked.len(), message.len() + TAG_LEN);
+        assert_eq!(operator.unmask(&masked, &params).unwrap(), message);
+
+        for pos in 0..masked.len() {
+            let mut tampered = masked.clone();
+            tampered[pos] ^= 0x01;
+            let err = operator.unmask(&tampered, &params).unwrap_err();
+            assert!(err.is_authentication_failure(), "byte {}: {:?}", pos, err);
+        }
+
+        // Wrong key and truncated input are rejected as well
+        let other = MaskingParams::ephemeral_from_frequency(1.5, 4);
+        assert!(operator.unmask(&masked, &other).unwrap_err().is_authentication_failure());
+        assert!(operator.unmask(&masked[..TAG_LEN - 1], &params).is_err());
+    }
+
+    #[test]
+    fn test_key_cache_lru() {
+        let mut cache = KeyScheduleCache::new();
+
+        let first = cache.get(1.5, 0);
+        let again = cache.get(1.5, 0);
+        assert_eq!(cache.derivations(), 1);
+        assert_eq!(first.sigma, again.sigma);
+        assert_eq!(first.sigma, MaskingParams::ephemeral_from_frequency(1.5, 0).sigma);
+
+        // Fill past capacity: the least recently used entry is evicted
+        for i in 0..KEY_CACHE_CAPACITY as u64 {
+            cache.get(2.0, i + 1);
+        }
+        assert_eq!(cache.len(), KEY_CACHE_CAPACITY);
+        let misses = cache.derivations();
+        cache.get(1.5, 0);
+        assert_eq!(cache.derivations(), misses + 1);
     }
 
     #[test]
@@ -127,4 +758,91 @@ mod tests {
         // Different epoch produces different params
         assert_ne!(params1.sigma, params3.sigma);
     }
+
+    #[test]
+    fn test_sigma_and_theta_derived_independently() {
+        // The θ seed is expanded separately from σ, so the top byte of the
+        // seed shows no relation to σ's bytes (it used to be a copy of them)
+        let samples: Vec<(f64, f64)> = (0..512)
+            .map(|bin| {
+                let params = MaskingParams::ephemeral_from_bin(bin, 0);
+                let seed_top = (params.theta / (2.0 * std::f64::consts::PI) * 256.0).floor().min(255.0);
+                (params.sigma[7] as f64, seed_top)
+            })
+            .collect();
+
+        let n = samples.len() as f64;
+        let (mx, my) = samples.iter().fold((0.0, 0.0), |(x, y), s| (x + s.0 / n, y + s.1 / n));
+        let (mut cov, mut vx, mut vy) = (0.0, 0.0, 0.0);
+        for (x, y) in &samples {
+            cov += (x - mx) * (y - my);
+            vx += (x - mx).powi(2);
+            vy += (y - my).powi(2);
+        }
+        let correlation = cov / (vx * vy).sqrt();
+        assert!(correlation.abs() < 0.15, "correlation {}", correlation);
+        assert!(samples.iter().filter(|(x, y)| x == y).count() < 16);
+    }
+
+    #[test]
+    fn test_salt_changes_sigma_and_theta() {
+        let plain = MaskingParams::ephemeral_from_bin(42, 3);
+        let salted = MaskingParams::ephemeral_from_bin_with_salt(42, 3, b"deployment-a");
+        let other = MaskingParams::ephemeral_from_bin_with_salt(42, 3, b"deployment-b");
+
+        assert_eq!(plain, MaskingParams::ephemeral_from_bin_with_salt(42, 3, &[]));
+        assert_eq!(salted, MaskingParams::ephemeral_from_bin_with_salt(42, 3, b"deployment-a"));
+        for (a, b) in [(&plain, &salted), (&salted, &other)] {
+            assert_ne!(a.sigma, b.sigma);
+            assert_ne!(a.theta, b.theta);
+        }
+
+        let mut cache = KeyScheduleCache::with_salt(b"deployment-a".to_vec());
+        assert_eq!(cache.get(0.042, 3).sigma, salted.sigma);
+    }
+
+    #[test]
+    fn test_theta_steps_snap_to_discrete_phases() {
+        let step = 2.0 * std::f64::consts::PI / 256.0;
+        let mut cache = KeyScheduleCache::new().with_theta_steps(256);
+        let mut again = KeyScheduleCache::new().with_theta_steps(256);
+        let mut phases = std::collections::BTreeSet::new();
+
+        for epoch in 0..64 {
+            let params = cache.get(1.5, epoch);
+            let k = params.theta / step;
+            assert!((k - k.round()).abs() < 1e-9 && (0.0..256.0).contains(&k.round()), "theta {}", params.theta);
+            phases.insert(k.round() as u32);
+
+            // Deterministic, and σ is untouched
+            assert_eq!(params, again.get(1.5, epoch));
+            assert_eq!(params.sigma, MaskingParams::ephemeral_from_frequency(1.5, epoch).sigma);
+        }
+        assert!(phases.len() > 32);
+
+        // 0 steps keeps θ continuous
+        let mut params = MaskingParams::ephemeral_from_frequency(1.5, 0);
+        params.quantize_theta(0);
+        assert_eq!(params, KeyScheduleCache::new().get(1.5, 0));
+    }
+
+    #[test]
+    fn test_key_material_zeroized_and_redacted() {
+        use zeroize::{Zeroize, Zeroizing};
+
+        let mut params = Zeroizing::new(MaskingParams::ephemeral_from_frequency(1.5, 2));
+        assert!(params.sigma.iter().any(|&b| b != 0) && params.theta != 0.0);
+
+        // Neither the bytes nor any of their values show up in Debug output
+        let debug = format!("{:?}", *params);
+        assert!(debug.contains("Sigma(<redacted>)"));
+        assert!(!debug.contains(&format!("{:?}", *params.sigma)));
+
+        let copy = params.clone();
+        params.zeroize();
+        assert_eq!(*params.sigma, [0u8; 32]);
+        assert_eq!(params.theta, 0.0);
+        // Clones own their key material
+        assert_eq!(*copy, MaskingParams::ephemeral_from_frequency(1.5, 2));
+    }
 }
@@ -1,4 +1,4 @@
-/// OMEGA Operator implementations
+//! OMEGA Operator implementations
 pub mod masking;
 pub mod resonance;
 pub mod sweep;
@@ -8,7 +8,16 @@ pub mod doublekick;
 
 use crate::types::*;
 
+/// Message of the `ParameterError` returned by operators without an inverse
+pub const NO_INVERSE: &str = "no inverse";
+
 /// Core trait for OMEGA operators
+///
+/// Invertibility: masking is inverted exactly; sweep is inverted by dividing
+/// out the gate wherever it is open; pfadinvarianz is a projection and is
+/// only inverted on its range. Resonance (zeroes off-band input), weight
+/// transfer (smooths away detail) and DoubleKick (random impulses) keep the
+/// default and report `NO_INVERSE`.
 pub trait OmegaOperator {
     type Input;
     type Output;
@@ -17,6 +26,11 @@ pub trait OmegaOperator {
     /// Apply the operator to input with given parameters
     fn apply(&self, input: Self::Input, params: &Self::Params) -> Result<Self::Output>;
 
+    /// Recover an input that `apply` maps to `output`
+    fn inverse(&self, _output: Self::Output, _params: &Self::Params) -> Result<Self::Input> {
+        Err(OmegaError::ParameterError(NO_INVERSE.to_string()))
+    }
+
     /// Get the operator name
     fn name(&self) -> &str;
 
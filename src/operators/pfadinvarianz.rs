@@ -1,30 +1,144 @@
-/// Pfadinvarianz Operator P̂_Γ
-///
-/// Path-invariant projection ensuring determinism.
-/// Idempotent operator: P̂ ∘ P̂ = P̂
+//! Pfadinvarianz Operator P̂_Γ
+//!
+//! Path-invariant projection ensuring determinism.
+//! Idempotent operator: P̂ ∘ P̂ = P̂
 
 use crate::types::*;
 use crate::operators::OmegaOperator;
 use ndarray::Array1;
 
+/// Largest number of coordinates whose full symmetric group is enumerated (8! = 40320)
+pub const MAX_GROUP_DEGREE: usize = 8;
+
+/// Random inputs tried by `is_idempotent`
+const IDEMPOTENCE_SAMPLES: usize = 32;
+
 #[derive(Clone)]
 pub struct Pfadinvarianz {
     permutations: Vec<Vec<usize>>,
 }
 
 impl Pfadinvarianz {
-    pub fn new(dimension: usize) -> Self {
+    /// Average over the dihedral group of the coordinates (cyclic shifts and
+    /// reflections, `2 * dimension` permutations)
+    ///
+    /// Cheap for any dimension. The set is closed under composition, so the
+    /// result is an exact projection (see `is_idempotent`).
+    pub fn subset(dimension: usize) -> Self {
         let permutations = Self::generate_permutations(dimension);
         Self { permutations }
     }
 
-    /// Apply path-invariant projection
-    pub fn apply(&self, v: &OmegaVector) -> OmegaVector {
+    /// Average over all `dimension!` permutations
+    ///
+    /// This is the exact projection onto the constant vectors: every component
+    /// becomes the mean, and applying it twice equals applying it once.
+    /// Errors for `dimension > MAX_GROUP_DEGREE`.
+    pub fn full_group(dimension: usize) -> Result<Self> {
+        if dimension > MAX_GROUP_DEGREE {
+            return Err(OmegaError::ParameterError(format!(
+                "Full group on {} coordinates exceeds the maximum of {}", dimension, MAX_GROUP_DEGREE
+            )));
+        }
+        if dimension == 0 {
+            return Ok(Self { permutations: vec![] });
+        }
+
+        Ok(Self { permutations: Self::symmetric_group(dimension) })
+    }
+
+    /// Average over every permutation of the coordinates in `subset`,
+    /// leaving all other coordinates fixed
+    ///
+    /// The group is the full symmetric group on `subset`, so the projection
+    /// replaces those coordinates by their mean and is exactly idempotent.
+    pub fn on_subset(dimension: usize, subset: &[usize]) -> Result<Self> {
+        if let Some(&i) = subset.iter().find(|&&i| i >= dimension) {
+            return Err(OmegaError::ParameterError(format!(
+                "Subset index {} out of range for dimension {}", i, dimension
+            )));
+        }
+        for (pos, i) in subset.iter().enumerate() {
+            if subset[..pos].contains(i) {
+                return Err(OmegaError::ParameterError(format!(
+                    "Subset index {} appears more than once", i
+                )));
+            }
+        }
+        if subset.len() > MAX_GROUP_DEGREE {
+            return Err(OmegaError::ParameterError(format!(
+                "Subset of {} coordinates exceeds the maximum of {}", subset.len(), MAX_GROUP_DEGREE
+            )));
+        }
+
+        let permutations = Self::symmetric_group(subset.len())
+            .into_iter()
+            .map(|sigma| {
+                let mut perm: Vec<usize> = (0..dimension).collect();
+                for (&target, &source) in subset.iter().zip(sigma.iter()) {
+                    perm[target] = subset[source];
+                }
+                perm
+            })
+            .collect();
+
+        Ok(Self { permutations })
+    }
+
+    /// Average over exactly `permutations`, e.g. a cyclic subgroup
+    ///
+    /// Each permutation maps output index `i` to input index `perm[i]`, and
+    /// all must be bijections of `0..dimension`, where the dimension is the
+    /// length of the first. An empty permutation, one of another length, one
+    /// with an index out of range or with a repeated index is a
+    /// `ParameterError`. An empty set is the identity. The set is not checked for closure; see
+    /// `is_idempotent`.
+    pub fn from_permutations(permutations: Vec<Vec<usize>>) -> Result<Self> {
+        let dimension = permutations.first().map_or(0, Vec::len);
+        for (k, perm) in permutations.iter().enumerate() {
+            if perm.is_empty() {
+                return Err(OmegaError::ParameterError(format!("Permutation {} is empty", k)));
+            }
+            if perm.len() != dimension {
+                return Err(OmegaError::ParameterError(format!(
+                    "Permutation {} has {} entries, expected {}", k, perm.len(), dimension
+                )));
+            }
+            let mut seen = vec![false; dimension];
+            for &i in perm {
+                if i >= dimension {
+                    return Err(OmegaError::ParameterError(format!(
+                        "Permutation {} maps to index {}, out of range for dimension {}", k, i, dimension
+                    )));
+                }
+                if std::mem::replace(&mut seen[i], true) {
+                    return Err(OmegaError::ParameterError(format!(
+                        "Permutation {} repeats index {}", k, i
+                    )));
+                }
+            }
+        }
+
+        Ok(Self { permutations })
+    }
+
+    /// Number of coordinates permuted (0 for the identity)
+    pub fn dimension(&self) -> usize {
+        self.permutations.first().map_or(0, Vec::len)
+    }
+
+    /// Number of permutations averaged over
+    pub fn permutation_count(&self) -> usize {
+        self.permutations.len()
+    }
+
+    /// Apply path-invariant projection (to a vector of either component width)
+    pub fn apply<F: Float>(&self, v: &Array1<F>) -> Array1<F> {
         if self.permutations.is_empty() {
             return v.clone();
         }
 
-        let mut sum = Array1::zeros(v.len());
+        let mut sum = Array1::<F>::zeros(v.len());
 
         // Average over all permutations
         for perm in &self.permutations {
@@ -32,11 +146,71 @@ impl Pfadinvarianz {
             sum = sum + permuted;
         }
 
-        sum / (self.permutations.len() as f64)
+        let count: F = component(self.permutations.len() as f64);
+        sum.mapv(|x| x / count)
+    }
+
+    /// Apply the projection independently to each consecutive block of
+    /// `dimension` components (a trailing partial block is left unchanged)
+    ///
+    /// `apply` only covers the first `dimension` components; this carries
+    /// longer vectors, such as multi-chunk messages, through intact.
+    pub fn apply_blocks<F: Float>(&self, v: &Array1<F>) -> Array1<F> {
+        let dimension = self.dimension();
+        if dimension == 0 {
+            return v.clone();
+        }
+
+        let mut result = v.clone();
+        for start in (0..v.len() / dimension).map(|block| block * dimension) {
+            let block = v.slice(ndarray::s![start..start + dimension]).to_owned();
+            result
+                .slice_mut(ndarray::s![start..start + dimension])
+                .assign(&self.apply(&block));
+        }
+        result
+    }
+
+    /// Whether `a` and `b` are equivalent under the permutation set, i.e.
+    /// their projections agree component-wise within `tol`
+    ///
+    /// For a permutation group this holds exactly when `b` is a permutation of
+    /// `a` from the group (up to `tol`). Vectors of different length are never
+    /// equivalent.
+    pub fn equivalent<F: Float>(&self, a: &Array1<F>, b: &Array1<F>, tol: F) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+
+        let pa = self.apply(a);
+        let pb = self.apply(b);
+        pa.iter().zip(pb.iter()).all(|(&x, &y)| (x - y).abs() <= tol)
+    }
+
+    /// Empirically check P̂ ∘ P̂ = P̂ on random inputs, within `tol` per component
+    ///
+    /// Averaging is an exact projection only when the permutations form a
+    /// group; a set that is not closed under composition fails this check.
+    /// The inputs come from a fixed seed, so the answer is deterministic.
+    pub fn is_idempotent(&self, tol: f64) -> bool {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+
+        let dimension = self.dimension();
+        if dimension == 0 {
+            return true;
+        }
+        let mut rng = StdRng::seed_from_u64(0x5046);
+        (0..IDEMPOTENCE_SAMPLES).all(|_| {
+            let v: OmegaVector = (0..dimension).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            let once = self.apply(&v);
+            let twice = self.apply(&once);
+            once.iter().zip(twice.iter()).all(|(a, b)| (a - b).abs() <= tol)
+        })
     }
 
     /// Apply a single permutation to vector
-    fn apply_permutation(&self, v: &OmegaVector, perm: &[usize]) -> OmegaVector {
+    fn apply_permutation<F: Float>(&self, v: &Array1<F>, perm: &[usize]) -> Array1<F> {
         let mut result = Array1::zeros(v.len());
         for (i, &p) in perm.iter().enumerate() {
             if p < v.len() && i < result.len() {
@@ -46,47 +220,51 @@ impl Pfadinvarianz {
         result
     }
 
-    /// Generate permutation group
-    /// For simplicity, we use a subset of all permutations
+    /// Dihedral group on `dimension` coordinates
+    ///
+    /// For d=5 the full group has 5! = 120 elements; the dihedral group keeps
+    /// the cyclic shifts and reversal with only 2d, and stays closed under
+    /// composition (a shift of a reflection is a reflection).
     fn generate_permutations(dimension: usize) -> Vec<Vec<usize>> {
         if dimension == 0 {
             return vec![];
         }
 
-        // For d=5, full permutations would be 5! = 120
-        // We use a representative subset for computational efficiency
-        let mut perms = vec![];
+        let mut perms = Vec::with_capacity(2 * dimension);
 
-        // Identity
-        perms.push((0..dimension).collect());
+        // Rotations i -> i + shift, including the identity
+        for shift in 0..dimension {
+            perms.push((0..dimension).map(|i| (i + shift) % dimension).collect());
+        }
 
-        // Cyclic shifts
-        for shift in 1..dimension {
-            let mut perm = Vec::with_capacity(dimension);
-            for i in 0..dimension {
-                perm.push((i + shift) % dimension);
+        // Reflections i -> shift - i; two-element groups have none distinct
+        if dimension > 2 {
+            for shift in 0..dimension {
+                perms.push((0..dimension).map(|i| (shift + dimension - i) % dimension).collect());
             }
-            perms.push(perm);
         }
 
-        // Reversals
-        perms.push((0..dimension).rev().collect());
-
-        // Swaps of adjacent elements
-        for i in 0..dimension - 1 {
-            let mut perm: Vec<usize> = (0..dimension).collect();
-            perm.swap(i, i + 1);
-            perms.push(perm);
-        }
+        perms
+    }
 
-        // Some additional permutations for better coverage
-        if dimension >= 3 {
-            // Rotate first 3 elements
-            let mut perm: Vec<usize> = (0..dimension).collect();
-            perm[0] = 1;
-            perm[1] = 2;
-            perm[2] = 0;
-            perms.push(perm);
+    /// All k! permutations of 0..k (Heap's algorithm)
+    fn symmetric_group(k: usize) -> Vec<Vec<usize>> {
+        let mut current: Vec<usize> = (0..k).collect();
+        let mut perms = vec![current.clone()];
+        let mut counters = vec![0; k];
+
+        let mut i = 1;
+        while i < k {
+            if counters[i] < i {
+                let j = if i % 2 == 0 { 0 } else { counters[i] };
+                current.swap(j, i);
+                perms.push(current.clone());
+                counters[i] += 1;
+                i = 1;
+            } else {
+                counters[i] = 0;
+                i += 1;
+            }
         }
 
         perms
@@ -95,7 +273,7 @@ impl Pfadinvarianz {
 
 impl Default for Pfadinvarianz {
     fn default() -> Self {
-        Self::new(5) // Default 5D space
+        Self::subset(5) // Default 5D space
     }
 }
 
@@ -108,6 +286,11 @@ impl OmegaOperator for Pfadinvarianz {
         Ok(self.apply(&input))
     }
 
+    /// Identity: every vector in the projection's range is its own preimage
+    fn inverse(&self, output: Self::Output, _params: &Self::Params) -> Result<Self::Input> {
+        Ok(output)
+    }
+
     fn name(&self) -> &str {
         "Pfadinvarianz"
     }
@@ -124,7 +307,7 @@ mod tests {
 
     #[test]
     fn test_idempotence() {
-        let pfad = Pfadinvarianz::default();
+        let pfad = Pfadinvarianz::full_group(5).unwrap();
         let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
 
         let v1 = pfad.apply(&v);
@@ -136,6 +319,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_full_group_is_exact_projection() {
+        let pfad = Pfadinvarianz::full_group(5).unwrap();
+        assert_eq!(pfad.permutations.len(), 120);
+
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let projected = pfad.apply(&v);
+        assert_ne!(projected, v);
+        assert_eq!(projected, arr1(&[3.0; 5]));
+        assert_eq!(pfad.apply(&projected), projected);
+
+        assert!(Pfadinvarianz::full_group(MAX_GROUP_DEGREE).is_ok());
+        assert!(Pfadinvarianz::full_group(MAX_GROUP_DEGREE + 1).is_err());
+    }
+
+    #[test]
+    fn test_idempotence_self_check() {
+        for dimension in 1..=7 {
+            let pfad = Pfadinvarianz::subset(dimension);
+            assert!(pfad.is_idempotent(1e-12), "dimension {}", dimension);
+        }
+        assert_eq!(Pfadinvarianz::subset(5).permutations.len(), 10);
+        assert!(Pfadinvarianz::full_group(4).unwrap().is_idempotent(1e-12));
+        assert!(Pfadinvarianz::on_subset(6, &[1, 4]).unwrap().is_idempotent(1e-12));
+
+        // Identity and a 3-cycle without its square: not closed under composition
+        let open = Pfadinvarianz {
+            permutations: vec![vec![0, 1, 2, 3, 4], vec![1, 2, 0, 3, 4]],
+        };
+        assert!(!open.is_idempotent(1e-6));
+    }
+
     #[test]
     fn test_path_invariance() {
         let pfad = Pfadinvarianz::default();
@@ -156,6 +371,88 @@ mod tests {
         assert_eq!(projected_v1.len(), projected_v2.len());
     }
 
+    #[test]
+    fn test_on_subset_averages_only_subset() {
+        let pfad = Pfadinvarianz::on_subset(5, &[0, 1]).unwrap();
+        let v = arr1(&[1.0, 3.0, 5.0, 7.0, 9.0]);
+
+        let result = pfad.apply(&v);
+        assert_eq!(result, arr1(&[2.0, 2.0, 5.0, 7.0, 9.0]));
+        assert_eq!(pfad.apply(&result), result);
+
+        let scattered = Pfadinvarianz::on_subset(5, &[1, 3, 4]).unwrap();
+        assert_eq!(scattered.permutations.len(), 6);
+        assert_eq!(scattered.apply(&v), arr1(&[1.0, 19.0 / 3.0, 5.0, 19.0 / 3.0, 19.0 / 3.0]));
+
+        assert!(Pfadinvarianz::on_subset(5, &[0, 5]).is_err());
+        assert!(Pfadinvarianz::on_subset(5, &[2, 2]).is_err());
+    }
+
+    #[test]
+    fn test_from_permutations_rejects_non_bijections() {
+        let too_short = Pfadinvarianz::from_permutations(vec![vec![0, 1, 2], vec![1, 0]]);
+        assert!(matches!(too_short, Err(OmegaError::ParameterError(msg)) if msg.contains("2 entries")));
+
+        let repeated = Pfadinvarianz::from_permutations(vec![vec![0, 1, 2], vec![1, 1, 0]]);
+        assert!(matches!(repeated, Err(OmegaError::ParameterError(msg)) if msg.contains("repeats index 1")));
+
+        let out_of_range = Pfadinvarianz::from_permutations(vec![vec![0, 3, 1]]);
+        assert!(matches!(out_of_range, Err(OmegaError::ParameterError(msg)) if msg.contains("out of range")));
+
+        assert!(Pfadinvarianz::from_permutations(vec![vec![]]).is_err());
+
+        // No permutations: the identity
+        let identity = Pfadinvarianz::from_permutations(vec![]).unwrap();
+        assert_eq!((identity.dimension(), identity.permutation_count()), (0, 0));
+        let v = arr1(&[3.0, -1.0, 2.0]);
+        assert_eq!(identity.apply(&v), v);
+        assert_eq!(identity.apply_blocks(&v), v);
+    }
+
+    #[test]
+    fn test_cyclic_group_averages_orbits() {
+        let rotations: Vec<Vec<usize>> = (0..4).map(|s| (0..4).map(|i| (i + s) % 4).collect()).collect();
+
+        // Rotation by two alone generates a subgroup with orbits {0, 2} and {1, 3}
+        let half_turns = Pfadinvarianz::from_permutations(vec![rotations[0].clone(), rotations[2].clone()]).unwrap();
+        assert_eq!((half_turns.dimension(), half_turns.permutation_count()), (4, 2));
+        let v = arr1(&[1.0, 2.0, 5.0, 8.0]);
+        assert_eq!(half_turns.apply(&v), arr1(&[3.0, 5.0, 3.0, 5.0]));
+        assert!(half_turns.is_idempotent(1e-12));
+
+        // The whole cyclic group is transitive: every component becomes the mean
+        let cyclic = Pfadinvarianz::from_permutations(rotations).unwrap();
+        assert_eq!(cyclic.apply(&v), arr1(&[4.0; 4]));
+        assert!(cyclic.is_idempotent(1e-12));
+        assert_eq!(cyclic.apply_blocks(&arr1(&[1.0, 2.0, 5.0, 8.0, 0.0, 0.0, 4.0, 4.0, 9.0])),
+            arr1(&[4.0, 4.0, 4.0, 4.0, 2.0, 2.0, 2.0, 2.0, 9.0]));
+    }
+
+    #[test]
+    fn test_equivalence_up_to_permutation() {
+        let pfad = Pfadinvarianz::on_subset(5, &[0, 1, 2, 3, 4]).unwrap();
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let shifted = arr1(&[2.0, 3.0, 4.0, 5.0, 1.0]);
+        let unrelated = arr1(&[1.0, 1.0, 1.0, 1.0, 9.0]);
+
+        assert!(pfad.equivalent(&v, &shifted, 1e-12));
+        assert!(!pfad.equivalent(&v, &unrelated, 1e-6));
+        assert!(!pfad.equivalent(&v, &arr1(&[1.0, 2.0]), 1e-6));
+    }
+
+    #[test]
+    fn test_apply_blocks_projects_each_chunk() {
+        let pfad = Pfadinvarianz::full_group(5).unwrap();
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 10.0, 10.0, 10.0, 20.0, 7.0]);
+
+        let result = pfad.apply_blocks(&v);
+        assert_eq!(
+            result,
+            arr1(&[3.0, 3.0, 3.0, 3.0, 3.0, 12.0, 12.0, 12.0, 12.0, 12.0, 7.0])
+        );
+        assert_eq!(pfad.apply_blocks(&v.slice(ndarray::s![..5]).to_owned()), pfad.apply(&arr1(&[1.0, 2.0, 3.0, 4.0, 5.0])));
+    }
+
     #[test]
     fn test_contractivity() {
         let pfad = Pfadinvarianz::default();
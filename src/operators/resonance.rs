@@ -1,80 +1,534 @@
-/// Resonance Operator R̂_ω
-///
-/// Spectral coupling for address-free communication.
-/// Filters vectors based on their dominant frequency component.
+//! Resonance Operator R̂_ω
+//!
+//! Spectral coupling for address-free communication.
+//! Filters vectors based on their dominant frequency component.
+//!
+//! Unlike the sweep, projection, weight transfer and DoubleKick operators,
+//! resonance only takes `f64` vectors: the spectrum is computed with
+//! `Complex<f64>` (rustfft or the direct DFT), so `f32` input has to be
+//! widened first.
 
 use crate::types::*;
 use crate::operators::OmegaOperator;
+use crate::utils;
 use ndarray::Array1;
-use rustfft::{FftPlanner, num_complex::Complex};
+use num_complex::Complex;
+#[cfg(feature = "fft")]
+use rustfft::{Fft, FftPlanner};
+#[cfg(feature = "fft")]
+use std::cell::RefCell;
+#[cfg(feature = "fft")]
+use std::collections::HashMap;
+#[cfg(feature = "fft")]
+use std::sync::Arc;
+
+/// Spectral estimator used to locate the dominant frequency
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetectionMethod {
+    /// Full-length FFT (direct DFT when built without the `fft` feature)
+    Fft,
+    /// Full-length direct DFT, O(n²)
+    Dft,
+    /// Welch's method: averaged periodograms of Hann-windowed,
+    /// half-overlapping segments. Lower resolution, much lower variance.
+    Welch { segment_len: usize },
+}
+
+/// Window applied to the samples before a full-length FFT or DFT
+///
+/// Tapering the edges reduces spectral leakage, so a tone between two bins
+/// does not spill power into distant bins and the peak is picked consistently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowKind {
+    /// No tapering
+    Rectangular,
+    /// Raised cosine reaching zero at the edges
+    #[default]
+    Hann,
+    /// Raised cosine on a 0.08 pedestal, with a lower first sidelobe than Hann
+    Hamming,
+}
+
+impl WindowKind {
+    /// Periodic window coefficients for `len` samples
+    pub fn coefficients(&self, len: usize) -> Vec<f64> {
+        let (a0, a1) = match self {
+            WindowKind::Rectangular => return vec![1.0; len],
+            WindowKind::Hann => (0.5, 0.5),
+            WindowKind::Hamming => (0.54, 0.46),
+        };
+        (0..len)
+            .map(|n| a0 - a1 * (2.0 * std::f64::consts::PI * n as f64 / len as f64).cos())
+            .collect()
+    }
+}
+
+/// Length thresholds used by `ResonanceOperator::auto`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoThresholds {
+    /// Vectors at least this long use Welch's method
+    pub welch_min_len: usize,
+    /// Segment length for Welch's method
+    pub welch_segment_len: usize,
+}
+
+impl Default for AutoThresholds {
+    fn default() -> Self {
+        Self {
+            welch_min_len: 1024,
+            welch_segment_len: 256,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Strategy {
+    Fixed(DetectionMethod),
+    Auto(AutoThresholds),
+}
+
+/// Default resonance bandwidth
+const DEFAULT_EPSILON: f64 = 0.1;
+
+/// Normalized matched-filter output above which `detect_chirp` fires
+pub const CHIRP_DETECTION_THRESHOLD: f64 = 0.5;
 
 pub struct ResonanceOperator {
-    omega: f64,
+    /// (frequency, epsilon) bands; the first entry is the primary frequency
+    listen: Vec<(f64, f64)>,
+    /// Bandwidth of bands added without an explicit epsilon
     epsilon: f64,
+    strategy: Strategy,
+    window: WindowKind,
+    fft: FftPlans,
 }
 
 impl ResonanceOperator {
     pub fn new(omega: f64) -> Self {
+        Self::with_epsilon(omega, DEFAULT_EPSILON)
+    }
+
+    pub fn with_epsilon(omega: f64, epsilon: f64) -> Self {
         Self {
-            omega,
-            epsilon: 0.1, // Default resonance bandwidth
+            listen: vec![(omega, epsilon)],
+            epsilon,
+            strategy: Strategy::Fixed(DetectionMethod::Fft),
+            window: WindowKind::Rectangular,
+            fft: FftPlans::new(),
         }
     }
 
-    pub fn with_epsilon(omega: f64, epsilon: f64) -> Self {
-        Self { omega, epsilon }
+    /// Window the samples before full-length FFT/DFT detection
+    ///
+    /// Operators start `Rectangular`; Welch's method always windows its
+    /// segments with Hann and ignores this setting.
+    pub fn with_window(mut self, window: WindowKind) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn window(&self) -> WindowKind {
+        self.window
+    }
+
+    /// Number of FFT plans created so far; detection plans once per vector length
+    pub fn fft_plans_created(&self) -> usize {
+        self.fft.created()
+    }
+
+    /// Also resonate on `omega`, with the default bandwidth
+    pub fn add_listen_frequency(&mut self, omega: f64) {
+        self.add_listen_frequency_with_epsilon(omega, self.epsilon);
+    }
+
+    /// Also resonate on `omega`, within its own bandwidth `epsilon`
+    pub fn add_listen_frequency_with_epsilon(&mut self, omega: f64, epsilon: f64) {
+        self.listen.push((omega, epsilon));
+    }
+
+    /// Stop resonating on `omega`; returns whether a band was removed
+    pub fn remove_listen_frequency(&mut self, omega: f64) -> bool {
+        let before = self.listen.len();
+        self.listen.retain(|&(listen, _)| listen != omega);
+        self.listen.len() != before
+    }
+
+    /// Retune the primary frequency, keeping its bandwidth and any additional bands
+    pub fn set_frequency(&mut self, omega: f64) {
+        match self.listen.first_mut() {
+            Some(primary) => primary.0 = omega,
+            None => self.listen.push((omega, self.epsilon)),
+        }
+    }
+
+    /// Bandwidth of the primary frequency (the default bandwidth if there is none)
+    pub fn epsilon(&self) -> f64 {
+        self.listen.first().map_or(self.epsilon, |&(_, epsilon)| epsilon)
+    }
+
+    /// (frequency, epsilon) bands this operator resonates on
+    pub fn listen_frequencies(&self) -> &[(f64, f64)] {
+        &self.listen
+    }
+
+    /// Whether a detected frequency falls within the band of any listen entry
+    pub fn matches_frequency(&self, freq: f64) -> bool {
+        self.matching_frequency(freq).is_some()
+    }
+
+    /// Listen frequency whose band contains `freq`, the closest one if bands overlap
+    pub fn matching_frequency(&self, freq: f64) -> Option<f64> {
+        self.listen
+            .iter()
+            .filter(|&&(omega, epsilon)| (freq - omega).abs() < epsilon)
+            .map(|&(omega, _)| omega)
+            .min_by(|a, b| (freq - a).abs().total_cmp(&(freq - b).abs()))
+    }
+
+    /// Always detect with `method`
+    pub fn with_method(omega: f64, method: DetectionMethod) -> Self {
+        Self {
+            strategy: Strategy::Fixed(method),
+            ..Self::new(omega)
+        }
+    }
+
+    /// Pick the detection method per vector from its length, using default thresholds
+    pub fn auto(omega: f64) -> Self {
+        Self::auto_with_thresholds(omega, AutoThresholds::default())
+    }
+
+    /// Pick the detection method per vector from its length
+    pub fn auto_with_thresholds(omega: f64, thresholds: AutoThresholds) -> Self {
+        Self {
+            strategy: Strategy::Auto(thresholds),
+            ..Self::new(omega)
+        }
+    }
+
+    /// Detection method used for a vector of length `len`
+    pub fn select_method(&self, len: usize) -> DetectionMethod {
+        match self.strategy {
+            Strategy::Fixed(method) => method,
+            Strategy::Auto(thresholds) if len >= thresholds.welch_min_len => {
+                DetectionMethod::Welch { segment_len: thresholds.welch_segment_len }
+            }
+            Strategy::Auto(_) => DetectionMethod::Fft,
+        }
     }
 
     /// Apply resonance filter to vector
     pub fn apply(&self, v: &OmegaVector) -> OmegaVector {
-        let dominant_freq = self.compute_dominant_frequency(v);
-
-        if (dominant_freq - self.omega).abs() < self.epsilon {
+        if self.is_resonant(v) {
             v.clone()
         } else {
             Array1::zeros(v.len())
         }
     }
 
-    /// Compute dominant frequency of vector using FFT
+    /// Compute dominant frequency of vector
     pub fn compute_dominant_frequency(&self, v: &OmegaVector) -> f64 {
-        let len = v.len();
-        if len == 0 {
+        if v.is_empty() {
+            return 0.0;
+        }
+        let (power, len) = self.spectrum(v);
+        dominant_frequency(&power, len)
+    }
+
+    /// Fraction of the vector's spectral energy that lies within a listen band
+    ///
+    /// Uses the same spectrum as detection (method and window). A low ratio
+    /// on an accepted frame means much of its energy sits in rejected bands,
+    /// a sign of interference or a poorly shaped signal. Zero for an empty or
+    /// all-zero vector.
+    pub fn band_energy_ratio(&self, v: &OmegaVector) -> f64 {
+        if v.is_empty() {
+            return 0.0;
+        }
+
+        let (power, len) = self.spectrum(v);
+        let (mut in_band, mut total) = (0.0, 0.0);
+        for (k, &p) in power.iter().enumerate() {
+            let freq = (k as f64 / len as f64) * 2.0 * std::f64::consts::PI;
+            if self.matches_frequency(freq) {
+                in_band += p;
+            }
+            total += p;
+        }
+
+        if total > 0.0 {
+            in_band / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Half power spectrum `|X[k]|²` (bins 0 to Nyquist), computed with the
+    /// detection method and window
+    ///
+    /// Bin `k` lies at normalized frequency `2πk / n`, where `n` is the
+    /// vector length (the segment length under Welch's method). Empty for an
+    /// empty vector; a single sample has only the DC bin.
+    pub fn power_spectrum(&self, v: &OmegaVector) -> Vec<f64> {
+        if v.is_empty() {
+            return Vec::new();
+        }
+        self.spectrum(v).0
+    }
+
+    /// Power-weighted mean frequency of the half power spectrum, in [0, π]
+    ///
+    /// Zero for an empty or all-zero vector.
+    pub fn spectral_centroid(&self, v: &OmegaVector) -> f64 {
+        if v.is_empty() {
             return 0.0;
         }
 
-        // Convert to complex numbers
-        let mut buffer: Vec<Complex<f64>> = v
+        let (power, len) = self.spectrum(v);
+        let total: f64 = power.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let weighted: f64 = power
             .iter()
-            .map(|&x| Complex::new(x, 0.0))
-            .collect();
-
-        // Perform FFT
-        let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(len);
-        fft.process(&mut buffer);
-
-        // Find dominant frequency (max magnitude, excluding DC component)
-        let mut max_magnitude = 0.0;
-        let mut max_index = 0;
-
-        for (i, c) in buffer.iter().enumerate().skip(1) {
-            let magnitude = c.norm();
-            if magnitude > max_magnitude {
-                max_magnitude = magnitude;
-                max_index = i;
+            .enumerate()
+            .map(|(k, &p)| p * (k as f64 / len as f64) * 2.0 * std::f64::consts::PI)
+            .sum();
+        weighted / total
+    }
+
+    /// Indices of the `n` strongest bins of `power_spectrum`, strongest
+    /// first (ties to the lower bin); DC included
+    pub fn peak_bins(&self, v: &OmegaVector, n: usize) -> Vec<usize> {
+        let power = self.power_spectrum(v);
+        let mut bins: Vec<usize> = (0..power.len()).collect();
+        bins.sort_by(|&a, &b| power[b].total_cmp(&power[a]).then(a.cmp(&b)));
+        bins.truncate(n);
+        bins
+    }
+
+    /// Half power spectrum of a non-empty vector with the configured method
+    /// and window, and the transform length it came from
+    fn spectrum(&self, v: &OmegaVector) -> (Vec<f64>, usize) {
+        let samples: Vec<f64> = v.iter().copied().collect();
+        match self.select_method(samples.len()) {
+            DetectionMethod::Fft => (self.fft.power(&apply_window(&samples, self.window)), samples.len()),
+            DetectionMethod::Dft => (dft_power(&apply_window(&samples, self.window)), samples.len()),
+            DetectionMethod::Welch { segment_len } => {
+                let segment_len = segment_len.clamp(1, samples.len());
+                (welch_power(&self.fft, &samples, segment_len), segment_len)
             }
         }
+    }
+
+    /// Normalized matched-filter output for a linear chirp from `f_start`
+    /// to `f_end` (see `utils::set_frequency_chirp`)
+    ///
+    /// The correlation coefficient between the mean-removed vector and the
+    /// expected chirp: 1 for the chirp alone, near 0 for tones, other chirps
+    /// and noise. 0 for an empty or constant vector.
+    pub fn chirp_correlation(&self, v: &OmegaVector, f_start: f64, f_end: f64) -> f64 {
+        if v.is_empty() {
+            return 0.0;
+        }
+        let template = utils::chirp(v.len(), f_start, f_end);
+        let mean = v.sum() / v.len() as f64;
+        let centered = v.mapv(|x| x - mean);
+
+        let norms = utils::energy(&centered).sqrt() * utils::energy(&template).sqrt();
+        if norms == 0.0 {
+            return 0.0;
+        }
+        centered.dot(&template) / norms
+    }
 
-        // Convert index to normalized frequency [0, 2π)
-        (max_index as f64 / len as f64) * 2.0 * std::f64::consts::PI
+    /// Whether the vector carries the chirp marker from `f_start` to `f_end`:
+    /// its matched-filter output reaches `CHIRP_DETECTION_THRESHOLD`
+    pub fn detect_chirp(&self, v: &OmegaVector, f_start: f64, f_end: f64) -> bool {
+        self.chirp_correlation(v, f_start, f_end) >= CHIRP_DETECTION_THRESHOLD
     }
 
     /// Check if vector is resonant with target frequency
     pub fn is_resonant(&self, v: &OmegaVector) -> bool {
-        let dominant_freq = self.compute_dominant_frequency(v);
-        (dominant_freq - self.omega).abs() < self.epsilon
+        self.resonant_frequency(v).is_some()
+    }
+
+    /// Listen frequency the vector resonates with, if any
+    pub fn resonant_frequency(&self, v: &OmegaVector) -> Option<f64> {
+        self.matching_frequency(self.compute_dominant_frequency(v))
+    }
+}
+
+/// Frequency of the strongest non-DC bin of the half power spectrum of a
+/// `len`-sample transform.
+///
+/// For real input, bins above len/2 mirror the lower ones, so only the
+/// unique positive frequencies up to Nyquist are computed and considered.
+fn dominant_frequency(power: &[f64], len: usize) -> f64 {
+    let mut max_power = 0.0;
+    let mut max_index = 0;
+
+    for (i, &p) in power.iter().enumerate().skip(1) {
+        if p > max_power {
+            max_power = p;
+            max_index = i;
+        }
+    }
+
+    // Convert index to normalized frequency [0, π]
+    (max_index as f64 / len as f64) * 2.0 * std::f64::consts::PI
+}
+
+/// Real-input transform of one length: an even length runs as a
+/// half-length complex FFT over the samples packed in pairs, untangled with
+/// `twiddles`; an odd length runs as a full complex FFT
+#[cfg(feature = "fft")]
+#[derive(Clone)]
+struct RealPlan {
+    fft: Arc<dyn Fft<f64>>,
+    twiddles: Vec<Complex<f64>>,
+}
+
+/// Forward real FFT plans, planned once per length and reused
+///
+/// Interior mutability keeps detection usable through `&self`.
+#[cfg(feature = "fft")]
+struct FftPlans {
+    planner: RefCell<FftPlanner<f64>>,
+    plans: RefCell<HashMap<usize, RealPlan>>,
+}
+
+#[cfg(feature = "fft")]
+impl FftPlans {
+    fn new() -> Self {
+        Self {
+            planner: RefCell::new(FftPlanner::new()),
+            plans: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Number of plans created so far (one per distinct length)
+    fn created(&self) -> usize {
+        self.plans.borrow().len()
+    }
+
+    fn plan(&self, len: usize) -> RealPlan {
+        self.plans
+            .borrow_mut()
+            .entry(len)
+            .or_insert_with(|| {
+                let mut planner = self.planner.borrow_mut();
+                if len % 2 == 1 {
+                    return RealPlan { fft: planner.plan_fft_forward(len), twiddles: Vec::new() };
+                }
+                let step = -2.0 * std::f64::consts::PI / len as f64;
+                RealPlan {
+                    fft: planner.plan_fft_forward(len / 2),
+                    twiddles: (0..=len / 2).map(|k| Complex::from_polar(1.0, step * k as f64)).collect(),
+                }
+            })
+            .clone()
+    }
+
+    /// Half power spectrum via rustfft
+    fn power(&self, samples: &[f64]) -> Vec<f64> {
+        let len = samples.len();
+        let plan = self.plan(len);
+
+        if len % 2 == 1 {
+            let mut buffer: Vec<Complex<f64>> = samples.iter().map(|&x| Complex::new(x, 0.0)).collect();
+            plan.fft.process(&mut buffer);
+            return buffer.iter().take(len / 2 + 1).map(|c| c.norm_sqr()).collect();
+        }
+
+        // Even samples in the real part, odd ones in the imaginary part
+        let mut z: Vec<Complex<f64>> = samples.chunks_exact(2).map(|p| Complex::new(p[0], p[1])).collect();
+        plan.fft.process(&mut z);
+
+        // Split Z into the spectra E and O of the even and odd samples using
+        // their conjugate symmetry, then X[k] = E[k] + e^(-2πik/len) O[k]
+        let half = len / 2;
+        plan.twiddles
+            .iter()
+            .enumerate()
+            .map(|(k, &twiddle)| {
+                let zk = z[k % half];
+                let zc = z[(half - k) % half].conj();
+                let even = (zk + zc) * 0.5;
+                let odd = (zk - zc) * Complex::new(0.0, -0.5);
+                (even + twiddle * odd).norm_sqr()
+            })
+            .collect()
+    }
+}
+
+/// Without rustfft there is nothing to plan
+#[cfg(not(feature = "fft"))]
+struct FftPlans;
+
+#[cfg(not(feature = "fft"))]
+impl FftPlans {
+    fn new() -> Self {
+        Self
+    }
+
+    fn created(&self) -> usize {
+        0
+    }
+
+    /// Power spectrum fallback without rustfft
+    fn power(&self, samples: &[f64]) -> Vec<f64> {
+        dft_power(samples)
+    }
+}
+
+/// Half power spectrum via direct DFT
+fn dft_power(samples: &[f64]) -> Vec<f64> {
+    let len = samples.len();
+    let mut power = vec![0.0; len / 2 + 1];
+
+    for (k, p) in power.iter_mut().enumerate() {
+        let step = -2.0 * std::f64::consts::PI * k as f64 / len as f64;
+        let sum: Complex<f64> = samples
+            .iter()
+            .enumerate()
+            .map(|(n, &x)| Complex::from_polar(x, step * n as f64))
+            .sum();
+        *p = sum.norm_sqr();
+    }
+
+    power
+}
+
+/// Multiply the samples by `window`'s coefficients
+fn apply_window(samples: &[f64], window: WindowKind) -> Vec<f64> {
+    samples
+        .iter()
+        .zip(window.coefficients(samples.len()))
+        .map(|(x, w)| x * w)
+        .collect()
+}
+
+/// Welch half power spectrum over `segment_len`-sample Hann-windowed
+/// segments with 50% overlap (`segment_len` at most the sample count)
+fn welch_power(fft: &FftPlans, samples: &[f64], segment_len: usize) -> Vec<f64> {
+    let hop = (segment_len / 2).max(1);
+    let mut average = vec![0.0; segment_len / 2 + 1];
+    let mut segments = 0;
+    let mut start = 0;
+    while start + segment_len <= samples.len() {
+        let windowed = apply_window(&samples[start..start + segment_len], WindowKind::Hann);
+        for (a, p) in average.iter_mut().zip(fft.power(&windowed)) {
+            *a += p;
+        }
+        segments += 1;
+        start += hop;
+    }
+
+    for a in average.iter_mut() {
+        *a /= segments as f64;
     }
+    average
 }
 
 impl OmegaOperator for ResonanceOperator {
@@ -99,16 +553,14 @@ impl OmegaOperator for ResonanceOperator {
 mod tests {
     use super::*;
     use ndarray::arr1;
+    use std::f64::consts::PI;
 
     #[test]
     fn test_resonance_filter() {
         let operator = ResonanceOperator::new(1.0);
 
-        // Create a vector with known frequency content
-        let mut v = Array1::zeros(64);
-        for i in 0..64 {
-            v[i] = (2.0 * std::f64::consts::PI * 1.0 * i as f64 / 64.0).sin();
-        }
+        // Create a vector with known frequency content (1.0 rad/sample)
+        let v = Array1::from_vec((0..64).map(|i| (1.0 * i as f64).sin()).collect());
 
         let result = operator.apply(&v);
 
@@ -116,6 +568,268 @@ mod tests {
         assert!(result.iter().any(|&x| x.abs() > 1e-10));
     }
 
+    #[test]
+    fn test_dominant_frequency_not_mirrored() {
+        let operator = ResonanceOperator::new(1.0);
+        let len = 64;
+
+        for &k in &[3usize, 10, 20, 31] {
+            let omega = 2.0 * std::f64::consts::PI * k as f64 / len as f64;
+            let v = Array1::from_vec((0..len).map(|i| (omega * i as f64).cos()).collect());
+
+            let freq = operator.compute_dominant_frequency(&v);
+
+            // True frequency, never its mirror 2π - ω
+            assert!((freq - omega).abs() < 1e-9, "k = {}: got {}", k, freq);
+            assert!(freq <= std::f64::consts::PI);
+        }
+    }
+
+    #[test]
+    fn test_auto_matches_explicit_methods() {
+        let tone = |len: usize, k: usize| {
+            let omega = 2.0 * std::f64::consts::PI * k as f64 / len as f64;
+            Array1::from_vec((0..len)
+                .map(|i| (omega * i as f64).sin() + 0.3 * (0.37 * i as f64).cos())
+                .collect())
+        };
+
+        let auto = ResonanceOperator::auto(1.0);
+        let fft = ResonanceOperator::with_method(1.0, DetectionMethod::Fft);
+        let dft = ResonanceOperator::with_method(1.0, DetectionMethod::Dft);
+
+        // Short vectors: direct spectrum
+        for &(len, k) in &[(8usize, 2usize), (64, 10), (500, 120)] {
+            let v = tone(len, k);
+            assert_eq!(auto.select_method(len), DetectionMethod::Fft);
+            let estimate = auto.compute_dominant_frequency(&v);
+            assert_eq!(estimate, fft.compute_dominant_frequency(&v));
+            assert!((estimate - dft.compute_dominant_frequency(&v)).abs() < 1e-12);
+        }
+
+        // Long vectors: Welch, agreeing with the full FFT on a bin both resolve
+        let welch = ResonanceOperator::with_method(1.0, DetectionMethod::Welch { segment_len: 256 });
+        for &len in &[1024usize, 4096] {
+            let v = tone(len, 20 * len / 256);
+            assert!(matches!(auto.select_method(len), DetectionMethod::Welch { .. }));
+            let estimate = auto.compute_dominant_frequency(&v);
+            assert_eq!(estimate, welch.compute_dominant_frequency(&v));
+            assert!((estimate - fft.compute_dominant_frequency(&v)).abs() < 1e-12);
+        }
+
+        // Thresholds are tunable
+        let eager = ResonanceOperator::auto_with_thresholds(1.0, AutoThresholds {
+            welch_min_len: 64,
+            welch_segment_len: 32,
+        });
+        assert_eq!(eager.select_method(64), DetectionMethod::Welch { segment_len: 32 });
+        assert_eq!(eager.select_method(63), DetectionMethod::Fft);
+    }
+
+    #[test]
+    fn test_power_spectrum_two_tones() {
+        let len = 64;
+        let tone = |k: usize, i: usize| (2.0 * PI * k as f64 * i as f64 / len as f64).sin();
+        let v = Array1::from_vec((0..len).map(|i| tone(5, i) + 0.6 * tone(12, i)).collect());
+        let operator = ResonanceOperator::new(1.0);
+
+        let power = operator.power_spectrum(&v);
+        assert_eq!(power.len(), len / 2 + 1);
+        assert_eq!(operator.peak_bins(&v, 2), vec![5, 12]);
+        assert_eq!(operator.peak_bins(&v, 100).len(), power.len());
+
+        // Centroid between the tones, pulled towards the stronger one
+        let (low, high) = (2.0 * PI * 5.0 / len as f64, 2.0 * PI * 12.0 / len as f64);
+        let centroid = operator.spectral_centroid(&v);
+        assert!(low < centroid && centroid < (low + high) / 2.0);
+        assert_eq!(operator.compute_dominant_frequency(&v), low);
+
+        assert!(operator.power_spectrum(&Array1::zeros(0)).is_empty());
+        assert!(operator.peak_bins(&Array1::zeros(0), 3).is_empty());
+        assert_eq!(operator.spectral_centroid(&Array1::zeros(0)), 0.0);
+        assert_eq!(operator.power_spectrum(&arr1(&[2.0])), vec![4.0]);
+        assert_eq!(operator.spectral_centroid(&Array1::zeros(8)), 0.0);
+    }
+
+    #[test]
+    fn test_per_frequency_epsilon() {
+        let mut operator = ResonanceOperator::with_epsilon(1.0, 0.01);
+        operator.add_listen_frequency_with_epsilon(2.0, 0.3);
+
+        assert!(operator.matches_frequency(2.2));
+        assert!(operator.matches_frequency(1.005));
+        assert!(!operator.matches_frequency(1.05));
+        assert!(!operator.matches_frequency(2.35));
+
+        // Retuning moves only the primary band
+        operator.set_frequency(1.05);
+        assert_eq!(operator.listen_frequencies(), &[(1.05, 0.01), (2.0, 0.3)]);
+        assert!(operator.matches_frequency(1.05));
+    }
+
+    #[test]
+    fn test_hann_window_stabilizes_peak() {
+        // 1.6 cycles over 8 samples: between bins 1 and 2, leaking heavily
+        let n = 8;
+        let bin_of = |freq: f64| (freq * n as f64 / (2.0 * PI)).round() as usize;
+        let concentration = |power: &[f64], peak: usize| {
+            let total: f64 = power[1..=n / 2].iter().sum();
+            let near: f64 = power[(peak - 1).max(1)..=(peak + 1).min(n / 2)].iter().sum();
+            near / total
+        };
+
+        let rectangular = ResonanceOperator::new(1.0);
+        let hann = ResonanceOperator::new(1.0).with_window(WindowKind::Hann);
+        assert_eq!(rectangular.window(), WindowKind::Rectangular);
+
+        let mut rectangular_peaks = std::collections::BTreeSet::new();
+        let mut hann_peaks = std::collections::BTreeSet::new();
+        for k in 0..32 {
+            let phase = k as f64 / 32.0 * 2.0 * PI;
+            let samples: Vec<f64> = (0..n)
+                .map(|i| (2.0 * PI * 1.6 * i as f64 / n as f64 + phase).sin())
+                .collect();
+            let v = Array1::from_vec(samples.clone());
+
+            // Rectangular is exactly the unwindowed spectrum
+            let unwindowed = dominant_frequency(&rectangular.fft.power(&samples), n);
+            assert_eq!(rectangular.compute_dominant_frequency(&v), unwindowed);
+
+            let r = bin_of(rectangular.compute_dominant_frequency(&v));
+            let h = bin_of(hann.compute_dominant_frequency(&v));
+            rectangular_peaks.insert(r);
+            hann_peaks.insert(h);
+
+            let r_power = rectangular.fft.power(&samples);
+            let h_power = hann.fft.power(&apply_window(&samples, WindowKind::Hann));
+            assert!(concentration(&h_power, h) > concentration(&r_power, r));
+        }
+
+        assert_eq!(hann_peaks.len(), 1);
+        assert!(rectangular_peaks.len() > 1);
+    }
+
+    #[cfg(feature = "fft")]
+    #[test]
+    fn test_fft_plan_reused_per_length() {
+        let operator = ResonanceOperator::new(1.0);
+        let v = Array1::from_vec((0..256).map(|i| (0.7 * i as f64).sin()).collect());
+        let expected = operator.compute_dominant_frequency(&v);
+
+        for _ in 0..1000 {
+            assert_eq!(operator.compute_dominant_frequency(&v), expected);
+        }
+        assert_eq!(operator.fft_plans_created(), 1);
+
+        operator.compute_dominant_frequency(&Array1::zeros(5));
+        assert_eq!(operator.fft_plans_created(), 2);
+
+        // Welch segments share the cache as well
+        let welch = ResonanceOperator::with_method(1.0, DetectionMethod::Welch { segment_len: 64 });
+        welch.compute_dominant_frequency(&v);
+        welch.compute_dominant_frequency(&v);
+        assert_eq!(welch.fft_plans_created(), 1);
+    }
+
+    /// Dominant frequency the way it was computed before the real FFT: a
+    /// full complex FFT, searched up to Nyquist
+    #[cfg(feature = "fft")]
+    fn complex_dominant_frequency(samples: &[f64]) -> f64 {
+        let mut buffer: Vec<Complex<f64>> = samples.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        FftPlanner::new().plan_fft_forward(buffer.len()).process(&mut buffer);
+        let power: Vec<f64> = buffer.iter().take(samples.len() / 2 + 1).map(|c| c.norm_sqr()).collect();
+        dominant_frequency(&power, samples.len())
+    }
+
+    #[cfg(feature = "fft")]
+    #[test]
+    fn test_real_fft_matches_complex_fft() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let operator = ResonanceOperator::new(1.0);
+
+        let mut signals: Vec<Vec<f64>> = Vec::new();
+        for &(len, omega) in &[(64usize, 1.0), (65, 2.3), (256, 0.4), (1000, 3.0), (2, 1.0), (1, 0.0)] {
+            signals.push((0..len).map(|i| (omega * i as f64).sin() + 0.2 * (0.1 * i as f64).cos()).collect());
+        }
+        for &len in &[8usize, 63, 128, 501] {
+            signals.push((0..len).map(|_| rng.gen_range(-1.0..1.0)).collect());
+        }
+        // Chirp sweeping through the band
+        signals.push((0..300).map(|i| (0.001 * (i * i) as f64).sin()).collect());
+
+        for samples in &signals {
+            let real = operator.compute_dominant_frequency(&Array1::from_vec(samples.clone()));
+            assert!((real - complex_dominant_frequency(samples)).abs() < 1e-12, "len {}", samples.len());
+
+            // Same bins, to rounding
+            let mut full: Vec<Complex<f64>> = samples.iter().map(|&x| Complex::new(x, 0.0)).collect();
+            FftPlanner::new().plan_fft_forward(full.len()).process(&mut full);
+            let half = operator.fft.power(samples);
+            assert_eq!(half.len(), samples.len() / 2 + 1);
+            for (p, c) in half.iter().zip(&full) {
+                assert!((p - c.norm_sqr()).abs() <= 1e-9 * (1.0 + c.norm_sqr()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_chirp_matched_filter() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let operator = ResonanceOperator::new(1.0);
+        let len = 512;
+        let noisy = |v: OmegaVector, rng: &mut rand::rngs::StdRng| utils::add_gaussian_noise(&v, 0.05, rng);
+
+        let marked = noisy(utils::set_frequency_chirp(Array1::zeros(len), 0.5, 2.0).unwrap(), &mut rng);
+        assert!(operator.detect_chirp(&marked, 0.5, 2.0));
+        // A constant offset does not hide the marker
+        assert!(operator.detect_chirp(&marked.mapv(|x| x + 40.0), 0.5, 2.0));
+
+        // A tone in the middle of the sweep, the reversed sweep and a
+        // narrower one are all rejected
+        let tone = noisy(utils::set_frequency(Array1::zeros(len), 1.25).unwrap(), &mut rng);
+        assert!(!operator.detect_chirp(&tone, 0.5, 2.0));
+        let reversed = noisy(utils::set_frequency_chirp(Array1::zeros(len), 2.0, 0.5).unwrap(), &mut rng);
+        assert!(!operator.detect_chirp(&reversed, 0.5, 2.0));
+        let narrow = noisy(utils::set_frequency_chirp(Array1::zeros(len), 0.5, 1.0).unwrap(), &mut rng);
+        assert!(!operator.detect_chirp(&narrow, 0.5, 2.0));
+        assert!(operator.detect_chirp(&narrow, 0.5, 1.0));
+
+        assert_eq!(operator.chirp_correlation(&Array1::zeros(len), 0.5, 2.0), 0.0);
+    }
+
+    #[test]
+    fn test_matching_frequency_and_removal() {
+        let mut operator = ResonanceOperator::new(1.0);
+        operator.add_listen_frequency(1.5);
+        operator.add_listen_frequency(1.55);
+
+        assert_eq!(operator.matching_frequency(1.02), Some(1.0));
+        assert_eq!(operator.matching_frequency(1.54), Some(1.55));
+        assert_eq!(operator.matching_frequency(1.3), None);
+
+        assert!(operator.remove_listen_frequency(1.0));
+        assert!(!operator.remove_listen_frequency(1.0));
+        assert_eq!(operator.matching_frequency(1.02), None);
+        assert_eq!(operator.epsilon(), 0.1);
+    }
+
+    #[test]
+    fn test_band_energy_ratio() {
+        use rand::{Rng, SeedableRng};
+
+        let operator = ResonanceOperator::new(1.0);
+        let tone = Array1::from_vec((0..1000).map(|i| (1.0 * i as f64).sin()).collect());
+        assert!(operator.band_energy_ratio(&tone) > 0.95);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let noise = Array1::from_vec((0..1000).map(|_| rng.gen_range(-1.0..1.0)).collect());
+        assert!(operator.band_energy_ratio(&noise) < 0.2);
+
+        assert_eq!(operator.band_energy_ratio(&Array1::zeros(8)), 0.0);
+    }
+
     #[test]
     fn test_resonance_reject() {
         let operator = ResonanceOperator::with_epsilon(1.0, 0.01);
@@ -1,23 +1,95 @@
-/// Sweep Operator Ŝ_τ
-///
-/// Adaptive threshold filtering with temporal scheduling.
-/// Provides DoS resilience through dynamic threshold adjustment.
+//! Sweep Operator Ŝ_τ
+//!
+//! Adaptive threshold filtering with temporal scheduling.
+//! Provides DoS resilience through dynamic threshold adjustment: with a
+//! `max_rate` set, the threshold also rises while vectors arrive faster
+//! than that rate, so bursts are attenuated harder than paced traffic.
+//! In `SweepMode::PerElement` each component is gated on its own value
+//! rather than all on the vector mean, so small components are suppressed
+//! even next to a large outlier.
 
 use crate::types::*;
 use crate::operators::OmegaOperator;
+use ndarray::Array1;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+/// Smallest gate value `invert` divides by
+const MIN_INVERTIBLE_GATE: f64 = 1e-9;
+
+/// Sliding window over which the arrival rate is measured
+pub const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Gate at or above which a transform counts as passed
+pub const PASS_GATE: f64 = 0.9;
+
+/// Gate at or below which a transform counts as blocked
+pub const BLOCK_GATE: f64 = 0.1;
+
+/// How many transforms the gate passed, attenuated or blocked
+///
+/// A transform is classified by its gate (in `SweepMode::PerElement`, the
+/// mean gate of its components): at least `PASS_GATE` is passed, at most
+/// `BLOCK_GATE` blocked, anything between attenuated. Warmup transforms
+/// count as passed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SweepStats {
+    pub passed: u64,
+    pub attenuated: u64,
+    pub blocked: u64,
+}
+
+impl SweepStats {
+    /// Transforms counted
+    pub fn total(&self) -> u64 {
+        self.passed + self.attenuated + self.blocked
+    }
+
+    /// Fraction of transforms blocked (0 if none were counted)
+    pub fn rejection_rate(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.blocked as f64 / total as f64,
+        }
+    }
+
+    fn record(&mut self, gate: f64) {
+        if gate >= PASS_GATE {
+            self.passed += 1;
+        } else if gate <= BLOCK_GATE {
+            self.blocked += 1;
+        } else {
+            self.attenuated += 1;
+        }
+    }
+}
+
+/// User threshold policy: receives the current tick, returns the threshold
+pub type ThresholdFn = Box<dyn FnMut(f64) -> f64 + Send>;
 
 #[derive(Clone)]
 pub struct Sweep {
     tau0: f64,      // Base threshold
     beta: f64,      // Gate width
-    schedule: String, // "cosine" or "linear"
+    schedule: SweepSchedule,
     t: f64,         // Current time
     period: f64,    // Schedule period
     delta_tau: f64, // Threshold variation
+    warmup_ticks: u64, // Ticks with the gate forced open
+    threshold_fn: Option<Arc<Mutex<ThresholdFn>>>, // Overrides the schedule
+    max_rate: Option<f64>, // Arrivals per second before the threshold rises
+    arrivals: VecDeque<Instant>, // Arrivals within RATE_WINDOW, oldest first
+    mode: SweepMode, // Gate on the mean or per component
+    stats: SweepStats, // Gate outcomes of the transforms so far
 }
 
 impl Sweep {
-    pub fn new(tau0: f64, beta: f64, schedule: String) -> Self {
+    pub fn new(tau0: f64, beta: f64, schedule: SweepSchedule) -> Self {
         Self {
             tau0,
             beta,
@@ -25,26 +97,229 @@ impl Sweep {
             t: 0.0,
             period: 100.0,
             delta_tau: 0.2,
+            warmup_ticks: 0,
+            threshold_fn: None,
+            max_rate: None,
+            arrivals: VecDeque::new(),
+            mode: SweepMode::Mean,
+            stats: SweepStats::default(),
+        }
+    }
+
+    /// Replace the built-in schedule with a custom threshold policy
+    ///
+    /// The closure receives the current tick and returns the threshold.
+    /// Clones of this sweep share the closure (and any state it captures).
+    pub fn with_threshold_fn(mut self, threshold_fn: ThresholdFn) -> Self {
+        self.threshold_fn = Some(Arc::new(Mutex::new(threshold_fn)));
+        self
+    }
+
+    /// Set how many ticks one schedule cycle lasts and how far the
+    /// threshold swings; `period` must be positive and finite
+    pub fn with_cycle(mut self, period: f64, delta_tau: f64) -> Result<Self> {
+        if !(period.is_finite() && period > 0.0) {
+            return Err(OmegaError::ParameterError(
+                "Sweep period must be positive and finite".to_string()
+            ));
+        }
+        self.period = period;
+        self.delta_tau = delta_tau;
+        Ok(self)
+    }
+
+    /// Raise the threshold while arrivals (see `record_arrival`) exceed
+    /// `max_rate` per second; `None` gates on the mean alone. The rate must
+    /// be positive and finite.
+    pub fn with_max_rate(mut self, max_rate: Option<f64>) -> Result<Self> {
+        if let Some(rate) = max_rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(OmegaError::ParameterError(
+                    "Sweep max_rate must be positive and finite".to_string()
+                ));
+            }
+        }
+        self.max_rate = max_rate;
+        Ok(self)
+    }
+
+    /// Arrival rate above which the threshold rises, if any
+    pub fn max_rate(&self) -> Option<f64> {
+        self.max_rate
+    }
+
+    /// Note a vector arriving at time `now`, forgetting arrivals older than
+    /// `RATE_WINDOW`; does nothing without a `max_rate`
+    pub fn record_arrival(&mut self, now: Instant) {
+        if self.max_rate.is_none() {
+            return;
+        }
+        self.arrivals.push_back(now);
+        while let Some(&oldest) = self.arrivals.front() {
+            if now.saturating_duration_since(oldest) < RATE_WINDOW {
+                break;
+            }
+            self.arrivals.pop_front();
         }
     }
 
-    /// Apply sweep operator to vector
-    pub fn transform(&mut self, v: &OmegaVector) -> OmegaVector {
-        let mu = self.compute_mean(v);
+    /// Arrivals per second over the last `RATE_WINDOW`
+    pub fn observed_rate(&self) -> f64 {
+        self.arrivals.len() as f64 / RATE_WINDOW.as_secs_f64()
+    }
+
+    /// Threshold increase for the observed rate: `beta` for each multiple
+    /// of `max_rate` exceeded, so every such multiple divides the gate's
+    /// odds by e
+    fn rate_penalty(&self) -> f64 {
+        match self.max_rate {
+            Some(max_rate) => (self.observed_rate() / max_rate - 1.0).max(0.0) * self.beta.abs(),
+            None => 0.0,
+        }
+    }
+
+    /// Extra attenuation the arrival rate puts on a vector with mean `mean`
+    /// at the current tick: the gate at the raised threshold over the gate
+    /// at the scheduled one, so exactly 1 while arrivals stay within
+    /// `max_rate` (and during warmup); does not advance the tick
+    pub fn rate_attenuation(&self, mean: f64) -> f64 {
+        let penalty = self.rate_penalty();
+        if penalty == 0.0 || self.in_warmup() {
+            return 1.0;
+        }
         let tau = self.compute_threshold(self.t);
-        let gate = self.sigmoid_gate(mu, tau);
+        self.sigmoid_gate(mean, tau + penalty) / self.sigmoid_gate(mean, tau)
+    }
+
+    /// Gate on the vector mean (the default) or on each component
+    pub fn with_mode(mut self, mode: SweepMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn mode(&self) -> SweepMode {
+        self.mode
+    }
+
+    /// Keep the gate fully open for the first `ticks` transforms
+    /// (again after `reset`), so traffic is not dropped right after startup
+    pub fn with_warmup(mut self, ticks: u64) -> Self {
+        self.warmup_ticks = ticks;
+        self
+    }
+
+    /// Start the schedule at `tick` instead of 0
+    pub fn with_tick(mut self, tick: f64) -> Self {
+        self.t = tick;
+        self
+    }
+
+    /// Transforms applied since construction or the last `reset`
+    pub fn tick(&self) -> f64 {
+        self.t
+    }
+
+    /// Whether the gate is still forced open
+    pub fn in_warmup(&self) -> bool {
+        self.t < self.warmup_ticks as f64
+    }
+
+    /// Apply sweep operator to vector (of either component width)
+    pub fn transform<F: Float>(&mut self, v: &Array1<F>) -> Array1<F> {
+        let (output, gate) = if self.in_warmup() {
+            (v.clone(), 1.0)
+        } else {
+            let tau = self.current_threshold();
+            match self.mode {
+                SweepMode::Mean => {
+                    let gate = self.sigmoid_gate(self.compute_mean(v), tau);
+                    let g: F = component(gate);
+                    (v.mapv(|x| g * x), gate)
+                }
+                SweepMode::PerElement => {
+                    let gates = v.mapv(|x| self.sigmoid_gate(x.to_f64().unwrap_or(f64::NAN), tau));
+                    let output = ndarray::Zip::from(v).and(&gates).map_collect(|&x, &g| component::<F>(g) * x);
+                    let mean_gate = if gates.is_empty() { 1.0 } else { gates.sum() / gates.len() as f64 };
+                    (output, mean_gate)
+                }
+            }
+        };
 
         self.t += 1.0; // Advance time
+        self.stats.record(gate);
+
+        output
+    }
+
+    /// Invert `transform` at the current tick (without advancing it)
+    ///
+    /// The output mean is the gate times the input mean, so the gate solves
+    /// `g = sigmoid((mean / g - tau) / beta)`; the input is then `v / g`.
+    /// For a positive mean the solution is unique; otherwise the largest
+    /// consistent gate is used, which is exact whenever the gate was open.
+    /// Fails where the gate is effectively closed, and always in
+    /// `SweepMode::PerElement`, where negative components have no unique
+    /// preimage.
+    pub fn invert<F: Float>(&self, v: &Array1<F>) -> Result<Array1<F>> {
+        if self.in_warmup() {
+            return Ok(v.clone());
+        }
+        if self.mode == SweepMode::PerElement {
+            return Err(OmegaError::ParameterError(
+                "Per-element sweep gating cannot be inverted".to_string()
+            ));
+        }
+
+        let mean = self.compute_mean(v);
+        let tau = self.current_threshold();
+        let gate_for = |g: f64| self.sigmoid_gate(mean / g, tau);
+
+        let gate = if mean > 0.0 {
+            // g - gate_for(g) is increasing in g: bisect for its root
+            let (mut lo, mut hi) = (MIN_INVERTIBLE_GATE, 1.0);
+            if gate_for(lo) <= lo {
+                return Err(OmegaError::ParameterError(
+                    "Sweep gate is closed; input cannot be recovered".to_string()
+                ));
+            }
+            for _ in 0..100 {
+                let mid = 0.5 * (lo + hi);
+                if mid < gate_for(mid) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            0.5 * (lo + hi)
+        } else {
+            // gate_for is nondecreasing here: iterate down from a fully open gate
+            let mut g = 1.0;
+            for _ in 0..1000 {
+                let next = gate_for(g);
+                if (next - g).abs() < 1e-15 || next < MIN_INVERTIBLE_GATE {
+                    g = next;
+                    break;
+                }
+                g = next;
+            }
+            g
+        };
 
-        v.mapv(|x| gate * x)
+        if gate < MIN_INVERTIBLE_GATE {
+            return Err(OmegaError::ParameterError(
+                "Sweep gate is closed; input cannot be recovered".to_string()
+            ));
+        }
+        let gate: F = component(gate);
+        Ok(v.mapv(|x| x / gate))
     }
 
     /// Compute mean of vector
-    fn compute_mean(&self, v: &OmegaVector) -> f64 {
+    fn compute_mean<F: Float>(&self, v: &Array1<F>) -> f64 {
         if v.is_empty() {
             return 0.0;
         }
-        v.sum() / v.len() as f64
+        v.sum().to_f64().unwrap_or(f64::NAN) / v.len() as f64
     }
 
     /// Sigmoid gate function
@@ -55,33 +330,49 @@ impl Sweep {
 
     /// Compute threshold based on schedule
     fn compute_threshold(&self, t: f64) -> f64 {
-        match self.schedule.as_str() {
-            "cosine" => {
+        if let Some(threshold_fn) = &self.threshold_fn {
+            let mut threshold_fn = threshold_fn.lock().unwrap_or_else(|e| e.into_inner());
+            return threshold_fn(t);
+        }
+
+        match self.schedule {
+            SweepSchedule::Cosine => {
                 let phase = std::f64::consts::PI * t / self.period;
                 self.tau0 + 0.5 * (1.0 + phase.cos()) * self.delta_tau
             }
-            "linear" => {
+            SweepSchedule::Linear => {
                 let cycle = (t % self.period) / self.period;
                 self.tau0 + cycle * self.delta_tau
             }
-            _ => self.tau0,
+            SweepSchedule::Constant => self.tau0,
         }
     }
 
-    /// Get current threshold value
+    /// Get current threshold value, including any rate penalty
     pub fn current_threshold(&self) -> f64 {
-        self.compute_threshold(self.t)
+        self.compute_threshold(self.t) + self.rate_penalty()
     }
 
-    /// Reset time counter
+    /// Gate outcomes counted since construction or `reset_rejection_stats`
+    pub fn rejection_stats(&self) -> SweepStats {
+        self.stats
+    }
+
+    /// Start counting gate outcomes afresh
+    pub fn reset_rejection_stats(&mut self) {
+        self.stats = SweepStats::default();
+    }
+
+    /// Reset time counter and forget recorded arrivals
     pub fn reset(&mut self) {
         self.t = 0.0;
+        self.arrivals.clear();
     }
 }
 
 impl Default for Sweep {
     fn default() -> Self {
-        Self::new(0.5, 0.1, "cosine".to_string())
+        Self::new(0.5, 0.1, SweepSchedule::Cosine)
     }
 }
 
@@ -95,6 +386,10 @@ impl OmegaOperator for Sweep {
         Ok(sweep.transform(&input))
     }
 
+    fn inverse(&self, output: Self::Output, _params: &Self::Params) -> Result<Self::Input> {
+        self.invert(&output)
+    }
+
     fn name(&self) -> &str {
         "Sweep"
     }
@@ -111,7 +406,7 @@ mod tests {
 
     #[test]
     fn test_sweep_filtering() {
-        let mut sweep = Sweep::new(0.5, 0.1, "cosine".to_string());
+        let mut sweep = Sweep::new(0.5, 0.1, SweepSchedule::Cosine);
 
         // High mean vector should pass
         let v_high = arr1(&[1.0, 1.0, 1.0, 1.0, 1.0]);
@@ -126,7 +421,7 @@ mod tests {
 
     #[test]
     fn test_threshold_schedule() {
-        let mut sweep = Sweep::new(0.5, 0.1, "cosine".to_string());
+        let mut sweep = Sweep::new(0.5, 0.1, SweepSchedule::Cosine);
 
         let tau0 = sweep.current_threshold();
         for _ in 0..50 {
@@ -138,9 +433,196 @@ mod tests {
         assert_ne!(tau0, tau50);
     }
 
+    #[test]
+    fn test_warmup_opens_gate() {
+        let mut sweep = Sweep::default().with_warmup(3);
+        let v_low = arr1(&[0.1, 0.1, 0.1, 0.1, 0.1]);
+
+        for _ in 0..3 {
+            assert!(sweep.in_warmup());
+            assert_eq!(sweep.transform(&v_low), v_low);
+        }
+
+        assert!(!sweep.in_warmup());
+        assert!(sweep.transform(&v_low).sum() < v_low.sum());
+
+        sweep.reset();
+        assert_eq!(sweep.transform(&v_low), v_low);
+    }
+
+    #[test]
+    fn test_period_sets_cycle_speed() {
+        // Count threshold minima (one per cosine cycle) over the same 200 transforms
+        let cycles = |period: f64| {
+            let mut sweep = Sweep::default().with_cycle(period, 0.2).unwrap();
+            let mut thresholds = Vec::new();
+            for _ in 0..200 {
+                thresholds.push(sweep.current_threshold());
+                sweep.transform(&arr1(&[0.5; 5]));
+            }
+            thresholds
+                .windows(3)
+                .filter(|w| w[1] < w[0] && w[1] <= w[2])
+                .count()
+        };
+
+        assert_eq!(cycles(100.0), 1);
+        assert_eq!(cycles(10.0), 10);
+
+        assert!(Sweep::default().with_cycle(0.0, 0.2).is_err());
+        assert!(Sweep::default().with_cycle(f64::NAN, 0.2).is_err());
+    }
+
+    #[test]
+    fn test_burst_raises_threshold() {
+        let start = Instant::now();
+        let v = arr1(&[1.0; 5]);
+        let limited = || Sweep::new(0.5, 0.1, SweepSchedule::Constant).with_max_rate(Some(10.0)).unwrap();
+
+        // 50 arrivals at 5 per second, then 50 within a tenth of a second
+        let mut paced = limited();
+        let mut burst = limited();
+        let (mut paced_out, mut burst_out) = (0.0, 0.0);
+        for i in 0..50u32 {
+            paced.record_arrival(start + Duration::from_millis(200) * i);
+            paced_out = paced.transform(&v).sum();
+            burst.record_arrival(start + Duration::from_millis(2) * i);
+            burst_out = burst.transform(&v).sum();
+        }
+
+        // Slow but sustained traffic is gated on its mean alone
+        let unlimited = Sweep::new(0.5, 0.1, SweepSchedule::Constant).transform(&v).sum();
+        assert_eq!(paced.observed_rate(), 5.0);
+        assert_eq!(paced_out, unlimited);
+        assert_eq!(burst.observed_rate(), 50.0);
+        // Four multiples of max_rate over the limit: gate odds divided by e^4
+        let odds = |sum: f64| (sum / 5.0) / (1.0 - sum / 5.0);
+        assert!(burst_out < paced_out);
+        assert!((odds(paced_out) / odds(burst_out) - 4f64.exp()).abs() < 1e-6);
+        assert_eq!(paced.rate_attenuation(1.0), 1.0);
+        assert!((burst.rate_attenuation(1.0) - burst_out / paced_out).abs() < 1e-12);
+
+        // The penalty lapses once the burst leaves the window
+        burst.record_arrival(start + Duration::from_secs(3));
+        assert_eq!(burst.observed_rate(), 1.0);
+        assert_eq!(burst.transform(&v).sum(), unlimited);
+
+        assert!(Sweep::default().with_max_rate(Some(0.0)).is_err());
+        assert!(Sweep::default().with_max_rate(Some(f64::INFINITY)).is_err());
+    }
+
+    #[test]
+    fn test_per_element_gates_each_component() {
+        // One spike lifts the mean far above the threshold
+        let mut v = vec![0.1; 10];
+        v[0] = 10.0;
+        let v = arr1(&v);
+
+        let mut mean = Sweep::new(0.5, 0.1, SweepSchedule::Constant);
+        let mut per_element = Sweep::new(0.5, 0.1, SweepSchedule::Constant).with_mode(SweepMode::PerElement);
+        let by_mean = mean.transform(&v);
+        let by_element = per_element.transform(&v);
+
+        // The mean gate passes the small values almost untouched...
+        assert!(by_mean[1] > 0.099);
+        // ...per element they are cut to sigmoid(-4) while the spike passes
+        assert!((by_element[1] - 0.1 / (1.0 + 4f64.exp())).abs() < 1e-12);
+        assert!(by_element[1] < by_mean[1] / 5.0);
+        assert!(by_element[0] > 9.99);
+
+        // Each gate stays in [0, 1]
+        assert!(by_element.iter().zip(v.iter()).all(|(y, x)| y.abs() <= x.abs()));
+        assert_eq!(per_element.lipschitz_constant(), 1.0);
+        assert!(per_element.invert(&by_element).is_err());
+        assert_eq!(Sweep::default().mode(), SweepMode::Mean);
+    }
+
+    #[test]
+    fn test_rejection_stats_split() {
+        let mut sweep = Sweep::new(0.5, 0.1, SweepSchedule::Constant);
+        let high = arr1(&[1.0; 5]);
+        let low = arr1(&[0.1; 5]);
+        let edge = arr1(&[0.5; 5]);
+
+        for _ in 0..6 {
+            sweep.transform(&high);
+        }
+        for _ in 0..3 {
+            sweep.transform(&low);
+        }
+        sweep.transform(&edge);
+
+        let stats = sweep.rejection_stats();
+        assert_eq!(stats, SweepStats { passed: 6, attenuated: 1, blocked: 3 });
+        assert_eq!(stats.total(), 10);
+        assert!((stats.rejection_rate() - 0.3).abs() < 1e-12);
+
+        // Warmup transforms pass; counting restarts on request only
+        let mut warm = Sweep::new(0.5, 0.1, SweepSchedule::Constant).with_warmup(2);
+        warm.transform(&low);
+        warm.transform(&low);
+        warm.transform(&low);
+        assert_eq!(warm.rejection_stats(), SweepStats { passed: 2, attenuated: 0, blocked: 1 });
+        warm.reset();
+        assert_eq!(warm.rejection_stats().total(), 3);
+        warm.reset_rejection_stats();
+        assert_eq!(warm.rejection_stats(), SweepStats::default());
+        assert_eq!(SweepStats::default().rejection_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_schedule_parsing() {
+        for schedule in [SweepSchedule::Cosine, SweepSchedule::Linear, SweepSchedule::Constant] {
+            assert_eq!(schedule.to_string().parse::<SweepSchedule>().unwrap(), schedule);
+        }
+
+        let err = "cosign".parse::<SweepSchedule>().unwrap_err();
+        assert!(matches!(err, OmegaError::ParameterError(_)));
+    }
+
+    #[test]
+    fn test_constant_threshold_fn_matches_constant_schedule() {
+        let mut builtin = Sweep::new(0.3, 0.1, SweepSchedule::Constant);
+        let mut custom = Sweep::new(0.9, 0.1, SweepSchedule::Cosine)
+            .with_threshold_fn(Box::new(|_tick| 0.3));
+
+        for level in [0.1, 0.3, 0.5, 1.0, -0.2] {
+            let v = arr1(&[level; 5]);
+            assert_eq!(custom.transform(&v), builtin.transform(&v));
+        }
+        assert_eq!(custom.current_threshold(), 0.3);
+    }
+
+    #[test]
+    fn test_threshold_fn_receives_tick() {
+        let mut sweep = Sweep::default().with_threshold_fn(Box::new(|tick| tick));
+        for _ in 0..4 {
+            sweep.transform(&arr1(&[0.5; 5]));
+        }
+        assert_eq!(sweep.current_threshold(), 4.0);
+    }
+
+    #[test]
+    fn test_inverse_recovers_input_through_open_gate() {
+        let sweep = Sweep::new(0.5, 0.1, SweepSchedule::Cosine);
+        let params = SweepParams::default();
+        let v = arr1(&[1.2, 0.8, 1.5, 0.9, 1.1]);
+
+        let gated = OmegaOperator::apply(&sweep, v.clone(), &params).unwrap();
+        let recovered = sweep.inverse(gated, &params).unwrap();
+        for (a, b) in recovered.iter().zip(v.iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+
+        // With the threshold far above the input the gate is shut
+        let shut = Sweep::new(5.0, 0.1, SweepSchedule::Constant);
+        let closed = OmegaOperator::apply(&shut, arr1(&[1.0, -1.0, 0.5, -0.5, 0.0]), &params).unwrap();
+        assert!(shut.inverse(closed, &params).is_err());
+    }
+
     #[test]
     fn test_contractivity() {
-        let mut sweep = Sweep::new(0.5, 0.1, "cosine".to_string());
+        let mut sweep = Sweep::new(0.5, 0.1, SweepSchedule::Cosine);
         let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
         let result = sweep.transform(&v);
 
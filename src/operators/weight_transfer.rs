@@ -1,10 +1,11 @@
-/// Weight Transfer Operator Ŵ_γ
-///
-/// Multi-scale coherence redistribution for adaptive resilience.
+//! Weight Transfer Operator Ŵ_γ
+//!
+//! Multi-scale coherence redistribution for adaptive resilience.
 
 use crate::types::*;
 use crate::operators::OmegaOperator;
-use ndarray::Array1;
+use crate::utils;
+use ndarray::{Array1, Array2};
 use std::collections::HashMap;
 
 #[derive(Clone)]
@@ -12,6 +13,8 @@ pub struct WeightTransfer {
     gamma: f64,
     weights: HashMap<ScaleLevel, f64>,
     target_weights: HashMap<ScaleLevel, f64>,
+    /// Per-component weights, one row per scale in `ScaleLevel::ALL` order
+    component_weights: Option<Array2<f64>>,
 }
 
 impl WeightTransfer {
@@ -27,25 +30,110 @@ impl WeightTransfer {
             gamma,
             weights: weight_map,
             target_weights,
+            component_weights: None,
         }
     }
 
-    /// Apply weight transfer
-    pub fn transform(&mut self, v: &OmegaVector) -> OmegaVector {
+    /// Weight individual components within each scale
+    ///
+    /// Rows are scales in `ScaleLevel::ALL` order (micro, meso, macro) and
+    /// columns are components. Column `j` weights every component `i` with
+    /// `i % ncols == j`, so a 5-column matrix applies to each 5D chunk alike.
+    /// Component weights multiply the (adaptive) scalar scale weight; a
+    /// matrix of ones reproduces the scalar scheme.
+    pub fn with_component_weights(mut self, weights: Array2<f64>) -> Result<Self> {
+        if weights.nrows() != ScaleLevel::ALL.len() {
+            return Err(OmegaError::ParameterError(format!(
+                "Component weights need {} rows (one per scale), got {}",
+                ScaleLevel::ALL.len(),
+                weights.nrows()
+            )));
+        }
+        if weights.ncols() == 0 {
+            return Err(OmegaError::ParameterError(
+                "Component weights need at least one column".to_string()
+            ));
+        }
+        if weights.iter().any(|w| !w.is_finite()) {
+            return Err(OmegaError::ParameterError(
+                "Component weights must be finite".to_string()
+            ));
+        }
+
+        self.component_weights = Some(weights);
+        Ok(self)
+    }
+
+    /// Per-component weights, if set
+    pub fn component_weights(&self) -> Option<&Array2<f64>> {
+        self.component_weights.as_ref()
+    }
+
+    /// Apply weight transfer (to a vector of either component width)
+    pub fn transform<F: Float>(&mut self, v: &Array1<F>) -> Array1<F> {
         // Update weights: w' = (1-γ)w + γw̃
         self.update_weights();
+        self.apply_weights(v)
+    }
 
+    /// Weighted sum of the scale bands with the current weights, without
+    /// adapting them
+    pub fn apply_weights<F: Float>(&self, v: &Array1<F>) -> Array1<F> {
         // Project onto multi-scale components
-        let mut result = Array1::zeros(v.len());
-
-        for (level, &weight) in &self.weights {
-            let projection = self.project_to_scale(v, level);
-            result = result + projection * weight;
+        let mut result = Array1::<F>::zeros(v.len());
+
+        // Fixed level order keeps the floating-point sum reproducible
+        for (row, level) in ScaleLevel::ALL.iter().enumerate() {
+            if let Some(&weight) = self.weights.get(level) {
+                let weight: F = component(weight);
+                let mut projection = self.project_to_scale(v, level).mapv(|x| x * weight);
+                if let Some(components) = &self.component_weights {
+                    let columns = components.ncols();
+                    for (i, x) in projection.iter_mut().enumerate() {
+                        *x = *x * component(components[[row, i % columns]]);
+                    }
+                }
+                result = result + projection;
+            }
         }
 
         result
     }
 
+    /// Undo `apply_weights` with the current weights: each band divided by
+    /// its weight
+    ///
+    /// Exact only where the bands are orthogonal projections, so `v` must
+    /// have a length divisible by four, every scale a nonzero weight and no
+    /// per-component weights be set; otherwise a `ParameterError`.
+    pub fn invert<F: Float>(&self, v: &Array1<F>) -> Result<Array1<F>> {
+        if self.component_weights.is_some() {
+            return Err(OmegaError::ParameterError(
+                "Weight transfer with component weights is not invertible".to_string()
+            ));
+        }
+        if !v.len().is_multiple_of(4) {
+            return Err(OmegaError::ParameterError(format!(
+                "Weight transfer inverts lengths divisible by 4, got {}", v.len()
+            )));
+        }
+
+        let bands = Self::haar_bands(v);
+        let mut result = Array1::<F>::zeros(v.len());
+        for (band, level) in bands.into_iter().zip(ScaleLevel::ALL.iter()) {
+            let weight = match self.weights.get(level) {
+                Some(&weight) if weight != 0.0 && weight.is_finite() => weight,
+                _ => return Err(OmegaError::ParameterError(format!(
+                    "Weight transfer needs a nonzero {:?} weight to invert", level
+                ))),
+            };
+            let inverse: F = component(1.0 / weight);
+            result = result + band.mapv(|x| x * inverse);
+        }
+
+        Ok(result)
+    }
+
     /// Update weights adaptively
     fn update_weights(&mut self) {
         for (level, weight) in self.weights.iter_mut() {
@@ -55,54 +143,69 @@ impl WeightTransfer {
     }
 
     /// Project vector to specific scale
-    fn project_to_scale(&self, v: &OmegaVector, level: &ScaleLevel) -> OmegaVector {
+    fn project_to_scale<F: Float>(&self, v: &Array1<F>, level: &ScaleLevel) -> Array1<F> {
+        let [micro, meso, macro_] = Self::haar_bands(v);
         match level {
-            ScaleLevel::Micro => {
-                // High-frequency components (detail)
-                self.highpass_filter(v)
-            }
-            ScaleLevel::Meso => {
-                // Mid-frequency components (structure)
-                self.bandpass_filter(v)
-            }
-            ScaleLevel::Macro => {
-                // Low-frequency components (trend)
-                self.lowpass_filter(v)
-            }
+            // Finest detail (level-1 wavelet coefficients)
+            ScaleLevel::Micro => micro,
+            // Intermediate band (level-2 detail coefficients)
+            ScaleLevel::Meso => meso,
+            // Trend (level-2 approximation coefficients)
+            ScaleLevel::Macro => macro_,
         }
     }
 
-    /// Simple lowpass filter (moving average)
-    fn lowpass_filter(&self, v: &OmegaVector) -> OmegaVector {
-        let mut result = Array1::zeros(v.len());
-        let window = 3;
+    /// Two-level Haar decomposition of `v` into its micro, meso and macro
+    /// bands, each reconstructed to the input length
+    ///
+    /// The bands are orthogonal and sum to `v`. Inputs whose length is not
+    /// a multiple of four are extended by repeating the last component and
+    /// the reconstructions truncated again.
+    fn haar_bands<F: Float>(v: &Array1<F>) -> [Array1<F>; 3] {
+        let n = v.len();
+        if n == 0 {
+            return [v.clone(), v.clone(), v.clone()];
+        }
+        let padded_len = n.div_ceil(4) * 4;
+        let x: Vec<F> = (0..padded_len).map(|i| v[i.min(n - 1)]).collect();
 
-        for i in 0..v.len() {
-            let mut sum = 0.0;
-            let mut count = 0;
+        let (a1, d1) = Self::haar_step(&x);
+        let (a2, d2) = Self::haar_step(&a1);
+        let zeros = |len: usize| vec![F::zero(); len];
 
-            for j in i.saturating_sub(window/2)..=(i + window/2).min(v.len() - 1) {
-                sum += v[j];
-                count += 1;
-            }
+        let micro = Self::haar_inverse_step(&zeros(a1.len()), &d1);
+        let meso = Self::haar_inverse_step(&Self::haar_inverse_step(&zeros(a2.len()), &d2), &zeros(d1.len()));
+        let macro_ = Self::haar_inverse_step(&Self::haar_inverse_step(&a2, &zeros(d2.len())), &zeros(d1.len()));
 
-            result[i] = sum / count as f64;
-        }
-
-        result
+        [micro, meso, macro_].map(|band| Array1::from_iter(band.into_iter().take(n)))
     }
 
-    /// Simple highpass filter (difference from lowpass)
-    fn highpass_filter(&self, v: &OmegaVector) -> OmegaVector {
-        let lowpass = self.lowpass_filter(v);
-        v - &lowpass
+    /// One Haar analysis step on an even-length signal: (approximation, detail)
+    ///
+    /// The detail coefficients are the first differences within each pair,
+    /// i.e. every other entry of `utils::diff` with the sign flipped.
+    fn haar_step<F: Float>(x: &[F]) -> (Vec<F>, Vec<F>) {
+        let scale: F = component(std::f64::consts::FRAC_1_SQRT_2);
+        let approximation = x
+            .chunks_exact(2)
+            .map(|pair| (pair[0] + pair[1]) * scale)
+            .collect();
+        let detail = utils::diff(&Array1::from_vec(x.to_vec()))
+            .iter()
+            .step_by(2)
+            .map(|&dx| -dx * scale)
+            .collect();
+        (approximation, detail)
     }
 
-    /// Simple bandpass filter (combination)
-    fn bandpass_filter(&self, v: &OmegaVector) -> OmegaVector {
-        let lowpass = self.lowpass_filter(v);
-        let highpass = self.highpass_filter(v);
-        (lowpass + highpass) * 0.5
+    /// Inverse of `haar_step`
+    fn haar_inverse_step<F: Float>(approximation: &[F], detail: &[F]) -> Vec<F> {
+        let scale: F = component(std::f64::consts::FRAC_1_SQRT_2);
+        approximation
+            .iter()
+            .zip(detail)
+            .flat_map(|(&a, &d)| [(a + d) * scale, (a - d) * scale])
+            .collect()
     }
 
     /// Set target weights for adaptation
@@ -117,6 +220,43 @@ impl WeightTransfer {
     pub fn get_weights(&self) -> &HashMap<ScaleLevel, f64> {
         &self.weights
     }
+
+    /// L1 distance between the current and target weights (a level missing
+    /// from either map counts as weight 0)
+    pub fn distance_to_target(&self) -> f64 {
+        ScaleLevel::ALL
+            .iter()
+            .map(|level| {
+                let weight = self.weights.get(level).copied().unwrap_or(0.0);
+                let target = self.target_weights.get(level).copied().unwrap_or(0.0);
+                (weight - target).abs()
+            })
+            .sum()
+    }
+
+    /// Whether every weight is within `tol` of its target
+    pub fn has_converged(&self, tol: f64) -> bool {
+        ScaleLevel::ALL.iter().all(|level| {
+            let weight = self.weights.get(level).copied().unwrap_or(0.0);
+            let target = self.target_weights.get(level).copied().unwrap_or(0.0);
+            (weight - target).abs() <= tol
+        })
+    }
+
+    /// Number of further `transform` calls until `has_converged(tol)`, or
+    /// `None` if it takes more than `max_steps`
+    ///
+    /// Each update shrinks the gap to the targets by the factor `1 - γ`.
+    pub fn steps_to_converge(&self, tol: f64, max_steps: usize) -> Option<usize> {
+        let mut simulated = self.clone();
+        for steps in 0..=max_steps {
+            if simulated.has_converged(tol) {
+                return Some(steps);
+            }
+            simulated.update_weights();
+        }
+        None
+    }
 }
 
 impl Default for WeightTransfer {
@@ -154,7 +294,7 @@ impl OmegaOperator for WeightTransfer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ndarray::arr1;
+    use ndarray::{arr1, Array2};
 
     #[test]
     fn test_weight_transfer() {
@@ -194,6 +334,77 @@ mod tests {
         assert_ne!(initial_micro, final_micro);
     }
 
+    #[test]
+    fn test_converges_in_predicted_steps() {
+        let mut wt = WeightTransfer::default();
+        wt.set_target_weights(vec![
+            (ScaleLevel::Micro, 0.9),
+            (ScaleLevel::Meso, 0.05),
+            (ScaleLevel::Macro, 0.05),
+        ]);
+        assert!((wt.distance_to_target() - 1.4).abs() < 1e-12);
+        assert!(!wt.has_converged(1e-6));
+
+        // Largest gap 0.7 shrinks by 1 - γ = 0.7 per step: 0.7^(n+1) <= 1e-6 first at n = 38
+        let predicted = wt.steps_to_converge(1e-6, 1000).unwrap();
+        assert_eq!(predicted, 38);
+        assert_eq!(wt.steps_to_converge(1e-6, 10), None);
+
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        for _ in 0..predicted {
+            wt.transform(&v);
+        }
+        assert!(wt.has_converged(1e-6));
+        assert!(wt.distance_to_target() < 3e-6);
+        assert_eq!(wt.steps_to_converge(1e-6, 0), Some(0));
+    }
+
+    #[test]
+    fn test_ramp_energy_in_macro_band() {
+        let wt = WeightTransfer::default();
+        let ramp = Array1::from_iter((0..64).map(|i| i as f64 * 0.1));
+        let energy = |level| crate::utils::energy(&wt.project_to_scale(&ramp, &level));
+        let (micro, meso, macro_) = (
+            energy(ScaleLevel::Micro),
+            energy(ScaleLevel::Meso),
+            energy(ScaleLevel::Macro),
+        );
+        let total = micro + meso + macro_;
+
+        assert!(macro_ / total > 0.99);
+        assert!(micro / total < 1e-3);
+        assert!(meso / total < 1e-2);
+    }
+
+    #[test]
+    fn test_bands_reconstruct_input() {
+        let wt = WeightTransfer::default();
+        for len in [1, 5, 7, 12] {
+            let v = Array1::from_iter((0..len).map(|i| ((i * 7) % 5) as f64 - 2.0));
+            let sum = ScaleLevel::ALL
+                .iter()
+                .map(|level| wt.project_to_scale(&v, level))
+                .fold(Array1::<f64>::zeros(len), |acc, band| acc + band);
+            assert_eq!(sum.len(), len);
+            assert!(sum.iter().zip(v.iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_invert_undoes_apply_weights() {
+        let mut wt = WeightTransfer::default();
+        let v = Array1::from_iter((0..12).map(|i| ((i * 5) % 7) as f64 - 3.0));
+        let w = wt.transform(&v);
+        let back = wt.invert(&w).unwrap();
+        assert!(back.iter().zip(v.iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+
+        assert!(wt.invert(&arr1(&[1.0, 2.0, 3.0])).is_err());
+        let no_macro = WeightTransfer::new(0.3, vec![(ScaleLevel::Micro, 0.5), (ScaleLevel::Meso, 0.5)]);
+        assert!(no_macro.invert(&no_macro.apply_weights(&v)).is_err());
+        let weighted = WeightTransfer::default().with_component_weights(Array2::ones((3, 4))).unwrap();
+        assert!(weighted.invert(&v).is_err());
+    }
+
     #[test]
     fn test_convex_combination() {
         let wt = WeightTransfer::default();
@@ -204,4 +415,38 @@ mod tests {
         // Weights should sum to approximately 1
         assert!((sum - 1.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_uniform_component_weights_match_scalar() {
+        let v = arr1(&[1.0, -2.0, 3.0, 0.5, 5.0, 4.0, -1.0]);
+        let mut scalar = WeightTransfer::default();
+        let mut uniform = WeightTransfer::default()
+            .with_component_weights(Array2::ones((3, v.len())))
+            .unwrap();
+
+        for _ in 0..3 {
+            let expected = scalar.transform(&v);
+            let actual = uniform.transform(&v);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_zeroed_component_removed() {
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0]);
+        let mut weights = Array2::ones((3, 5));
+        weights.column_mut(2).fill(0.0);
+        let mut wt = WeightTransfer::default().with_component_weights(weights).unwrap();
+
+        let result = wt.transform(&v);
+        // Column 2 covers component 2 of every 5D chunk
+        assert_eq!(result[2], 0.0);
+        assert_eq!(result[7], 0.0);
+        assert!(result[1].abs() > 1e-10);
+
+        assert!(WeightTransfer::default().with_component_weights(Array2::ones((2, 5))).is_err());
+        assert!(WeightTransfer::default().with_component_weights(Array2::ones((3, 0))).is_err());
+    }
 }
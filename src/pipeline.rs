@@ -0,0 +1,595 @@
+//! Composable vector-domain operator pipeline
+//!
+//! Stages share a uniform `OmegaVector -> OmegaVector` interface so they can be
+//! stored, ordered and analysed together. Masking works on bytes and is not a
+//! pipeline stage.
+
+use crate::types::*;
+use crate::operators::{
+    OmegaOperator, NO_INVERSE,
+    doublekick::DoubleKick,
+    pfadinvarianz::Pfadinvarianz,
+    resonance::ResonanceOperator,
+    sweep::Sweep,
+    weight_transfer::WeightTransfer,
+};
+use ndarray::{Array1, Array2};
+
+/// Type-erased vector stage of an `OmegaPipeline`
+pub trait VectorStage {
+    /// Stage name
+    fn name(&self) -> &str;
+
+    /// Apply the stage to a vector
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector>;
+
+    /// Length of the stage's output for `input_dim`-dimensional input, or a
+    /// `ParameterError` if the stage cannot take such input
+    fn output_dim(&self, input_dim: usize) -> Result<usize> {
+        Ok(input_dim)
+    }
+
+    /// Matrix of the stage for `dim`-dimensional input, if the stage is linear
+    fn linear_matrix(&self, _dim: usize) -> Option<Array2<f64>> {
+        None
+    }
+
+    /// Lipschitz constant of the stage
+    fn lipschitz_constant(&self) -> f64;
+
+    /// Recover an input the stage maps to `v`, where the stage is invertible
+    fn inverse(&mut self, _v: &OmegaVector) -> Result<OmegaVector> {
+        Err(OmegaError::ParameterError(NO_INVERSE.to_string()))
+    }
+}
+
+/// Fixed linear map `v -> A v`
+#[derive(Clone, Debug)]
+pub struct LinearStage {
+    matrix: Array2<f64>,
+}
+
+/// Smallest pivot `LinearStage::inverse` divides by
+const MIN_PIVOT: f64 = 1e-12;
+
+impl LinearStage {
+    pub fn new(matrix: Array2<f64>) -> Self {
+        Self { matrix }
+    }
+
+    fn check_input(&self, input_dim: usize) -> Result<()> {
+        if self.matrix.ncols() != input_dim {
+            return Err(OmegaError::ParameterError(format!(
+                "Linear stage takes {} components, got {}",
+                self.matrix.ncols(),
+                input_dim
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl VectorStage for LinearStage {
+    fn name(&self) -> &str {
+        "Linear"
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        self.check_input(v.len())?;
+        Ok(self.matrix.dot(v))
+    }
+
+    fn output_dim(&self, input_dim: usize) -> Result<usize> {
+        self.check_input(input_dim)?;
+        Ok(self.matrix.nrows())
+    }
+
+    fn linear_matrix(&self, dim: usize) -> Option<Array2<f64>> {
+        if self.matrix.ncols() == dim {
+            Some(self.matrix.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Spectral norm, by power iteration on AᵀA
+    fn lipschitz_constant(&self) -> f64 {
+        let gram = self.matrix.t().dot(&self.matrix);
+        let mut x = Array1::from_elem(gram.ncols(), 1.0);
+        let mut eigenvalue = 0.0;
+        for _ in 0..200 {
+            let y = gram.dot(&x);
+            let norm = y.iter().map(|v| v * v).sum::<f64>().sqrt();
+            if norm == 0.0 {
+                return 0.0;
+            }
+            eigenvalue = norm / x.iter().map(|v| v * v).sum::<f64>().sqrt();
+            x = y / norm;
+        }
+        eigenvalue.sqrt()
+    }
+
+    /// Solves `A x = v` by Gaussian elimination with partial pivoting; only
+    /// square, non-singular matrices are invertible
+    fn inverse(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        let n = self.matrix.nrows();
+        if self.matrix.ncols() != n || v.len() != n {
+            return Err(OmegaError::ParameterError(format!(
+                "Linear stage inverse needs a square matrix matching {} components",
+                v.len()
+            )));
+        }
+
+        let mut a = self.matrix.clone();
+        let mut x = v.clone();
+        for col in 0..n {
+            let pivot = (col..n)
+                .max_by(|&i, &j| a[[i, col]].abs().total_cmp(&a[[j, col]].abs()))
+                .unwrap_or(col);
+            if a[[pivot, col]].abs() < MIN_PIVOT {
+                return Err(OmegaError::ParameterError("Linear stage matrix is singular".to_string()));
+            }
+            for k in 0..n {
+                a.swap([col, k], [pivot, k]);
+            }
+            x.swap(col, pivot);
+
+            for row in col + 1..n {
+                let factor = a[[row, col]] / a[[col, col]];
+                for k in col..n {
+                    a[[row, k]] -= factor * a[[col, k]];
+                }
+                x[row] -= factor * x[col];
+            }
+        }
+        for row in (0..n).rev() {
+            let tail: f64 = (row + 1..n).map(|k| a[[row, k]] * x[k]).sum();
+            x[row] = (x[row] - tail) / a[[row, row]];
+        }
+        Ok(x)
+    }
+}
+
+impl VectorStage for Sweep {
+    fn name(&self) -> &str {
+        "Sweep"
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        Ok(self.transform(v))
+    }
+
+    fn lipschitz_constant(&self) -> f64 {
+        OmegaOperator::lipschitz_constant(self)
+    }
+
+    /// Divides out the gate at the sweep's current tick
+    fn inverse(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        self.invert(v)
+    }
+}
+
+impl VectorStage for Pfadinvarianz {
+    fn name(&self) -> &str {
+        "Pfadinvarianz"
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        Ok(self.apply(v))
+    }
+
+    fn linear_matrix(&self, dim: usize) -> Option<Array2<f64>> {
+        // Average of the permutation matrices
+        let mut matrix = Array2::zeros((dim, dim));
+        let mut probe = Array1::zeros(dim);
+        for j in 0..dim {
+            probe[j] = 1.0;
+            matrix.column_mut(j).assign(&self.apply(&probe));
+            probe[j] = 0.0;
+        }
+        Some(matrix)
+    }
+
+    fn lipschitz_constant(&self) -> f64 {
+        OmegaOperator::lipschitz_constant(self)
+    }
+
+    /// Identity, exact on the projection's range
+    fn inverse(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        Ok(v.clone())
+    }
+}
+
+impl VectorStage for WeightTransfer {
+    fn name(&self) -> &str {
+        "WeightTransfer"
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        Ok(self.transform(v))
+    }
+
+    fn lipschitz_constant(&self) -> f64 {
+        OmegaOperator::lipschitz_constant(self)
+    }
+}
+
+impl VectorStage for DoubleKick {
+    fn name(&self) -> &str {
+        "DoubleKick"
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        Ok(self.apply(v))
+    }
+
+    fn lipschitz_constant(&self) -> f64 {
+        OmegaOperator::lipschitz_constant(self)
+    }
+}
+
+impl VectorStage for ResonanceOperator {
+    fn name(&self) -> &str {
+        "Resonance"
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        Ok(self.apply(v))
+    }
+
+    fn lipschitz_constant(&self) -> f64 {
+        OmegaOperator::lipschitz_constant(self)
+    }
+}
+
+/// Any vector-domain operator, as one concrete type
+///
+/// Lets heterogeneous operators live in a plain `Vec<Operator>` without
+/// boxing; each variant delegates to the operator's `VectorStage` impl.
+// DoubleKick carries its RNG inline; without the `fft` feature no other
+// variant comes close in size
+#[allow(clippy::large_enum_variant)]
+pub enum Operator {
+    Sweep(Sweep),
+    Pfad(Pfadinvarianz),
+    Weight(WeightTransfer),
+    DoubleKick(DoubleKick),
+    Resonance(ResonanceOperator),
+}
+
+impl Operator {
+    fn stage(&self) -> &dyn VectorStage {
+        match self {
+            Operator::Sweep(op) => op,
+            Operator::Pfad(op) => op,
+            Operator::Weight(op) => op,
+            Operator::DoubleKick(op) => op,
+            Operator::Resonance(op) => op,
+        }
+    }
+
+    fn stage_mut(&mut self) -> &mut dyn VectorStage {
+        match self {
+            Operator::Sweep(op) => op,
+            Operator::Pfad(op) => op,
+            Operator::Weight(op) => op,
+            Operator::DoubleKick(op) => op,
+            Operator::Resonance(op) => op,
+        }
+    }
+
+    /// Apply the wrapped operator to a vector
+    pub fn apply_vector(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        self.stage_mut().forward(v)
+    }
+}
+
+impl VectorStage for Operator {
+    fn name(&self) -> &str {
+        self.stage().name()
+    }
+
+    fn forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        self.apply_vector(v)
+    }
+
+    fn output_dim(&self, input_dim: usize) -> Result<usize> {
+        self.stage().output_dim(input_dim)
+    }
+
+    fn linear_matrix(&self, dim: usize) -> Option<Array2<f64>> {
+        self.stage().linear_matrix(dim)
+    }
+
+    fn lipschitz_constant(&self) -> f64 {
+        self.stage().lipschitz_constant()
+    }
+
+    fn inverse(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        self.stage_mut().inverse(v)
+    }
+}
+
+/// Ordered sequence of vector stages
+#[derive(Default)]
+pub struct OmegaPipeline {
+    stages: Vec<Box<dyn VectorStage + Send>>,
+}
+
+impl OmegaPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a stage
+    pub fn push<S: VectorStage + Send + 'static>(&mut self, stage: S) -> &mut Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Number of stages
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Stage names in application order
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|s| s.name()).collect()
+    }
+
+    /// Length of the pipeline's output for `input_dim`-dimensional input,
+    /// failing at the first stage that cannot take its predecessor's output
+    pub fn output_dim(&self, input_dim: usize) -> Result<usize> {
+        self.stages.iter().try_fold(input_dim, |dim, stage| stage.output_dim(dim))
+    }
+
+    /// Apply every stage in order
+    pub fn apply_forward(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        let mut result = v.clone();
+        for stage in self.stages.iter_mut() {
+            result = stage.forward(&result)?;
+        }
+        Ok(result)
+    }
+
+    /// Apply every stage to each `dim`-component chunk of `v` in turn
+    ///
+    /// The pipeline must map `dim` components to `dim` (see `output_dim`).
+    pub fn forward_chunks(&mut self, v: &OmegaVector, dim: usize) -> Result<OmegaVector> {
+        map_chunks(v, dim, |chunk| self.apply_forward(chunk))
+    }
+
+    /// Undo `forward_chunks`, chunk by chunk
+    pub fn inverse_chunks(&mut self, v: &OmegaVector, dim: usize) -> Result<OmegaVector> {
+        map_chunks(v, dim, |chunk| self.apply_inverse(chunk))
+    }
+
+    /// Undo every stage, last stage first
+    ///
+    /// Fails with `ParameterError(NO_INVERSE)` at the first stage that cannot
+    /// be inverted.
+    pub fn apply_inverse(&mut self, v: &OmegaVector) -> Result<OmegaVector> {
+        let mut result = v.clone();
+        for stage in self.stages.iter_mut().rev() {
+            result = stage.inverse(&result)?;
+        }
+        Ok(result)
+    }
+
+    /// Upper bound on the composite Lipschitz constant: the product of the
+    /// stage constants
+    pub fn composite_lipschitz(&self) -> f64 {
+        self.stages.iter().map(|s| s.lipschitz_constant()).product()
+    }
+
+    /// Numerical Jacobian of the pipeline at `v` via central differences
+    ///
+    /// Column `j` holds the output change per unit change of input component `j`.
+    /// Stateful stages (sweep time, weight adaptation) advance on every
+    /// evaluation, and DoubleKick injects fresh noise, so disable those for a
+    /// well-defined estimate.
+    pub fn jacobian(&mut self, v: &OmegaVector, eps: f64) -> Result<Array2<f64>> {
+        if eps <= 0.0 {
+            return Err(OmegaError::ParameterError(
+                "Jacobian step eps must be positive".to_string()
+            ));
+        }
+
+        let dim = v.len();
+        let out_dim = self.apply_forward(v)?.len();
+        let mut jacobian = Array2::zeros((out_dim, dim));
+
+        for j in 0..dim {
+            let mut plus = v.clone();
+            let mut minus = v.clone();
+            plus[j] += eps;
+            minus[j] -= eps;
+
+            let column = (self.apply_forward(&plus)? - self.apply_forward(&minus)?) / (2.0 * eps);
+            jacobian.column_mut(j).assign(&column);
+        }
+
+        Ok(jacobian)
+    }
+
+    /// Analytic matrix of the composite map if every stage is linear
+    pub fn linear_matrix(&self, dim: usize) -> Option<Array2<f64>> {
+        let mut composite: Array2<f64> = Array2::eye(dim);
+        for stage in &self.stages {
+            let matrix = stage.linear_matrix(composite.nrows())?;
+            composite = matrix.dot(&composite);
+        }
+        Some(composite)
+    }
+}
+
+/// Map every `dim`-component chunk of `v` through `f`, which must keep the
+/// chunk length
+fn map_chunks<F>(v: &OmegaVector, dim: usize, mut f: F) -> Result<OmegaVector>
+where
+    F: FnMut(&OmegaVector) -> Result<OmegaVector>,
+{
+    if dim == 0 || !v.len().is_multiple_of(dim) {
+        return Err(OmegaError::ParameterError(format!(
+            "{} components do not split into chunks of {}",
+            v.len(),
+            dim
+        )));
+    }
+    let mut result = Array1::zeros(v.len());
+    for (chunk, mut out) in v.exact_chunks(dim).into_iter().zip(result.exact_chunks_mut(dim)) {
+        let mapped = f(&chunk.to_owned())?;
+        if mapped.len() != dim {
+            return Err(OmegaError::ParameterError(format!(
+                "Pipeline maps {} components to {}",
+                dim,
+                mapped.len()
+            )));
+        }
+        out.assign(&mapped);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr1;
+
+    #[test]
+    fn test_jacobian_matches_linear_matrix() {
+        let a = Array2::from_shape_fn((5, 5), |(i, j)| 0.1 * (i as f64 + 1.0) - 0.05 * j as f64);
+
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(Pfadinvarianz::default()).push(LinearStage::new(a));
+
+        let v = arr1(&[0.3, -0.2, 0.9, 0.1, -0.5]);
+        let estimated = pipeline.jacobian(&v, 1e-4).unwrap();
+        let analytic = pipeline.linear_matrix(5).unwrap();
+
+        for (e, a) in estimated.iter().zip(analytic.iter()) {
+            assert!((e - a).abs() < 1e-8, "estimated {} vs analytic {}", e, a);
+        }
+    }
+
+    #[test]
+    fn test_operator_enum_sequence() {
+        let mut operators = vec![
+            Operator::Weight(WeightTransfer::default()),
+            Operator::Pfad(Pfadinvarianz::full_group(5).unwrap()),
+            Operator::Sweep(Sweep::default()),
+            Operator::DoubleKick(DoubleKick::with_seed(0.0, 0.0, 1)),
+        ];
+        operators.push(Operator::Resonance(ResonanceOperator::new(1.0)));
+        let names: Vec<&str> = operators.iter().map(|op| op.name()).collect();
+        assert_eq!(names, vec!["WeightTransfer", "Pfadinvarianz", "Sweep", "DoubleKick", "Resonance"]);
+
+        let v = arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mut expected = v.clone();
+        for op in operators.iter_mut().take(2) {
+            expected = op.apply_vector(&expected).unwrap();
+        }
+        // After the full-group projection the vector is constant
+        assert!(expected.iter().all(|&x| (x - expected[0]).abs() < 1e-12));
+
+        // A constant vector has no non-DC content, so resonance rejects it
+        let output = operators.iter_mut().skip(2).fold(expected, |acc, op| op.apply_vector(&acc).unwrap());
+        assert_eq!(output, Array1::<f64>::zeros(5));
+    }
+
+    #[test]
+    fn test_composite_lipschitz_and_stage_order() {
+        let v = arr1(&[0.9, 0.1, 0.6, 0.3, 0.8]);
+
+        let mut sweep_first = OmegaPipeline::new();
+        sweep_first.push(Sweep::default()).push(Pfadinvarianz::default());
+        assert_eq!(sweep_first.composite_lipschitz(), 1.0);
+
+        let scale = LinearStage::new(Array2::eye(5) * 2.0);
+        assert!((scale.lipschitz_constant() - 2.0).abs() < 1e-9);
+
+        // Sweep and the projection commute (the projection keeps the mean the
+        // gate reads), but the weight transfer's Haar bands do not commute
+        // with a projection mixing coordinates across a Haar pair
+        let pair = || Pfadinvarianz::on_subset(5, &[1, 2]).unwrap();
+        let mut transfer_first = OmegaPipeline::new();
+        transfer_first.push(WeightTransfer::default()).push(pair());
+        let mut pfad_first = OmegaPipeline::new();
+        pfad_first.push(pair()).push(WeightTransfer::default());
+        assert_eq!(pfad_first.composite_lipschitz(), 1.0);
+        let (a, b) = (transfer_first.apply_forward(&v).unwrap(), pfad_first.apply_forward(&v).unwrap());
+        assert!(a.iter().zip(b.iter()).any(|(x, y)| (x - y).abs() > 1e-3));
+    }
+
+    #[test]
+    fn test_apply_inverse_runs_stages_backwards() {
+        let v = arr1(&[1.2, 0.8, 1.5, 0.9, 1.1]);
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(Pfadinvarianz::full_group(5).unwrap()).push(Sweep::default());
+
+        let projected = Pfadinvarianz::full_group(5).unwrap().apply(&v);
+        let output = pipeline.apply_forward(&v).unwrap();
+        // Invert at the tick the forward pass used
+        let mut inverse = OmegaPipeline::new();
+        inverse.push(Pfadinvarianz::full_group(5).unwrap()).push(Sweep::default());
+        let recovered = inverse.apply_inverse(&output).unwrap();
+        for (r, p) in recovered.iter().zip(projected.iter()) {
+            assert!((r - p).abs() < 1e-9);
+        }
+
+        let mut kicked = OmegaPipeline::new();
+        kicked.push(DoubleKick::with_seed(0.1, 0.1, 1));
+        assert!(kicked.apply_inverse(&v).is_err());
+    }
+
+    #[test]
+    fn test_nonlinear_pipeline_has_no_linear_matrix() {
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(Sweep::default()).push(Pfadinvarianz::default());
+
+        assert!(pipeline.linear_matrix(5).is_none());
+        assert_eq!(pipeline.stage_names(), vec!["Sweep", "Pfadinvarianz"]);
+
+        let jacobian = pipeline.jacobian(&arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]), 1e-5).unwrap();
+        assert_eq!(jacobian.dim(), (5, 5));
+        assert!(pipeline.jacobian(&arr1(&[1.0]), 0.0).is_err());
+    }
+
+    #[test]
+    fn test_linear_stage_dimensions_and_inverse() {
+        let a = Array2::from_shape_fn((5, 5), |(i, j)| if i == j { 2.0 } else { 0.1 * (i + 2 * j) as f64 - 0.4 });
+        let mut stage = LinearStage::new(a);
+        assert_eq!(stage.output_dim(5).unwrap(), 5);
+        assert!(stage.output_dim(4).is_err());
+        assert!(stage.forward(&arr1(&[1.0, 2.0])).is_err());
+
+        let v = arr1(&[0.3, -1.2, 0.7, 2.5, -0.4]);
+        let output = stage.forward(&v).unwrap();
+        let recovered = stage.inverse(&output).unwrap();
+        for (r, x) in recovered.iter().zip(v.iter()) {
+            assert!((r - x).abs() < 1e-12);
+        }
+        assert!(LinearStage::new(Array2::zeros((5, 5))).inverse(&v).is_err());
+
+        // A 5 -> 3 projection cannot be followed by a 5-component stage
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(LinearStage::new(Array2::ones((3, 5)))).push(LinearStage::new(Array2::eye(5)));
+        assert!(pipeline.output_dim(5).is_err());
+        assert!(pipeline.apply_forward(&v).is_err());
+
+        // Chunk by chunk, forward then inverse
+        let mut pipeline = OmegaPipeline::new();
+        pipeline.push(stage).push(LinearStage::new(Array2::eye(5) * 0.5));
+        let long = Array1::from_shape_fn(15, |i| (i as f64 * 0.7).sin());
+        let output = pipeline.forward_chunks(&long, 5).unwrap();
+        let recovered = pipeline.inverse_chunks(&output, 5).unwrap();
+        for (r, x) in recovered.iter().zip(long.iter()) {
+            assert!((r - x).abs() < 1e-12);
+        }
+        assert!(pipeline.forward_chunks(&long, 4).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Replay protection for received frames
+//!
+//! A `ReplayGuard` remembers the masked payloads of recently accepted frames,
+//! keyed by a SHA-256 digest together with the frequency bin whose key
+//! authenticated them. Because masking keys are derived from the frequency
+//! and epoch, a frame is bound to the frequency it was sent on; the guard
+//! uses this to tell three attacks apart:
+//!
+//! - an exact duplicate presented again at its original frequency,
+//! - a recorded frame presented at a different frequency,
+//! - a frame from an epoch older than the policy accepts.
+
+use crate::types::*;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+
+/// How a frame was identified as a replay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayKind {
+    /// The same frame was already accepted at this frequency
+    Duplicate,
+    /// The frame was accepted before under a different frequency's key
+    FrequencyShifted,
+    /// The frame's epoch is older than the acceptance window
+    StaleEpoch,
+}
+
+/// Replay protection settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayPolicy {
+    /// Number of accepted frames remembered for duplicate detection
+    pub window: usize,
+    /// Past epochs still accepted (0 = current epoch only)
+    pub max_epoch_lag: u64,
+}
+
+impl Default for ReplayPolicy {
+    fn default() -> Self {
+        Self {
+            window: 1024,
+            max_epoch_lag: 1,
+        }
+    }
+}
+
+/// Digest identifying a masked payload
+pub type FrameDigest = [u8; 32];
+
+/// Sliding record of accepted frames
+#[derive(Clone, Debug, Default)]
+pub struct ReplayGuard {
+    policy: ReplayPolicy,
+    seen: HashMap<FrameDigest, i64>,
+    order: VecDeque<FrameDigest>,
+}
+
+impl ReplayGuard {
+    pub fn new(policy: ReplayPolicy) -> Self {
+        Self {
+            policy,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn policy(&self) -> &ReplayPolicy {
+        &self.policy
+    }
+
+    /// Digest of a masked payload
+    pub fn digest(masked: &[u8]) -> FrameDigest {
+        Sha256::digest(masked).into()
+    }
+
+    /// Reject frames sent under an epoch older than the acceptance window
+    pub fn check_epoch(&self, frame_epoch: u64, current_epoch: u64) -> Option<ReplayKind> {
+        if frame_epoch.saturating_add(self.policy.max_epoch_lag) < current_epoch {
+            Some(ReplayKind::StaleEpoch)
+        } else {
+            None
+        }
+    }
+
+    /// Check a payload presented at the given candidate frequencies against
+    /// the frames accepted so far
+    pub fn check(&self, digest: &FrameDigest, frequencies: &[f64]) -> Option<ReplayKind> {
+        let &bin = self.seen.get(digest)?;
+        if frequencies.iter().any(|&omega| quantize_frequency(omega) == bin) {
+            Some(ReplayKind::Duplicate)
+        } else {
+            Some(ReplayKind::FrequencyShifted)
+        }
+    }
+
+    /// Remember a payload accepted under the key for `omega`, forgetting the
+    /// oldest entry once the window is full
+    pub fn record(&mut self, digest: FrameDigest, omega: f64) {
+        if self.policy.window == 0 {
+            return;
+        }
+        if self.seen.insert(digest, quantize_frequency(omega)).is_none() {
+            self.order.push_back(digest);
+        }
+        while self.order.len() > self.policy.window {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+
+    /// Number of frames currently remembered
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_distinguishes_replay_kinds() {
+        let mut guard = ReplayGuard::new(ReplayPolicy::default());
+        let digest = ReplayGuard::digest(b"masked payload");
+
+        assert_eq!(guard.check(&digest, &[1.0]), None);
+        guard.record(digest, 1.0);
+        assert_eq!(guard.check(&digest, &[1.0]), Some(ReplayKind::Duplicate));
+        assert_eq!(guard.check(&digest, &[2.0]), Some(ReplayKind::FrequencyShifted));
+
+        assert_eq!(guard.check_epoch(4, 5), None);
+        assert_eq!(guard.check_epoch(3, 5), Some(ReplayKind::StaleEpoch));
+    }
+
+    #[test]
+    fn test_window_forgets_oldest() {
+        let mut guard = ReplayGuard::new(ReplayPolicy { window: 2, ..Default::default() });
+        let digests: Vec<FrameDigest> = (0..3u8).map(|i| ReplayGuard::digest(&[i])).collect();
+        for digest in &digests {
+            guard.record(*digest, 1.0);
+        }
+
+        assert_eq!(guard.len(), 2);
+        assert_eq!(guard.check(&digests[0], &[1.0]), None);
+        assert_eq!(guard.check(&digests[2], &[1.0]), Some(ReplayKind::Duplicate));
+    }
+}
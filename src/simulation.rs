@@ -0,0 +1,209 @@
+//! Deterministic multi-node simulation
+//!
+//! Every source of randomness in a run (per-node DoubleKick seeds, frequency
+//! assignment and traffic pattern) is derived from a single master seed via a
+//! counter-mode KDF, so a whole network can be reproduced from one `u64`.
+//! Masking nonces are the exception: they always come from the OS, so the
+//! same seed reproduces every frame header but the nonce, while the masked
+//! payloads differ between runs.
+
+use crate::types::*;
+use crate::node::OmegaNode;
+use crate::frame::Frame;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use sha2::{Digest, Sha256};
+
+/// Lowest frequency handed out to simulated nodes
+const FREQ_MIN: f64 = 0.5;
+/// Highest frequency handed out to simulated nodes
+const FREQ_MAX: f64 = 3.0;
+
+/// A frame observed on the simulated medium
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameRecord {
+    /// Step in which the frame was broadcast
+    pub step: u64,
+    /// Index of the sending node
+    pub sender: usize,
+    /// Frequency the frame was addressed to
+    pub target_freq: f64,
+    /// Transmitted frame
+    pub frame: Frame,
+}
+
+/// Reproducible network of OMEGA nodes driven from one master seed
+pub struct Simulation {
+    master_seed: u64,
+    nodes: Vec<OmegaNode>,
+    traffic: StdRng,
+    step: u64,
+    frame_log: Vec<FrameRecord>,
+}
+
+impl Simulation {
+    /// Build `node_count` nodes whose seeds and frequencies all derive from `master_seed`
+    pub fn from_seed(master_seed: u64, node_count: usize) -> Result<Self> {
+        if node_count == 0 {
+            return Err(OmegaError::ParameterError(
+                "Simulation requires at least one node".to_string()
+            ));
+        }
+
+        // Evenly spaced frequency slots, assigned to nodes in a seeded order
+        let mut frequencies: Vec<f64> = (0..node_count)
+            .map(|k| FREQ_MIN + (FREQ_MAX - FREQ_MIN) * (k as f64 + 0.5) / node_count as f64)
+            .collect();
+        let mut assign_rng = StdRng::seed_from_u64(Self::derive_seed(master_seed, "frequency", 0));
+        frequencies.shuffle(&mut assign_rng);
+
+        let mut nodes = Vec::with_capacity(node_count);
+        for (i, &omega) in frequencies.iter().enumerate() {
+            let config = NodeConfig {
+                omega,
+                ..Default::default()
+            };
+            let seed = Self::derive_seed(master_seed, "doublekick", i as u64);
+            nodes.push(OmegaNode::with_seed(config, seed)?);
+        }
+
+        Ok(Self {
+            master_seed,
+            nodes,
+            traffic: StdRng::seed_from_u64(Self::derive_seed(master_seed, "traffic", 0)),
+            step: 0,
+            frame_log: Vec::new(),
+        })
+    }
+
+    /// Counter-mode KDF: SHA-256(label || master_seed || counter), truncated to 64 bits
+    pub fn derive_seed(master_seed: u64, label: &str, counter: u64) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(b"omega-sim");
+        hasher.update(label.as_bytes());
+        hasher.update(master_seed.to_le_bytes());
+        hasher.update(counter.to_le_bytes());
+        let hash = hasher.finalize();
+
+        u64::from_le_bytes([
+            hash[0], hash[1], hash[2], hash[3],
+            hash[4], hash[5], hash[6], hash[7],
+        ])
+    }
+
+    /// Run one round: every node sends a message to a seeded peer's frequency,
+    /// and the resulting frame is broadcast to all other nodes
+    pub async fn step(&mut self) -> Result<()> {
+        let node_count = self.nodes.len();
+
+        for sender in 0..node_count {
+            let target = if node_count > 1 {
+                let offset = self.traffic.gen_range(1..node_count);
+                (sender + offset) % node_count
+            } else {
+                sender
+            };
+            let target_freq = self.nodes[target].get_frequency();
+            let message = format!("step {} from node {}", self.step, sender);
+
+            self.nodes[sender].send_message(message.as_bytes(), target_freq).await?;
+            let frame = match self.nodes[sender].take_frame() {
+                Some(frame) => frame,
+                None => continue,
+            };
+
+            for (i, node) in self.nodes.iter_mut().enumerate() {
+                if i != sender {
+                    node.push_frame(frame.clone());
+                    // Frames garbled past their length prefix are counted in
+                    // the receiver's decode_errors; the run carries on
+                    match node.receive_message().await {
+                        Ok(_) | Err(OmegaError::VectorizationError(_)) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+
+            self.frame_log.push(FrameRecord {
+                step: self.step,
+                sender,
+                target_freq,
+                frame,
+            });
+        }
+
+        self.step += 1;
+        Ok(())
+    }
+
+    /// Run `steps` rounds
+    pub async fn run(&mut self, steps: usize) -> Result<()> {
+        for _ in 0..steps {
+            self.step().await?;
+        }
+        Ok(())
+    }
+
+    /// Master seed this simulation was derived from
+    pub fn master_seed(&self) -> u64 {
+        self.master_seed
+    }
+
+    /// Simulated nodes
+    pub fn nodes(&self) -> &[OmegaNode] {
+        &self.nodes
+    }
+
+    /// Every frame broadcast so far, in transmission order
+    pub fn frame_log(&self) -> &[FrameRecord] {
+        &self.frame_log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::FrameHeader;
+
+    /// The reproducible part of a frame log: who sent what header where
+    fn traffic(sim: &Simulation) -> Vec<(u64, usize, f64, FrameHeader)> {
+        sim.frame_log()
+            .iter()
+            .map(|r| (r.step, r.sender, r.target_freq, FrameHeader { nonce: [0; 12], ..r.frame.header }))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_same_seed_reproduces_traffic() {
+        let mut sim1 = Simulation::from_seed(42, 4).unwrap();
+        let mut sim2 = Simulation::from_seed(42, 4).unwrap();
+
+        sim1.run(5).await.unwrap();
+        sim2.run(5).await.unwrap();
+
+        assert_eq!(sim1.frame_log().len(), 20);
+        assert_eq!(traffic(&sim1), traffic(&sim2));
+    }
+
+    #[tokio::test]
+    async fn test_different_seed_diverges() {
+        let mut sim1 = Simulation::from_seed(1, 3).unwrap();
+        let mut sim2 = Simulation::from_seed(2, 3).unwrap();
+
+        sim1.run(2).await.unwrap();
+        sim2.run(2).await.unwrap();
+
+        assert_ne!(traffic(&sim1), traffic(&sim2));
+    }
+
+    #[test]
+    fn test_frequency_assignment_is_deterministic() {
+        let sim1 = Simulation::from_seed(7, 5).unwrap();
+        let sim2 = Simulation::from_seed(7, 5).unwrap();
+
+        let f1: Vec<f64> = sim1.nodes().iter().map(|n| n.get_frequency()).collect();
+        let f2: Vec<f64> = sim2.nodes().iter().map(|n| n.get_frequency()).collect();
+        assert_eq!(f1, f2);
+    }
+}
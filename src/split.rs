@@ -0,0 +1,268 @@
+//! Sink and stream halves of a node
+//!
+//! `OmegaNode::into_split` moves the node behind a shared lock and returns
+//! an `OmegaSink`, which masks and sends every message to one target, and an
+//! `OmegaStream`, which receives and decodes. The halves can be moved to
+//! different tasks and put back together with `OmegaSink::reunite`.
+//!
+//! `OmegaSink` implements `futures::Sink<Vec<u8>>` and `OmegaStream`
+//! implements `futures::Stream`, so the `SinkExt` and `StreamExt`
+//! combinators (`send`, `send_all`, `map`, `forward`, ...) apply. While no
+//! frame is pending the stream waits for one from a connected node or the
+//! transport; it ends (yields `None`) once the sink half has been closed or
+//! dropped and every pending message has been received.
+
+use crate::types::*;
+use crate::hopping::SendTarget;
+use crate::node::OmegaNode;
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll, Waker};
+use tokio::sync::Mutex;
+
+/// Operation in flight on the shared node
+type Pending<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// State shared by the two halves
+struct Shared {
+    node: Mutex<OmegaNode>,
+    // Set once the sink half is closed or dropped
+    closed: AtomicBool,
+    // Stream waiting for more traffic, woken when the sink closes
+    waker: std::sync::Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Result of one receive attempt of the stream
+enum Next {
+    /// A message or an error of the medium
+    Item(Result<Vec<u8>>),
+    /// A frame arrived while looking; try again
+    Retry,
+    /// Nothing pending; the task is woken when a frame arrives
+    Wait,
+}
+
+/// Sending half of a split node
+pub struct OmegaSink {
+    shared: Arc<Shared>,
+    target: SendTarget,
+    sending: Option<Pending<Result<()>>>,
+}
+
+/// Receiving half of a split node
+pub struct OmegaStream {
+    shared: Arc<Shared>,
+    receiving: Option<Pending<Next>>,
+}
+
+/// Halves sharing `node`; see `OmegaNode::into_split`
+pub(crate) fn split(node: OmegaNode, target: SendTarget) -> (OmegaSink, OmegaStream) {
+    let shared = Arc::new(Shared {
+        node: Mutex::new(node),
+        closed: AtomicBool::new(false),
+        waker: std::sync::Mutex::new(None),
+    });
+    (
+        OmegaSink { shared: Arc::clone(&shared), target, sending: None },
+        OmegaStream { shared, receiving: None },
+    )
+}
+
+impl OmegaSink {
+    /// Put the node back together from its two halves
+    ///
+    /// Fails with a `ParameterError` if they come from different nodes;
+    /// both halves are dropped then. A send or receive still in flight is
+    /// cancelled, so flush the sink first.
+    pub fn reunite(self, stream: OmegaStream) -> Result<OmegaNode> {
+        if !Arc::ptr_eq(&self.shared, &stream.shared) {
+            return Err(OmegaError::ParameterError(
+                "sink and stream belong to different nodes".to_string()
+            ));
+        }
+        let shared = Arc::clone(&self.shared);
+        drop(stream);
+        drop(self);
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => Ok(shared.node.into_inner()),
+            Err(_) => unreachable!("the halves hold the only references"),
+        }
+    }
+
+    /// Target every message is sent to
+    pub fn target(&self) -> SendTarget {
+        self.target
+    }
+}
+
+/// Each message is masked and sent on `start_send`, completing on the next
+/// `poll_ready` or `poll_flush`. The node stays locked while the send waits
+/// (a full bounded transport, an exhausted rate limit), so the stream half
+/// waits with it. Closing the sink ends the stream half.
+impl Sink<Vec<u8>> for OmegaSink {
+    type Error = OmegaError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_flush(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: Vec<u8>) -> Result<()> {
+        let shared = Arc::clone(&self.shared);
+        let target = self.target;
+        self.sending = Some(Box::pin(async move {
+            shared.node.lock().await.send_message(&message, target).await
+        }));
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let Some(sending) = self.sending.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let result = ready!(sending.as_mut().poll(cx));
+        self.sending = None;
+        Poll::Ready(result)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        self.shared.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for OmegaSink {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Yields the next received message, waiting while none is pending, and
+/// ends once the sink half is closed and nothing is pending
+///
+/// Frames that yield no message are skipped (see `OmegaNode::receive_all`);
+/// errors of the medium are returned as items. The node is unlocked while
+/// the stream waits, so the sink half can keep sending.
+impl Stream for OmegaStream {
+    type Item = Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            let receiving = this.receiving.get_or_insert_with(|| {
+                let shared = Arc::clone(&this.shared);
+                Box::pin(async move {
+                    let mut node = shared.node.lock().await;
+                    match node.next_pending_message().await.transpose() {
+                        Some(item) => Next::Item(item),
+                        // Registers the waker with the links and the transport
+                        None => match std::future::poll_fn(|cx| Poll::Ready(node.poll_arrival(cx))).await {
+                            Poll::Ready(()) => Next::Retry,
+                            Poll::Pending => Next::Wait,
+                        },
+                    }
+                })
+            });
+            let next = ready!(receiving.as_mut().poll(cx));
+            this.receiving = None;
+            match next {
+                Next::Item(item) => return Poll::Ready(Some(item)),
+                Next::Retry => continue,
+                Next::Wait => {}
+            }
+
+            // Checked again after registering, so a close in between is seen
+            if this.shared.closed.load(Ordering::Acquire) {
+                return Poll::Ready(None);
+            }
+            *this.shared.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(cx.waker().clone());
+            if this.shared.closed.load(Ordering::Acquire) {
+                return Poll::Ready(None);
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::InMemoryTransport;
+    use futures_util::{stream, SinkExt, StreamExt};
+
+    #[tokio::test]
+    async fn test_sink_to_stream() {
+        let mut a = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut b = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut c = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        a.connect(&mut b);
+        b.connect(&mut c);
+
+        let messages: Vec<Vec<u8>> = (0..5).map(|i| format!("message {}", i).into_bytes()).collect();
+        let (mut sink_a, _stream_a) = a.into_split(1.0);
+        let (mut sink_b, mut stream_b) = b.into_split(1.0);
+        let (mut sink_c, mut stream_c) = c.into_split(1.0);
+
+        // a → b, then b relays five messages on to c from another task and
+        // closes its sink, which ends its stream
+        sink_a.send_all(&mut stream::iter(messages.clone()).map(Ok)).await.unwrap();
+        let relay = tokio::spawn(async move {
+            stream_b.by_ref().take(5).forward(&mut sink_b).await.unwrap();
+            assert!(stream_b.next().await.is_none());
+            (sink_b, stream_b)
+        });
+
+        // c waits for the relayed messages as they arrive
+        let received: Vec<Vec<u8>> = stream_c.by_ref().take(5).map(Result::unwrap).collect().await;
+        assert_eq!(received, messages);
+        let (_sink_b, stream_b) = relay.await.unwrap();
+
+        sink_c.close().await.unwrap();
+        assert!(stream_c.next().await.is_none());
+        let mut c = sink_c.reunite(stream_c).unwrap();
+        assert_eq!(c.stats().received, 5);
+        assert!(c.receive_message().await.unwrap().is_none());
+
+        // Halves of different nodes do not fit together
+        assert!(sink_a.reunite(stream_b).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_waits_for_transport() {
+        let bus = InMemoryTransport::new();
+        let mut sender = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        let mut receiver = OmegaNode::new(NodeConfig::lossless()).unwrap();
+        sender.set_transport(Box::new(bus.clone()));
+        receiver.set_transport(Box::new(bus));
+        let (sink, mut stream) = receiver.into_split(1.0);
+
+        // Nothing is pending, so the stream waits instead of ending
+        let waiting = tokio::spawn(async move { (stream.next().await, stream) });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        sender.send_message(b"late", 1.0).await.unwrap();
+        let (message, mut stream) = waiting.await.unwrap();
+        assert_eq!(message.unwrap().unwrap(), b"late".to_vec());
+
+        // Dropping the sink ends a waiting stream
+        let ending = tokio::spawn(async move { stream.next().await.is_none() });
+        tokio::task::yield_now().await;
+        assert!(!ending.is_finished());
+        drop(sink);
+        assert!(ending.await.unwrap());
+    }
+}
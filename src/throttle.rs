@@ -0,0 +1,94 @@
+//! Token-bucket rate limiting for outgoing frames
+//!
+//! Independent of the sweep operator, which gates reception.
+
+use crate::types::*;
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+// std's monotonic clock panics on wasm32-unknown-unknown
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+/// Token bucket refilled continuously at `rate` tokens per second
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Bucket allowing `rate` operations per second with bursts of up to `capacity`
+    pub fn new(rate: f64, capacity: f64) -> Result<Self> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(OmegaError::ParameterError(
+                "Rate limit must be positive and finite".to_string()
+            ));
+        }
+        if !(capacity.is_finite() && capacity >= 1.0) {
+            return Err(OmegaError::ParameterError(
+                "Bucket capacity must be at least one token".to_string()
+            ));
+        }
+
+        Ok(Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        })
+    }
+
+    /// Operations per second
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a token if one is available
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Time until the next token becomes available (zero if one is available now)
+    pub fn time_until_available(&mut self) -> Duration {
+        self.refill();
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_limits_bursts() {
+        let mut bucket = TokenBucket::new(1.0, 3.0).unwrap();
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+        assert!(bucket.time_until_available() > Duration::from_millis(900));
+
+        assert!(TokenBucket::new(0.0, 1.0).is_err());
+        assert!(TokenBucket::new(1.0, 0.5).is_err());
+    }
+}
@@ -0,0 +1,323 @@
+//! Byte transports carrying encoded frames between nodes
+//!
+//! Without a transport a node keeps its frames in a local in-process buffer
+//! (the simulated medium used throughout the tests). A `Transport` replaces
+//! that buffer with a real medium: `InMemoryTransport` is a bus shared by
+//! nodes in one process, `TcpTransport` exchanges frames with peers over TCP
+//! (with the `native` feature only). Frames travel as bytes; see
+//! `Frame::to_bytes`.
+
+use crate::types::*;
+use crate::frame::Frame;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use tokio::sync::{mpsc, Semaphore};
+#[cfg(feature = "native")]
+use std::net::SocketAddr;
+#[cfg(feature = "native")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "native")]
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(feature = "native")]
+use tokio::task::JoinHandle;
+
+/// Largest frame accepted from a TCP peer
+#[cfg(feature = "native")]
+pub const MAX_TCP_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Medium carrying encoded frames
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Broadcast one encoded frame
+    ///
+    /// A transport with a bounded outbound queue waits here until there is
+    /// room, so callers are slowed to the pace of the receivers instead of
+    /// frames being dropped.
+    async fn send(&self, bytes: &[u8]) -> Result<()>;
+
+    /// Next received frame, or `None` if nothing is pending (never blocks)
+    async fn recv(&self) -> Option<Vec<u8>>;
+
+    /// Like `recv`, but when nothing is pending `cx` is woken once a frame
+    /// may have arrived
+    ///
+    /// The default polls `recv` once and asks to be woken again right away,
+    /// so a waiting reader keeps polling; transports that know when frames
+    /// arrive override it.
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        match self.recv().as_mut().poll(cx) {
+            Poll::Ready(Some(bytes)) => Poll::Ready(bytes),
+            _ => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Identifier of a peer in one node's registry
+pub type PeerId = u64;
+
+/// Where a node delivers its broadcasts for one registered peer
+#[derive(Clone)]
+pub enum PeerHandle {
+    /// In-process inbox of another node (see `OmegaNode::link`)
+    Channel(mpsc::UnboundedSender<Frame>),
+    /// Byte transport reaching the peer
+    Transport(Arc<dyn Transport>),
+}
+
+impl PeerHandle {
+    /// Hand one frame to the peer
+    pub async fn deliver(&self, frame: &Frame) -> Result<()> {
+        match self {
+            PeerHandle::Channel(tx) => tx
+                .send(frame.clone())
+                .map_err(|_| OmegaError::NetworkError("peer channel closed".to_string())),
+            PeerHandle::Transport(transport) => transport.send(&frame.to_bytes()).await,
+        }
+    }
+}
+
+impl std::fmt::Debug for PeerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeerHandle::Channel(tx) => f.debug_tuple("Channel").field(&tx.is_closed()).finish(),
+            PeerHandle::Transport(_) => f.write_str("Transport"),
+        }
+    }
+}
+
+/// In-process broadcast bus; clones share the same medium
+///
+/// Every frame sent by any clone is queued once and received by whichever
+/// clone polls first, like the simulated buffer it stands in for. A bus
+/// created with `bounded` holds at most `capacity` frames; `send` then waits
+/// for a receiver to take one.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryTransport {
+    queue: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    // Free queue slots; None = unbounded
+    slots: Option<Arc<Semaphore>>,
+    // Readers waiting in `poll_recv` for the next frame
+    wakers: Arc<Mutex<Vec<Waker>>>,
+}
+
+impl InMemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bus holding at most `capacity` frames at a time
+    pub fn bounded(capacity: usize) -> Self {
+        Self {
+            slots: Some(Arc::new(Semaphore::new(capacity))),
+            ..Self::default()
+        }
+    }
+
+    /// Frames waiting to be received
+    pub fn pending(&self) -> usize {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+#[async_trait]
+impl Transport for InMemoryTransport {
+    async fn send(&self, bytes: &[u8]) -> Result<()> {
+        if let Some(slots) = &self.slots {
+            // The slot is handed back by the `recv` that takes this frame
+            slots
+                .acquire()
+                .await
+                .map_err(|_| OmegaError::NetworkError("transport closed".to_string()))?
+                .forget();
+        }
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).push_back(bytes.to_vec());
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap_or_else(|e| e.into_inner()));
+        wakers.into_iter().for_each(Waker::wake);
+        Ok(())
+    }
+
+    async fn recv(&self) -> Option<Vec<u8>> {
+        let frame = self.queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front()?;
+        if let Some(slots) = &self.slots {
+            slots.add_permits(1);
+        }
+        Some(frame)
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        // The queue stays locked while registering, so a concurrent `send`
+        // either is seen here or wakes the waker afterwards
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(frame) = queue.pop_front() else {
+            let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        };
+        if let Some(slots) = &self.slots {
+            slots.add_permits(1);
+        }
+        Poll::Ready(frame)
+    }
+}
+
+/// Frames exchanged with a fixed set of peers over TCP
+///
+/// Listens on a local address; every received frame is queued for `recv`.
+/// `send` connects to each configured peer and writes the frame prefixed by
+/// its length as a little-endian `u32`. Must be created inside a tokio runtime.
+#[cfg(feature = "native")]
+#[derive(Debug)]
+pub struct TcpTransport {
+    local_addr: SocketAddr,
+    peers: Vec<SocketAddr>,
+    inbox: Mutex<mpsc::UnboundedReceiver<Vec<u8>>>,
+    accept_task: JoinHandle<()>,
+}
+
+#[cfg(feature = "native")]
+impl TcpTransport {
+    /// Listen on `addr` and send to `peers`
+    pub async fn bind<A: ToSocketAddrs>(addr: A, peers: Vec<SocketAddr>) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let accept_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(read_frames(stream, tx.clone()));
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            peers,
+            inbox: Mutex::new(rx),
+            accept_task,
+        })
+    }
+
+    /// Address this transport listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Also send to `peer`
+    pub fn add_peer(&mut self, peer: SocketAddr) {
+        if !self.peers.contains(&peer) {
+            self.peers.push(peer);
+        }
+    }
+
+    /// Peers frames are sent to
+    pub fn peers(&self) -> &[SocketAddr] {
+        &self.peers
+    }
+}
+
+#[cfg(feature = "native")]
+impl Drop for TcpTransport {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+/// Queue length-prefixed frames from one connection until it closes or
+/// sends an oversized frame
+#[cfg(feature = "native")]
+async fn read_frames(mut stream: TcpStream, tx: mpsc::UnboundedSender<Vec<u8>>) {
+    loop {
+        let mut len = [0u8; 4];
+        if stream.read_exact(&mut len).await.is_err() {
+            return;
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_TCP_FRAME_LEN {
+            return;
+        }
+        let mut frame = vec![0u8; len];
+        if stream.read_exact(&mut frame).await.is_err() || tx.send(frame).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn send(&self, bytes: &[u8]) -> Result<()> {
+        let len = u32::try_from(bytes.len())
+            .ok()
+            .filter(|&len| len as usize <= MAX_TCP_FRAME_LEN)
+            .ok_or_else(|| OmegaError::NetworkError("frame too large for TCP transport".to_string()))?;
+
+        for peer in &self.peers {
+            let mut stream = TcpStream::connect(peer).await.map_err(|e| {
+                OmegaError::NetworkError(format!("connect to {}: {}", peer, e))
+            })?;
+            stream.write_all(&len.to_le_bytes()).await?;
+            stream.write_all(bytes).await?;
+            stream.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    async fn recv(&self) -> Option<Vec<u8>> {
+        self.inbox.lock().unwrap_or_else(|e| e.into_inner()).try_recv().ok()
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        match self.inbox.lock().unwrap_or_else(|e| e.into_inner()).poll_recv(cx) {
+            Poll::Ready(Some(bytes)) => Poll::Ready(bytes),
+            // The listener is gone; no frame will arrive
+            Poll::Ready(None) => Poll::Pending,
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_clones_share_medium() {
+        let a = InMemoryTransport::new();
+        let b = a.clone();
+
+        a.send(b"first").await.unwrap();
+        a.send(b"second").await.unwrap();
+        assert_eq!(b.pending(), 2);
+        assert_eq!(b.recv().await, Some(b"first".to_vec()));
+        assert_eq!(a.recv().await, Some(b"second".to_vec()));
+        assert_eq!(b.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_send_waits_for_room() {
+        let bus = InMemoryTransport::bounded(1);
+        bus.send(b"first").await.unwrap();
+
+        let mut blocked = Box::pin(bus.send(b"second"));
+        assert!(poll_once(&mut blocked).await.is_none());
+
+        assert_eq!(bus.recv().await, Some(b"first".to_vec()));
+        blocked.await.unwrap();
+        assert_eq!(bus.pending(), 1);
+    }
+
+    /// Poll a future once, returning its output if it completed
+    async fn poll_once<F: std::future::Future + Unpin>(f: &mut F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            out = f => Some(out),
+            _ = std::future::ready(()) => None,
+        }
+    }
+}
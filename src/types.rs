@@ -1,45 +1,240 @@
-/// Core type definitions for the OMEGA Protocol
+//! Core type definitions for the OMEGA Protocol
 use ndarray::Array1;
 use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
-/// 5-dimensional vector space for OMEGA operations
-pub type OmegaVector = Array1<f64>;
+pub use num_traits::Float;
 
-/// Masking parameters for information-theoretic encryption
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Vector of the OMEGA operations: consecutive chunks of `NodeConfig::dimension`
+/// components (5 by default)
+pub type OmegaVector = OmegaVectorF64;
+
+/// Double-precision vector, the default component type
+pub type OmegaVectorF64 = Array1<f64>;
+
+/// Single-precision vector, half the memory of `OmegaVectorF64`
+///
+/// Sweep, Pfadinvarianz, WeightTransfer and DoubleKick transform vectors of
+/// either width. Resonance, masking and the node work on `f64` only.
+pub type OmegaVectorF32 = Array1<f32>;
+
+/// `x` as a vector component of type `F`, saturating to ±∞ where `F` has
+/// no finite value for it
+pub fn component<F: Float>(x: f64) -> F {
+    F::from(x).unwrap_or_else(|| if x > 0.0 { F::infinity() } else { F::neg_infinity() })
+}
+
+/// Default number of masking diffusion rounds
+pub const DEFAULT_MASKING_ROUNDS: u8 = 8;
+
+/// Frequency grid used for key derivation; frequencies in the same bin share keys
+pub const FREQUENCY_QUANTUM: f64 = 1e-3;
+
+/// Quantize a frequency to its key-derivation bin
+pub fn quantize_frequency(omega: f64) -> i64 {
+    (omega / FREQUENCY_QUANTUM).round() as i64
+}
+
+/// 256-bit masking key σ
+///
+/// Dereferences to the key bytes. Wiped on drop, and its `Debug` output
+/// never shows the bytes, so a logged `MaskingParams` does not leak it.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+#[serde(transparent)]
+pub struct Sigma([u8; 32]);
+
+impl Sigma {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<[u8; 32]> for Sigma {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl std::ops::Deref for Sigma {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Sigma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Sigma(<redacted>)")
+    }
+}
+
+/// Masking parameters (σ keys the permutation and keystream, θ selects the nonce)
+///
+/// σ and θ are key material: they are wiped along with the other fields
+/// when the parameters are dropped (or on `Zeroize::zeroize`), and `Debug`
+/// redacts σ.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct MaskingParams {
     /// Phase rotation parameter [0, 2π)
     pub theta: f64,
     /// Permutation seed (256-bit)
-    pub sigma: [u8; 32],
+    pub sigma: Sigma,
+    /// Number of diffusion rounds (more rounds = stronger diffusion, slower)
+    pub rounds: u8,
+    /// Append and verify a 16-byte authentication tag
+    pub authenticated: bool,
+    /// Per-message counter completing the keystream nonce; must never repeat
+    /// for the same (σ, θ)
+    #[serde(default)]
+    pub counter: u32,
+    /// Random per-message nonce, sent in the clear; mixed into the keystream
+    /// key and bound into the authentication tag
+    #[serde(default)]
+    pub nonce: [u8; 12],
+    /// Cipher generating the keystream; bound into the authentication tag
+    #[serde(default)]
+    pub cipher: KeystreamCipher,
+}
+
+/// Cipher generating the masking keystream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeystreamCipher {
+    /// ChaCha20
+    #[default]
+    ChaCha20,
+    /// AES-256 in counter mode
+    Aes256Ctr,
+}
+
+impl zeroize::DefaultIsZeroes for KeystreamCipher {}
+
+/// HKDF info string for the σ key
+const SIGMA_INFO: &[u8] = b"omega-protocol/v1/masking/sigma";
+
+/// HKDF info string for the θ seed
+const THETA_INFO: &[u8] = b"omega-protocol/v1/masking/theta";
+
+/// HKDF-SHA256 (RFC 5869): extract a pseudorandom key from `salt` and
+/// `ikm`, then expand it under `info` to fill `out` (at most 255 * 32 bytes)
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], out: &mut [u8]) {
+    // An empty salt is a zero-length HMAC key, the same as a block of zeros
+    hkdf::Hkdf::<sha2::Sha256>::new(Some(salt), ikm)
+        .expand(info, out)
+        .expect("HKDF-SHA256 expands to at most 255 * 32 bytes");
 }
 
 impl MaskingParams {
     /// Derive ephemeral masking parameters from frequency and epoch
     pub fn ephemeral_from_frequency(omega: f64, epoch: u64) -> Self {
-        use sha2::{Digest, Sha256};
+        Self::ephemeral_from_bin(quantize_frequency(omega), epoch)
+    }
+
+    /// Derive ephemeral masking parameters from a quantized frequency bin and epoch
+    pub fn ephemeral_from_bin(freq_bin: i64, epoch: u64) -> Self {
+        Self::ephemeral_from_bin_with_salt(freq_bin, epoch, &[])
+    }
+
+    /// Derive ephemeral masking parameters under a deployment-specific
+    /// `salt`; deployments with different salts never share keys
+    ///
+    /// σ and the θ seed are expanded separately by HKDF-SHA256 from the
+    /// frequency bin and epoch, with distinct info strings, so they are
+    /// independent. An empty salt gives the default parameters.
+    pub fn ephemeral_from_bin_with_salt(freq_bin: i64, epoch: u64, salt: &[u8]) -> Self {
+        let mut ikm = [0u8; 16];
+        ikm[..8].copy_from_slice(&freq_bin.to_le_bytes());
+        ikm[8..].copy_from_slice(&epoch.to_le_bytes());
 
-        let mut hasher = Sha256::new();
-        hasher.update(omega.to_le_bytes());
-        hasher.update(epoch.to_le_bytes());
-        let hash = hasher.finalize();
+        let mut sigma = Sigma::default();
+        hkdf_sha256(salt, &ikm, SIGMA_INFO, &mut sigma.0);
+        let mut theta_seed = [0u8; 8];
+        hkdf_sha256(salt, &ikm, THETA_INFO, &mut theta_seed);
 
-        let mut sigma = [0u8; 32];
-        sigma.copy_from_slice(&hash);
+        let theta = (u64::from_le_bytes(theta_seed) as f64 / u64::MAX as f64) * 2.0 * std::f64::consts::PI;
+        theta_seed.zeroize();
 
-        // Derive theta from hash
-        let theta_bytes = u64::from_le_bytes([
-            hash[0], hash[1], hash[2], hash[3],
-            hash[4], hash[5], hash[6], hash[7],
-        ]);
-        let theta = (theta_bytes as f64 / u64::MAX as f64) * 2.0 * std::f64::consts::PI;
+        Self {
+            theta,
+            sigma,
+            rounds: DEFAULT_MASKING_ROUNDS,
+            authenticated: true,
+            counter: 0,
+            nonce: [0; 12],
+            cipher: KeystreamCipher::ChaCha20,
+        }
+    }
 
-        Self { theta, sigma }
+    /// Snap θ down to the nearest of `steps` discrete phases `k·2π/steps`,
+    /// for hardware that only supports discrete phases (0 = leave θ
+    /// continuous)
+    pub fn quantize_theta(&mut self, steps: u32) {
+        if steps == 0 {
+            return;
+        }
+        let step = 2.0 * std::f64::consts::PI / steps as f64;
+        let k = ((self.theta / step).floor() as u32).min(steps - 1);
+        self.theta = k as f64 * step;
+    }
+}
+
+/// Masking routine a frame was encrypted with, carried in its header as a
+/// one-byte code
+///
+/// `ALL` lists the modes this implementation supports, most preferred first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskingMode {
+    /// Permutation, ChaCha20 keystream and `DEFAULT_MASKING_ROUNDS` diffusion rounds
+    #[default]
+    Diffused,
+    /// Permutation and ChaCha20 keystream only; each byte is masked
+    /// independently, which is cheaper and can be streamed
+    Stream,
+    /// Permutation and AES-256-CTR keystream only; streamable like `Stream`
+    AesCtr,
+    /// Bare ChaCha20 keystream XOR, the toy cipher: two plaintexts masked
+    /// under one key and nonce leak their XOR. Not in `Capabilities::SUPPORTED`,
+    /// so it is never negotiated.
+    Xor,
+}
+
+impl MaskingMode {
+    pub const ALL: [MaskingMode; 4] = [MaskingMode::Diffused, MaskingMode::Stream, MaskingMode::AesCtr, MaskingMode::Xor];
+
+    /// Header code of the mode
+    pub fn code(&self) -> u8 {
+        match self {
+            MaskingMode::Diffused => 0,
+            MaskingMode::Stream => 1,
+            MaskingMode::AesCtr => 2,
+            MaskingMode::Xor => 3,
+        }
+    }
+
+    /// Mode for a header code; unknown codes are a `CodecError`
+    pub fn from_code(code: u8) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.code() == code)
+            .ok_or_else(|| OmegaError::CodecError(format!("unknown masking mode {}", code)))
+    }
+
+    /// Configure `params` for this mode
+    pub fn apply(&self, params: &mut MaskingParams) {
+        params.rounds = match self {
+            MaskingMode::Diffused => DEFAULT_MASKING_ROUNDS,
+            MaskingMode::Stream | MaskingMode::AesCtr | MaskingMode::Xor => 0,
+        };
+        params.cipher = match self {
+            MaskingMode::Diffused | MaskingMode::Stream | MaskingMode::Xor => KeystreamCipher::ChaCha20,
+            MaskingMode::AesCtr => KeystreamCipher::Aes256Ctr,
+        };
     }
 }
 
 /// Resonance parameters for spectral coupling
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ResonanceParams {
     /// Target frequency
     pub omega: f64,
@@ -56,15 +251,210 @@ impl Default for ResonanceParams {
     }
 }
 
+/// Time schedule of the sweep threshold
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SweepSchedule {
+    /// Raised cosine over each period
+    #[default]
+    Cosine,
+    /// Sawtooth ramp over each period
+    Linear,
+    /// Fixed base threshold
+    Constant,
+}
+
+impl std::str::FromStr for SweepSchedule {
+    type Err = OmegaError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cosine" => Ok(SweepSchedule::Cosine),
+            "linear" => Ok(SweepSchedule::Linear),
+            "constant" => Ok(SweepSchedule::Constant),
+            _ => Err(OmegaError::ParameterError(format!(
+                "Unknown sweep schedule '{}' (expected cosine, linear or constant)",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for SweepSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SweepSchedule::Cosine => "cosine",
+            SweepSchedule::Linear => "linear",
+            SweepSchedule::Constant => "constant",
+        })
+    }
+}
+
+impl SweepSchedule {
+    pub const ALL: [SweepSchedule; 3] = [SweepSchedule::Cosine, SweepSchedule::Linear, SweepSchedule::Constant];
+}
+
+/// What the sweep gate is computed from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SweepMode {
+    /// One gate from the vector mean, scaling every component alike
+    #[default]
+    Mean,
+    /// A separate gate for each component from its own value
+    PerElement,
+}
+
+/// Set of protocol features a node supports, advertised in its descriptor
+///
+/// Bits 0-7 are masking modes, 8-15 sweep schedules and 16-23 codec
+/// versions. Bits a peer sets that this implementation does not know are
+/// kept on decode and fall away on intersection.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    pub const MASKING_DIFFUSED: Capabilities = Capabilities(1 << 0);
+    pub const MASKING_STREAM: Capabilities = Capabilities(1 << 1);
+    pub const MASKING_AES_CTR: Capabilities = Capabilities(1 << 2);
+    /// The toy `MaskingMode::Xor`; never part of `SUPPORTED`
+    pub const MASKING_XOR: Capabilities = Capabilities(1 << 3);
+    pub const SCHEDULE_COSINE: Capabilities = Capabilities(1 << 8);
+    pub const SCHEDULE_LINEAR: Capabilities = Capabilities(1 << 9);
+    pub const SCHEDULE_CONSTANT: Capabilities = Capabilities(1 << 10);
+    /// Vector wire format version 1 (`utils::serialize_vector`)
+    pub const CODEC_VECTOR_V1: Capabilities = Capabilities(1 << 16);
+
+    /// Everything this implementation supports
+    pub const SUPPORTED: Capabilities = Capabilities(
+        Self::MASKING_DIFFUSED.0
+            | Self::MASKING_STREAM.0
+            | Self::MASKING_AES_CTR.0
+            | Self::SCHEDULE_COSINE.0
+            | Self::SCHEDULE_LINEAR.0
+            | Self::SCHEDULE_CONSTANT.0
+            | Self::CODEC_VECTOR_V1.0,
+    );
+
+    /// Assumed for descriptors that predate capability negotiation
+    pub const LEGACY: Capabilities = Capabilities(
+        Self::MASKING_DIFFUSED.0
+            | Self::SCHEDULE_COSINE.0
+            | Self::SCHEDULE_LINEAR.0
+            | Self::SCHEDULE_CONSTANT.0,
+    );
+
+    pub const fn empty() -> Self {
+        Capabilities(0)
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Capabilities(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every flag of `other` is set
+    pub const fn contains(&self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersection(&self, other: Capabilities) -> Self {
+        Capabilities(self.0 & other.0)
+    }
+
+    pub const fn union(&self, other: Capabilities) -> Self {
+        Capabilities(self.0 | other.0)
+    }
+
+    /// Flag for a masking mode
+    pub fn masking_mode(mode: MaskingMode) -> Self {
+        match mode {
+            MaskingMode::Diffused => Self::MASKING_DIFFUSED,
+            MaskingMode::Stream => Self::MASKING_STREAM,
+            MaskingMode::AesCtr => Self::MASKING_AES_CTR,
+            MaskingMode::Xor => Self::MASKING_XOR,
+        }
+    }
+
+    /// Flag for a sweep schedule
+    pub fn schedule(schedule: SweepSchedule) -> Self {
+        match schedule {
+            SweepSchedule::Cosine => Self::SCHEDULE_COSINE,
+            SweepSchedule::Linear => Self::SCHEDULE_LINEAR,
+            SweepSchedule::Constant => Self::SCHEDULE_CONSTANT,
+        }
+    }
+
+    /// Masking modes in the set, most preferred first
+    pub fn masking_modes(&self) -> Vec<MaskingMode> {
+        MaskingMode::ALL.into_iter().filter(|&m| self.contains(Self::masking_mode(m))).collect()
+    }
+
+    /// Sweep schedules in the set
+    pub fn schedules(&self) -> Vec<SweepSchedule> {
+        SweepSchedule::ALL.into_iter().filter(|&s| self.contains(Self::schedule(s))).collect()
+    }
+}
+
+impl std::ops::BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl std::ops::BitAnd for Capabilities {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        self.intersection(rhs)
+    }
+}
+
+impl std::fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Capabilities({:#x})", self.0)
+    }
+}
+
+/// Features two nodes have in common; errors if they share no masking
+/// mode, since then neither can read the other's frames
+pub fn negotiate_capabilities(a: Capabilities, b: Capabilities) -> Result<Capabilities> {
+    let common = a & b;
+    if common.masking_modes().is_empty() {
+        return Err(OmegaError::ParameterError(format!(
+            "no common masking mode ({:?} vs {:?})", a, b
+        )));
+    }
+    Ok(common)
+}
+
 /// Sweep parameters for adaptive threshold filtering
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SweepParams {
     /// Base threshold
     pub tau0: f64,
     /// Gate width parameter
     pub beta: f64,
-    /// Schedule type: "cosine" or "linear"
-    pub schedule: String,
+    /// Threshold schedule
+    pub schedule: SweepSchedule,
+    /// Ticks after startup during which the gate stays fully open
+    pub warmup_ticks: u64,
+    /// Ticks per threshold schedule cycle (must be positive)
+    pub period: f64,
+    /// Amplitude of the threshold swing above `tau0`
+    pub delta_tau: f64,
+    /// Arrivals per second above which the threshold rises (None = gate
+    /// on the mean alone)
+    pub max_rate: Option<f64>,
+    /// Gate on the vector mean or on each component
+    pub mode: SweepMode,
 }
 
 impl Default for SweepParams {
@@ -72,13 +462,18 @@ impl Default for SweepParams {
         Self {
             tau0: 0.5,
             beta: 0.1,
-            schedule: "cosine".to_string(),
+            schedule: SweepSchedule::Cosine,
+            warmup_ticks: 0,
+            period: 100.0,
+            delta_tau: 0.2,
+            max_rate: None,
+            mode: SweepMode::Mean,
         }
     }
 }
 
 /// Pfadinvarianz parameters for path-invariant projection
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct PfadinvarianzParams {
     /// Number of permutations in group
     pub permutation_count: usize,
@@ -100,8 +495,13 @@ pub enum ScaleLevel {
     Macro,
 }
 
+impl ScaleLevel {
+    /// All scale levels, finest first
+    pub const ALL: [ScaleLevel; 3] = [ScaleLevel::Micro, ScaleLevel::Meso, ScaleLevel::Macro];
+}
+
 /// Weight transfer parameters
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct WeightTransferParams {
     /// Transfer rate γ ∈ [0, 1]
     pub gamma: f64,
@@ -123,7 +523,7 @@ impl Default for WeightTransferParams {
 }
 
 /// DoubleKick parameters for dual orthogonal impulse
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DoubleKickParams {
     /// First impulse magnitude
     pub alpha1: f64,
@@ -141,7 +541,7 @@ impl Default for DoubleKickParams {
 }
 
 /// Complete OMEGA parameters
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct OmegaParams {
     pub masking: MaskingParams,
     pub resonance: ResonanceParams,
@@ -156,7 +556,12 @@ impl Default for OmegaParams {
         Self {
             masking: MaskingParams {
                 theta: 0.0,
-                sigma: [0u8; 32],
+                sigma: Sigma::default(),
+                rounds: DEFAULT_MASKING_ROUNDS,
+                authenticated: false,
+                counter: 0,
+                nonce: [0; 12],
+                cipher: KeystreamCipher::ChaCha20,
             },
             resonance: ResonanceParams::default(),
             sweep: SweepParams::default(),
@@ -167,13 +572,72 @@ impl Default for OmegaParams {
     }
 }
 
+/// Allowed deviation of the weight-transfer level weights from summing to 1
+pub const WEIGHT_SUM_TOLERANCE: f64 = 1e-6;
+
+impl OmegaParams {
+    /// Check the parameters the operators rely on
+    ///
+    /// Rejects a transfer rate γ outside [0, 1], a non-positive resonance
+    /// bandwidth, a zero (or non-finite) sweep gate width β, which would
+    /// divide by zero in the gate, a non-positive sweep `max_rate`, and
+    /// level weights that do not sum to 1.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(OmegaError::ParameterError(msg));
+
+        let gamma = self.weight_transfer.gamma;
+        if !(0.0..=1.0).contains(&gamma) {
+            return invalid(format!("weight_transfer.gamma must be in [0, 1], got {}", gamma));
+        }
+        let epsilon = self.resonance.epsilon;
+        if epsilon.is_nan() || epsilon <= 0.0 {
+            return invalid(format!("resonance.epsilon must be positive, got {}", epsilon));
+        }
+        let beta = self.sweep.beta;
+        if beta == 0.0 || !beta.is_finite() {
+            return invalid(format!("sweep.beta must be finite and nonzero, got {}", beta));
+        }
+        if let Some(rate) = self.sweep.max_rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                return invalid(format!("sweep.max_rate must be positive and finite, got {}", rate));
+            }
+        }
+        let weight_sum: f64 = self.weight_transfer.levels.iter().map(|(_, w)| w).sum();
+        if weight_sum.is_nan() || (weight_sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE {
+            return invalid(format!("weight_transfer.levels must sum to 1, got {}", weight_sum));
+        }
+        Ok(())
+    }
+}
+
 /// Node configuration
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NodeConfig {
     /// Local resonance frequency
     pub omega: f64,
     /// OMEGA parameters
     pub params: OmegaParams,
+    /// Exact-reconstruction mode: masking is the only transform, bytes are
+    /// packed into the vector exactly, and the spectral operators and
+    /// resonance filtering are bypassed
+    pub lossless: bool,
+    /// Per-operator logging verbosity
+    pub log: crate::logging::LogConfig,
+    /// Salt mixed into every masking key derivation; nodes only understand
+    /// each other when they share it (empty = no salt)
+    pub key_salt: Vec<u8>,
+    /// Epochs tried on each side of the local one when unmasking, so peers
+    /// whose key rotation is slightly out of step still decode (0 = local
+    /// epoch only)
+    pub epoch_window: u64,
+    /// Number of discrete phases θ is quantized to (0 = continuous); nodes
+    /// only understand each other when they share it
+    pub theta_steps: u32,
+    /// Components per chunk of the state vector, of `vectorize` and of the
+    /// Pfadinvarianz projection (at least 1, and at least 2 unless lossless:
+    /// the spectral path carries the frequency marker within each chunk, so
+    /// frequencies below about π / dimension are not reliably detected)
+    pub dimension: usize,
 }
 
 impl Default for NodeConfig {
@@ -181,10 +645,264 @@ impl Default for NodeConfig {
         Self {
             omega: 1.0,
             params: OmegaParams::default(),
+            lossless: false,
+            log: crate::logging::LogConfig::default(),
+            key_salt: Vec::new(),
+            epoch_window: 0,
+            theta_steps: 0,
+            dimension: crate::utils::VECTOR_CHUNK_DIM,
         }
     }
 }
 
+impl NodeConfig {
+    /// Configuration whose send → receive round trip is bit-exact
+    pub fn lossless() -> Self {
+        Self {
+            lossless: true,
+            ..Self::default()
+        }
+    }
+
+    /// Check the operator parameters and the dimension
+    pub fn validate(&self) -> Result<()> {
+        self.params.validate()?;
+        if self.dimension == 0 {
+            return Err(OmegaError::ParameterError("Dimension must be at least 1".to_string()));
+        }
+        if self.dimension == 1 && !self.lossless {
+            return Err(OmegaError::ParameterError(
+                "Dimension must be at least 2 on the spectral path".to_string()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builder starting from the default configuration
+    pub fn builder() -> NodeConfigBuilder {
+        NodeConfigBuilder::default()
+    }
+}
+
+/// Fluent construction of a `NodeConfig`: starts from the defaults and
+/// overrides only the fields that are set; `build` validates the result
+#[derive(Clone, Debug, Default)]
+pub struct NodeConfigBuilder {
+    config: NodeConfig,
+}
+
+impl NodeConfigBuilder {
+    /// Local resonance frequency
+    pub fn omega(mut self, omega: f64) -> Self {
+        self.config.omega = omega;
+        self
+    }
+
+    /// Exact-reconstruction mode
+    pub fn lossless(mut self, lossless: bool) -> Self {
+        self.config.lossless = lossless;
+        self
+    }
+
+    /// Per-operator logging verbosity
+    pub fn log(mut self, log: crate::logging::LogConfig) -> Self {
+        self.config.log = log;
+        self
+    }
+
+    /// Per-deployment masking key salt
+    pub fn key_salt(mut self, salt: impl Into<Vec<u8>>) -> Self {
+        self.config.key_salt = salt.into();
+        self
+    }
+
+    /// Epochs tried on each side of the local one when unmasking
+    pub fn epoch_window(mut self, epochs: u64) -> Self {
+        self.config.epoch_window = epochs;
+        self
+    }
+
+    /// Number of discrete phases θ is quantized to (0 = continuous)
+    pub fn theta_steps(mut self, steps: u32) -> Self {
+        self.config.theta_steps = steps;
+        self
+    }
+
+    /// Components per chunk of the vector space
+    pub fn dimension(mut self, dimension: usize) -> Self {
+        self.config.dimension = dimension;
+        self
+    }
+
+    /// Replace all operator parameters at once
+    pub fn params(mut self, params: OmegaParams) -> Self {
+        self.config.params = params;
+        self
+    }
+
+    /// Resonance bandwidth
+    pub fn resonance_epsilon(mut self, epsilon: f64) -> Self {
+        self.config.params.resonance.epsilon = epsilon;
+        self
+    }
+
+    /// Sweep base threshold
+    pub fn sweep_tau0(mut self, tau0: f64) -> Self {
+        self.config.params.sweep.tau0 = tau0;
+        self
+    }
+
+    /// Sweep gate width
+    pub fn sweep_beta(mut self, beta: f64) -> Self {
+        self.config.params.sweep.beta = beta;
+        self
+    }
+
+    /// Sweep threshold schedule
+    pub fn sweep_schedule(mut self, schedule: SweepSchedule) -> Self {
+        self.config.params.sweep.schedule = schedule;
+        self
+    }
+
+    /// Sweep gating on the mean or per component
+    pub fn sweep_mode(mut self, mode: SweepMode) -> Self {
+        self.config.params.sweep.mode = mode;
+        self
+    }
+
+    /// Sweep schedule cycle: ticks per period and threshold swing
+    pub fn sweep_cycle(mut self, period: f64, delta_tau: f64) -> Self {
+        self.config.params.sweep.period = period;
+        self.config.params.sweep.delta_tau = delta_tau;
+        self
+    }
+
+    /// Ticks during which the sweep gate stays open
+    pub fn warmup_ticks(mut self, ticks: u64) -> Self {
+        self.config.params.sweep.warmup_ticks = ticks;
+        self
+    }
+
+    /// Weight transfer rate γ
+    pub fn gamma(mut self, gamma: f64) -> Self {
+        self.config.params.weight_transfer.gamma = gamma;
+        self
+    }
+
+    /// Weight of each scale level
+    pub fn weight_levels(mut self, levels: Vec<(ScaleLevel, f64)>) -> Self {
+        self.config.params.weight_transfer.levels = levels;
+        self
+    }
+
+    /// DoubleKick impulse magnitudes
+    pub fn doublekick(mut self, alpha1: f64, alpha2: f64) -> Self {
+        self.config.params.doublekick = DoubleKickParams { alpha1, alpha2 };
+        self
+    }
+
+    /// Validate and return the configuration
+    pub fn build(self) -> Result<NodeConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Logical node address, checked after resonance as a second-stage filter
+///
+/// Carried inside the masked payload, so only nodes holding the frame's key
+/// learn whom it is addressed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NodeId(pub [u8; 16]);
+
+impl NodeId {
+    /// Encoded length in bytes
+    pub const LEN: usize = 16;
+
+    /// Address every node accepts
+    pub const BROADCAST: NodeId = NodeId([0xff; 16]);
+
+    /// Fresh random address
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl From<[u8; 16]> for NodeId {
+    fn from(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Descriptor a node advertises during a handshake
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDescriptor {
+    /// Preferred resonance frequency
+    pub frequency: f64,
+    /// Current key epoch
+    pub epoch: u64,
+    /// Features the node supports
+    #[serde(default = "NodeDescriptor::legacy_capabilities")]
+    pub capabilities: Capabilities,
+}
+
+impl NodeDescriptor {
+    fn legacy_capabilities() -> Capabilities {
+        Capabilities::LEGACY
+    }
+
+    /// Encode the descriptor as a control frame
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Decode a descriptor control frame
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Parameters two nodes agreed on during a handshake
+#[derive(Clone, Debug, PartialEq)]
+pub struct NegotiatedSession {
+    /// Common resonance frequency (lower of the two preferences)
+    pub frequency: f64,
+    /// Synchronized epoch (later of the two, so keys never roll back)
+    pub epoch: u64,
+    /// Most preferred masking mode both nodes support
+    pub masking_mode: MaskingMode,
+    /// Features both nodes support
+    pub capabilities: Capabilities,
+}
+
+impl NegotiatedSession {
+    /// Agree on common parameters from two descriptors (symmetric); errors
+    /// if the nodes share no masking mode
+    pub fn agree(a: &NodeDescriptor, b: &NodeDescriptor) -> Result<Self> {
+        let capabilities = negotiate_capabilities(a.capabilities, b.capabilities)?;
+        Ok(Self {
+            frequency: a.frequency.min(b.frequency),
+            epoch: a.epoch.max(b.epoch),
+            masking_mode: capabilities.masking_modes()[0],
+            capabilities,
+        })
+    }
+}
+
+impl From<serde_json::Error> for OmegaError {
+    fn from(e: serde_json::Error) -> Self {
+        OmegaError::CodecError(e.to_string())
+    }
+}
+
 /// Result type for OMEGA operations
 pub type Result<T> = std::result::Result<T, OmegaError>;
 
@@ -206,6 +924,64 @@ pub enum OmegaError {
     #[error("Parameter error: {0}")]
     ParameterError(String),
 
+    #[error("Codec error: {0}")]
+    CodecError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
+
+/// Message of the `MaskingError` raised when an authentication tag does not verify
+pub const AUTHENTICATION_FAILED: &str = "authentication failed";
+
+/// `MaskingError` message when a send would repeat a (key, nonce) pair
+pub const NONCE_REUSE: &str = "nonce reuse";
+
+impl OmegaError {
+    /// Whether this error is a failed authentication tag check
+    pub fn is_authentication_failure(&self) -> bool {
+        matches!(self, OmegaError::MaskingError(msg) if msg == AUTHENTICATION_FAILED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_hkdf_sha256_rfc5869_vectors() {
+        // RFC 5869, appendix A, test cases 1-3 (SHA-256)
+        let long = |start: u8| (0..80).map(|i| start.wrapping_add(i)).collect::<Vec<u8>>();
+        let cases = [
+            (
+                vec![0x0b; 22],
+                hex("000102030405060708090a0b0c"),
+                hex("f0f1f2f3f4f5f6f7f8f9"),
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+            ),
+            (
+                long(0x00),
+                long(0x60),
+                long(0xb0),
+                "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87",
+            ),
+            (
+                vec![0x0b; 22],
+                vec![],
+                vec![],
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8",
+            ),
+        ];
+
+        for (ikm, salt, info, okm) in cases {
+            let expected = hex(okm);
+            let mut out = vec![0u8; expected.len()];
+            hkdf_sha256(&salt, &ikm, &info, &mut out);
+            assert_eq!(out, expected);
+        }
+    }
+}
@@ -1,45 +1,211 @@
-/// Utility functions for OMEGA Protocol
+//! Utility functions for OMEGA Protocol
 
 use crate::types::*;
-use ndarray::Array1;
+use ndarray::{Array1, Array2, ArrayD};
+use ndarray_npy::{NpzReader, NpzWriter};
+use std::path::Path;
 
-/// Convert bytes to 5D vector representation
+/// Components per chunk produced by `vectorize` (the default dimension)
+pub const VECTOR_CHUNK_DIM: usize = 5;
+
+/// Bytes of the little-endian length prefix written by `vectorize`
+pub const LENGTH_PREFIX_BYTES: usize = 4;
+
+/// Convert bytes to a vector of consecutive 5D chunks, one component per byte
+///
+/// The byte stream is the original length as a little-endian `u32` followed
+/// by the data, zero-padded to a multiple of five bytes, so the result has
+/// `5 * ceil((len + 4) / 5)` components. Empty input yields a single chunk.
 pub fn vectorize(data: &[u8]) -> Result<OmegaVector> {
-    if data.is_empty() {
-        return Err(OmegaError::VectorizationError(
-            "Cannot vectorize empty data".to_string()
-        ));
+    vectorize_with_dim(data, VECTOR_CHUNK_DIM)
+}
+
+/// `vectorize` into chunks of `dimension` components; `devectorize` inverts
+/// it for any dimension
+pub fn vectorize_with_dim(data: &[u8], dimension: usize) -> Result<OmegaVector> {
+    if dimension == 0 {
+        return Err(OmegaError::VectorizationError("Chunk dimension must be positive".to_string()));
     }
+    let len = u32::try_from(data.len()).map_err(|_| {
+        OmegaError::VectorizationError("Data too long for the length prefix".to_string())
+    })?;
 
-    // Pad or truncate to multiple of 5
-    let target_len = ((data.len() + 4) / 5) * 5;
-    let mut padded = data.to_vec();
-    padded.resize(target_len, 0);
+    let mut framed = Vec::with_capacity(LENGTH_PREFIX_BYTES + data.len() + dimension);
+    framed.extend_from_slice(&len.to_le_bytes());
+    framed.extend_from_slice(data);
 
-    // Take first 5 bytes and normalize to [-1, 1]
-    let mut vec = Array1::zeros(5);
-    for i in 0..5.min(padded.len()) {
-        vec[i] = (padded[i] as f64 - 128.0) / 128.0;
-    }
+    // Pad to a whole number of chunks
+    let target_len = framed.len().div_ceil(dimension) * dimension;
+    framed.resize(target_len, 0);
 
-    Ok(vec)
+    // Normalize every byte to [-1, 1)
+    Ok(framed.iter().map(|&b| (b as f64 - 128.0) / 128.0).collect())
 }
 
-/// Convert 5D vector back to bytes
+/// Convert a vector back to bytes, one byte per component
+///
+/// Exact inverse of `vectorize`: the length prefix is read back and the
+/// output truncated to exactly the original bytes. The prefix is read from
+/// the flat byte stream, so it may span chunk boundaries, and the chunk
+/// dimension does not matter.
 pub fn devectorize(v: &OmegaVector) -> Result<Vec<u8>> {
     let mut bytes = Vec::with_capacity(v.len());
 
     for &val in v.iter() {
         // Denormalize from [-1, 1] to [0, 255]
-        let byte_val = ((val * 128.0) + 128.0)
-            .max(0.0)
-            .min(255.0) as u8;
+        let byte_val = ((val * 128.0) + 128.0).round().clamp(0.0, 255.0) as u8;
         bytes.push(byte_val);
     }
 
+    if bytes.len() < LENGTH_PREFIX_BYTES {
+        return Err(OmegaError::VectorizationError(
+            "Vector too short for the length prefix".to_string()
+        ));
+    }
+    let mut prefix = [0u8; LENGTH_PREFIX_BYTES];
+    prefix.copy_from_slice(&bytes[..LENGTH_PREFIX_BYTES]);
+    let len = u32::from_le_bytes(prefix) as usize;
+    if len > bytes.len() - LENGTH_PREFIX_BYTES {
+        return Err(OmegaError::VectorizationError(
+            "Length prefix exceeds the vector".to_string()
+        ));
+    }
+
+    bytes.drain(..LENGTH_PREFIX_BYTES);
+    bytes.truncate(len);
     Ok(bytes)
 }
 
+/// Bytes carried per component by `pack_bytes`
+pub const PACKED_BYTES_PER_COMPONENT: usize = 6;
+
+/// Mean of each consecutive chunk of `dimension` components
+///
+/// A trailing partial chunk is ignored; `dimension` 0 gives an empty vector.
+pub fn chunk_means(v: &OmegaVector, dimension: usize) -> OmegaVector {
+    if dimension == 0 {
+        return Array1::zeros(0);
+    }
+    v.exact_chunks(dimension)
+        .into_iter()
+        .map(|chunk| chunk.sum() / dimension as f64)
+        .collect()
+}
+
+/// Chunk-constant vector with every level repeated `dimension` times, so
+/// `chunk_means(&spread_chunks(levels, d), d) == levels`
+pub fn spread_chunks(levels: &OmegaVector, dimension: usize) -> OmegaVector {
+    levels
+        .iter()
+        .flat_map(|&level| std::iter::repeat_n(level, dimension))
+        .collect()
+}
+
+/// `v` with each whole chunk shifted to zero mean (the part of `v` that
+/// `chunk_means` does not see); a trailing partial chunk is kept as it is
+pub fn chunk_shape(v: &OmegaVector, dimension: usize) -> OmegaVector {
+    let mut shape = v.clone();
+    if dimension == 0 {
+        return shape;
+    }
+    for mut chunk in shape.exact_chunks_mut(dimension) {
+        let mean = chunk.sum() / dimension as f64;
+        chunk -= mean;
+    }
+    shape
+}
+
+/// Exact byte-to-vector packing
+///
+/// Component 0 holds the byte length; each following component holds up to
+/// six bytes as an integer below 2^48, which an f64 represents exactly.
+/// `unpack_bytes` inverts this bit for bit as long as the vector is not
+/// transformed in between.
+pub fn pack_bytes(data: &[u8]) -> OmegaVector {
+    let mut components = Vec::with_capacity(1 + data.len().div_ceil(PACKED_BYTES_PER_COMPONENT));
+    components.push(data.len() as f64);
+
+    for chunk in data.chunks(PACKED_BYTES_PER_COMPONENT) {
+        let mut word = [0u8; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        components.push(u64::from_le_bytes(word) as f64);
+    }
+
+    Array1::from_vec(components)
+}
+
+/// Inverse of `pack_bytes`
+pub fn unpack_bytes(v: &OmegaVector) -> Result<Vec<u8>> {
+    let invalid = |msg: &str| OmegaError::VectorizationError(msg.to_string());
+    let is_integer_below = |x: f64, bound: f64| x >= 0.0 && x < bound && x.fract() == 0.0;
+
+    let len = *v.first().ok_or_else(|| invalid("Packed vector has no length component"))?;
+    if !is_integer_below(len, (1u64 << 53) as f64) {
+        return Err(invalid("Packed vector has an invalid length component"));
+    }
+    let len = len as usize;
+    if v.len() != 1 + len.div_ceil(PACKED_BYTES_PER_COMPONENT) {
+        return Err(invalid("Packed vector length does not match its length component"));
+    }
+
+    let mut data = Vec::with_capacity(len);
+    for &x in v.iter().skip(1) {
+        if !is_integer_below(x, (1u64 << 48) as f64) {
+            return Err(invalid("Packed component is not a 48-bit integer"));
+        }
+        data.extend_from_slice(&(x as u64).to_le_bytes()[..PACKED_BYTES_PER_COMPONENT]);
+    }
+    data.truncate(len);
+
+    Ok(data)
+}
+
+/// Leading byte of the `serialize_vector` format, identifying its version
+pub const VECTOR_WIRE_VERSION: u8 = 1;
+
+/// Compact wire encoding of a vector: the version byte, the component count
+/// as a little-endian `u32`, then each component as a little-endian `f64`
+///
+/// Components are written bit for bit, so NaN and infinite values are kept
+/// (NaN payloads included); rejecting them is left to the receiver.
+pub fn serialize_vector(v: &OmegaVector) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + 4 + 8 * v.len());
+    out.push(VECTOR_WIRE_VERSION);
+    out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+    for x in v.iter() {
+        out.extend_from_slice(&x.to_le_bytes());
+    }
+    out
+}
+
+/// Decode `serialize_vector` output; an unknown version, a truncated buffer
+/// or a count that does not match the data is a `VectorizationError`
+pub fn deserialize_vector(bytes: &[u8]) -> Result<OmegaVector> {
+    let invalid = |msg: String| OmegaError::VectorizationError(msg);
+
+    let (&version, rest) = bytes.split_first().ok_or_else(|| invalid("Empty vector encoding".to_string()))?;
+    if version != VECTOR_WIRE_VERSION {
+        return Err(invalid(format!("Unsupported vector encoding version {}", version)));
+    }
+    if rest.len() < 4 {
+        return Err(invalid("Truncated vector length".to_string()));
+    }
+    let (len, data) = rest.split_at(4);
+    let len = u32::from_le_bytes(len.try_into().expect("4 bytes")) as usize;
+    if Some(data.len()) != len.checked_mul(8) {
+        return Err(invalid(format!(
+            "Vector encoding declares {} components but carries {} bytes",
+            len,
+            data.len()
+        )));
+    }
+
+    Ok(data
+        .chunks_exact(8)
+        .map(|b| f64::from_le_bytes(b.try_into().expect("8 bytes")))
+        .collect())
+}
+
 /// Set frequency component in vector (simplified version)
 /// In a full implementation, this would use FFT to inject frequency
 pub fn set_frequency(v: OmegaVector, omega: f64) -> Result<OmegaVector> {
@@ -58,6 +224,29 @@ pub fn set_frequency(v: OmegaVector, omega: f64) -> Result<OmegaVector> {
     Ok(v + freq_component)
 }
 
+/// Unit-amplitude linear chirp of `len` samples whose instantaneous
+/// frequency moves from `f_start` at the first sample to `f_end` at the last
+/// (radians per sample)
+pub fn chirp(len: usize, f_start: f64, f_end: f64) -> OmegaVector {
+    // Phase is the running sum of the instantaneous frequency
+    let rate = if len > 1 { (f_end - f_start) / (len - 1) as f64 } else { 0.0 };
+    Array1::from_iter((0..len).map(|i| {
+        let t = i as f64;
+        (f_start * t + 0.5 * rate * t * t).sin()
+    }))
+}
+
+/// Add a linear chirp marker sweeping from `f_start` to `f_end`, at the same
+/// amplitude as the `set_frequency` tone
+///
+/// Unlike a fixed tone, the marker spreads its energy across the band, so it
+/// is hard to spot without knowing both end frequencies; see
+/// `ResonanceOperator::detect_chirp`.
+pub fn set_frequency_chirp(v: OmegaVector, f_start: f64, f_end: f64) -> Result<OmegaVector> {
+    let marker = chirp(v.len(), f_start, f_end) * 0.1;
+    Ok(v + marker)
+}
+
 /// Compute dominant frequency using simple autocorrelation
 /// (Simplified version; full implementation uses FFT)
 pub fn compute_dominant_frequency(v: &OmegaVector) -> f64 {
@@ -74,8 +263,360 @@ pub fn compute_dominant_frequency(v: &OmegaVector) -> f64 {
     }
 
     // Frequency is proportional to zero crossings
-    let freq = (zero_crossings as f64 / v.len() as f64) * std::f64::consts::PI;
-    freq
+    (zero_crossings as f64 / v.len() as f64) * std::f64::consts::PI
+}
+
+/// Number of histogram bins used by `shannon_entropy`
+pub const ENTROPY_BINS: usize = 16;
+
+/// Signal energy: sum of squared components (squared L2 norm)
+pub fn energy(v: &OmegaVector) -> f64 {
+    v.iter().map(|x| x * x).sum()
+}
+
+/// First differences `v[i + 1] - v[i]`; the result has one component less
+/// than `v` (empty for fewer than two components)
+pub fn diff<F: Float>(v: &Array1<F>) -> Array1<F> {
+    Array1::from_iter(v.iter().zip(v.iter().skip(1)).map(|(&a, &b)| b - a))
+}
+
+/// Running sums `v[0] + ... + v[i]`, same length as `v`
+///
+/// Inverse of `diff` up to the initial value: `v[0] + cumsum(diff(v))[i]`
+/// is `v[i + 1]`.
+pub fn cumsum<F: Float>(v: &Array1<F>) -> Array1<F> {
+    let mut total = F::zero();
+    v.mapv(|x| {
+        total = total + x;
+        total
+    })
+}
+
+/// How `downmix` reduces a vector to one scalar
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DownmixMethod {
+    /// Arithmetic mean of the components
+    #[default]
+    Mean,
+    /// Sum of squared components, as `energy`
+    Energy,
+    /// Largest absolute component
+    DominantMagnitude,
+}
+
+/// Reduce a vector of any dimension to a single representative scalar,
+/// e.g. to plot node activity as a time series (0 for an empty vector)
+pub fn downmix(v: &OmegaVector, method: DownmixMethod) -> f64 {
+    if v.is_empty() {
+        return 0.0;
+    }
+    match method {
+        DownmixMethod::Mean => v.sum() / v.len() as f64,
+        DownmixMethod::Energy => energy(v),
+        DownmixMethod::DominantMagnitude => v.iter().fold(0.0, |max, x| x.abs().max(max)),
+    }
+}
+
+/// Descriptive statistics of a vector, from `summary`
+///
+/// All fields are 0 for an empty vector. `std` is the population standard
+/// deviation and `norm` the L2 norm.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VectorSummary {
+    pub len: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std: f64,
+    pub norm: f64,
+}
+
+impl std::fmt::Display for VectorSummary {
+    /// One line; the precision (default 4) applies to every statistic
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let p = f.precision().unwrap_or(4);
+        write!(
+            f,
+            "n={} min={:.p$} max={:.p$} mean={:.p$} std={:.p$} norm={:.p$}",
+            self.len, self.min, self.max, self.mean, self.std, self.norm
+        )
+    }
+}
+
+/// Min, max, mean, standard deviation and norm of a vector in one pass
+/// (Welford's update for the mean and variance)
+pub fn summary(v: &OmegaVector) -> VectorSummary {
+    if v.is_empty() {
+        return VectorSummary::default();
+    }
+
+    let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut mean, mut m2, mut sum_sq) = (0.0, 0.0, 0.0);
+    for (i, &x) in v.iter().enumerate() {
+        min = min.min(x);
+        max = max.max(x);
+        let delta = x - mean;
+        mean += delta / (i + 1) as f64;
+        m2 += delta * (x - mean);
+        sum_sq += x * x;
+    }
+
+    VectorSummary {
+        len: v.len(),
+        min,
+        max,
+        mean,
+        std: (m2 / v.len() as f64).sqrt(),
+        norm: sum_sq.sqrt(),
+    }
+}
+
+/// Components as `[a, b, ...]` with exactly `decimals` decimal places,
+/// identical on every platform (for golden files and frame dumps)
+///
+/// Values are rounded from their exact binary value, so the output does not
+/// depend on the platform or locale. A value that rounds to zero prints
+/// without a sign; non-finite values print as `NaN`, `inf` and `-inf`.
+pub fn format_vector(v: &OmegaVector, decimals: usize) -> String {
+    let mut out = String::with_capacity(2 + v.len() * (decimals + 5));
+    out.push('[');
+    for (i, &x) in v.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let formatted = format!("{:.*}", decimals, x);
+        match formatted.strip_prefix('-') {
+            Some(magnitude) if magnitude.bytes().all(|b| b == b'0' || b == b'.') => out.push_str(magnitude),
+            _ => out.push_str(&formatted),
+        }
+    }
+    out.push(']');
+    out
+}
+
+/// Shannon entropy (bits) of the components, over a histogram of
+/// `ENTROPY_BINS` equal-width bins spanning the vector's range
+pub fn shannon_entropy(v: &OmegaVector) -> f64 {
+    if v.is_empty() {
+        return 0.0;
+    }
+
+    let min = v.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if (max - min).abs() < 1e-12 {
+        return 0.0; // Constant vector: a single occupied bin
+    }
+
+    let mut histogram = [0usize; ENTROPY_BINS];
+    for &x in v.iter() {
+        let bin = ((x - min) / (max - min) * ENTROPY_BINS as f64) as usize;
+        histogram[bin.min(ENTROPY_BINS - 1)] += 1;
+    }
+
+    let n = v.len() as f64;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / n;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Add white Gaussian noise with standard deviation `stddev` to every component
+pub fn add_gaussian_noise<R: rand::Rng>(v: &OmegaVector, stddev: f64, rng: &mut R) -> OmegaVector {
+    if stddev == 0.0 {
+        return v.clone();
+    }
+    v.mapv(|x| {
+        // Box-Muller transform
+        let u1: f64 = 1.0 - rng.gen::<f64>();
+        let u2: f64 = rng.gen();
+        x + stddev * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    })
+}
+
+/// Coherently combine redundant copies of the same frame (maximal-ratio style)
+///
+/// Each copy is polarity-aligned against the plain average and weighted by the
+/// inverse of its residual variance, so cleaner copies contribute more.
+/// Copies whose length differs from the first frame are ignored.
+pub fn combine_coherent(frames: &[OmegaVector]) -> OmegaVector {
+    let len = match frames.first() {
+        Some(first) => first.len(),
+        None => return Array1::zeros(0),
+    };
+    let copies: Vec<&OmegaVector> = frames.iter().filter(|f| f.len() == len).collect();
+    if copies.len() == 1 || len == 0 {
+        return copies[0].clone();
+    }
+
+    // Reference: plain average of the copies
+    let mut reference: OmegaVector = Array1::zeros(len);
+    for f in &copies {
+        reference += *f;
+    }
+    reference /= copies.len() as f64;
+
+    let mut combined: OmegaVector = Array1::zeros(len);
+    let mut total_weight = 0.0;
+
+    for f in &copies {
+        // Align polarity with the reference
+        let correlation: f64 = f.iter().zip(reference.iter()).map(|(a, b)| a * b).sum();
+        let sign = if correlation < 0.0 { -1.0 } else { 1.0 };
+        let aligned = f.mapv(|x| sign * x);
+
+        // Weight by inverse residual (noise) variance
+        let noise_var = aligned
+            .iter()
+            .zip(reference.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>() / len as f64;
+        let weight = 1.0 / noise_var.max(1e-12);
+
+        combined = combined + aligned * weight;
+        total_weight += weight;
+    }
+
+    combined / total_weight
+}
+
+/// Time reversal: component i moves to len - 1 - i
+pub fn reverse(v: &OmegaVector) -> OmegaVector {
+    v.iter().rev().copied().collect()
+}
+
+/// Whether the vector equals its own reversal within `tol` per component
+///
+/// For real signals this is conjugate symmetry, i.e. a real-valued spectrum.
+pub fn is_symmetric(v: &OmegaVector, tol: f64) -> bool {
+    v.iter()
+        .zip(v.iter().rev())
+        .take(v.len() / 2)
+        .all(|(a, b)| (a - b).abs() <= tol)
+}
+
+/// Circular offset by which `received` lags `reference`: the shift `s`
+/// maximizing the cross-correlation `Σ reference[i] · received[(i + s) mod n]`
+///
+/// Ties go to the smallest shift. Returns 0 for empty vectors or vectors of
+/// different length.
+pub fn estimate_shift(reference: &OmegaVector, received: &OmegaVector) -> usize {
+    let n = reference.len();
+    if n == 0 || received.len() != n {
+        return 0;
+    }
+
+    let correlation = |shift: usize| -> f64 {
+        (0..n).map(|i| reference[i] * received[(i + shift) % n]).sum()
+    };
+    let mut best = (0, correlation(0));
+    for shift in 1..n {
+        let c = correlation(shift);
+        if c > best.1 {
+            best = (shift, c);
+        }
+    }
+    best.0
+}
+
+/// Undo a circular lag of `shift` (see `estimate_shift`): component
+/// `(i + shift) mod n` moves to `i`
+pub fn align(v: &OmegaVector, shift: usize) -> OmegaVector {
+    let n = v.len();
+    (0..n).map(|i| v[(i + shift) % n]).collect()
+}
+
+/// Number of non-overlapping resonance slots of half-width `epsilon` whose
+/// centres fit in `[min, max]`: `floor((max - min) / (2 * epsilon)) + 1`
+///
+/// Returns 0 for an empty range or a non-positive `epsilon`.
+pub fn estimate_channel_count(min: f64, max: f64, epsilon: f64) -> usize {
+    if !(max >= min && epsilon > 0.0) {
+        return 0;
+    }
+    // Tolerate rounding in the division, e.g. 0.6 / 0.2 = 2.9999999999999996
+    ((max - min) / (2.0 * epsilon) + 1e-9).floor() as usize + 1
+}
+
+/// Centres of the `estimate_channel_count` slots, starting at `min` and
+/// spaced `2 * epsilon` apart, so nodes on different slots never resonate
+/// with each other
+pub fn allocate_frequencies(min: f64, max: f64, epsilon: f64) -> Vec<f64> {
+    (0..estimate_channel_count(min, max, epsilon))
+        .map(|slot| min + slot as f64 * 2.0 * epsilon)
+        .collect()
+}
+
+/// Stack equal-length vectors into the rows of one `(count, len)` array
+fn stack_vectors(vectors: &[OmegaVector]) -> Result<Array2<f64>> {
+    let len = vectors.first().map_or(0, |v| v.len());
+    if vectors.iter().any(|v| v.len() != len) {
+        return Err(OmegaError::ParameterError(
+            "All vectors saved to one .npy array must have the same length".to_string()
+        ));
+    }
+    let values = vectors.iter().flat_map(|v| v.iter().copied()).collect();
+    Array2::from_shape_vec((vectors.len(), len), values)
+        .map_err(|e| OmegaError::ParameterError(e.to_string()))
+}
+
+/// Rows of a 2-D array, or a 1-D array as a single vector
+fn unstack_vectors(array: ArrayD<f64>) -> Result<Vec<OmegaVector>> {
+    match array.ndim() {
+        1 => Ok(vec![array.into_iter().collect()]),
+        2 => Ok(array.outer_iter().map(|row| row.iter().copied().collect()).collect()),
+        _ => Err(OmegaError::CodecError("npy: expected a 1-D or 2-D array".to_string())),
+    }
+}
+
+fn npy_error(err: impl std::fmt::Display) -> OmegaError {
+    OmegaError::CodecError(format!("npy: {}", err))
+}
+
+/// Write vectors as one little-endian float64 `.npy` array of shape
+/// `(count, len)`, loadable with `numpy.load`
+///
+/// All vectors must have the same length.
+pub fn save_npy<P: AsRef<Path>>(path: P, vectors: &[OmegaVector]) -> Result<()> {
+    ndarray_npy::write_npy(path, &stack_vectors(vectors)?).map_err(npy_error)
+}
+
+/// Read vectors written by `save_npy` (or NumPy): a float64 array of shape
+/// `(count, len)`, or `(len,)` for a single vector
+pub fn load_npy<P: AsRef<Path>>(path: P) -> Result<Vec<OmegaVector>> {
+    unstack_vectors(ndarray_npy::read_npy(path).map_err(npy_error)?)
+}
+
+/// Write named groups of vectors as the arrays of one `.npz` archive, as
+/// `numpy.savez` does; each group follows the `save_npy` rules
+pub fn save_npz<P: AsRef<Path>>(path: P, arrays: &[(&str, &[OmegaVector])]) -> Result<()> {
+    let mut npz = NpzWriter::new(std::fs::File::create(path)?);
+    for (name, vectors) in arrays {
+        // NumPy strips the extension again when loading
+        npz.add_array(format!("{}.npy", name), &stack_vectors(vectors)?)
+            .map_err(npy_error)?;
+    }
+    npz.finish().map_err(npy_error)?;
+    Ok(())
+}
+
+/// Read every array of a `.npz` archive (written by `save_npz` or
+/// `numpy.savez`) as a named group of vectors, in archive order
+pub fn load_npz<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Vec<OmegaVector>)>> {
+    let mut npz = NpzReader::new(std::fs::File::open(path)?).map_err(npy_error)?;
+    let names = npz.names().map_err(npy_error)?;
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let vectors = unstack_vectors(npz.by_index(index).map_err(npy_error)?)?;
+            let name = name.strip_suffix(".npy").map(str::to_string).unwrap_or(name);
+            Ok((name, vectors))
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -88,8 +629,58 @@ mod tests {
         let vec = vectorize(data).unwrap();
         let recovered = devectorize(&vec).unwrap();
 
-        // Should have same length after round-trip
-        assert_eq!(recovered.len(), 5);
+        assert_eq!(recovered, data);
+    }
+
+    #[test]
+    fn test_vectorize_whole_message() {
+        let data: Vec<u8> = (0..96u32).map(|i| (i * 37 % 256) as u8).collect();
+        let v = vectorize(&data).unwrap();
+        assert_eq!(v.len(), 100);
+        assert_eq!(devectorize(&v).unwrap(), data);
+
+        // Trailing zeros in the data survive; padding does not leak
+        let v = vectorize(&[7, 0, 0]).unwrap();
+        assert_eq!(v.len(), 10);
+        assert_eq!(devectorize(&v).unwrap(), vec![7, 0, 0]);
+
+        // Empty input round-trips to empty
+        let v = vectorize(&[]).unwrap();
+        assert_eq!(v.len(), VECTOR_CHUNK_DIM);
+        assert!(devectorize(&v).unwrap().is_empty());
+
+        assert!(devectorize(&Array1::zeros(3)).is_err());
+        assert!(devectorize(&Array1::ones(10)).is_err());
+    }
+
+    #[test]
+    fn test_vectorize_round_trip_random_lengths() {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(11);
+        for len in 0..1000 {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let v = vectorize(&data).unwrap();
+            assert_eq!(v.len() % VECTOR_CHUNK_DIM, 0);
+            assert_eq!(devectorize(&v).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_pack_unpack_exact() {
+        for len in [0usize, 1, 5, 6, 7, 13, 300] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 97 + 255) as u8).collect();
+            let packed = pack_bytes(&data);
+            assert_eq!(packed.len(), 1 + len.div_ceil(PACKED_BYTES_PER_COMPONENT));
+            assert_eq!(unpack_bytes(&packed).unwrap(), data);
+        }
+
+        let mut corrupted = pack_bytes(b"abcdefgh");
+        corrupted[1] += 0.5;
+        assert!(unpack_bytes(&corrupted).is_err());
+        assert!(unpack_bytes(&Array1::from_vec(vec![9.0, 1.0])).is_err());
+        assert!(unpack_bytes(&Array1::zeros(0)).is_err());
     }
 
     #[test]
@@ -104,6 +695,175 @@ mod tests {
         assert_eq!(result.len(), v.len());
     }
 
+    #[test]
+    fn test_combine_coherent() {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let clean = Array1::from_vec((0..32).map(|i| (0.4 * i as f64).sin()).collect());
+        let copies: Vec<OmegaVector> = (0..3)
+            .map(|_| clean.mapv(|x| x + rng.gen_range(-0.3..0.3)))
+            .collect();
+
+        let distance = |a: &OmegaVector| {
+            a.iter().zip(clean.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt()
+        };
+
+        let combined = combine_coherent(&copies);
+        let best_single = copies.iter().map(distance).fold(f64::INFINITY, f64::min);
+
+        // Combining improves on every individual copy
+        assert!(distance(&combined) < best_single);
+    }
+
+    #[test]
+    fn test_vector_wire_round_trip() {
+        let v = Array1::from_vec(vec![0.5, -0.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e-308]);
+        let bytes = serialize_vector(&v);
+        assert_eq!(bytes.len(), 1 + 4 + 8 * v.len());
+
+        let decoded = deserialize_vector(&bytes).unwrap();
+        let bits = |v: &OmegaVector| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&decoded), bits(&v));
+        assert!(deserialize_vector(&serialize_vector(&Array1::zeros(0))).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_vector_wire_rejects_malformed_input() {
+        let bytes = serialize_vector(&Array1::from_vec(vec![1.0, 2.0, 3.0]));
+        let rejected = |bytes: &[u8]| matches!(deserialize_vector(bytes), Err(OmegaError::VectorizationError(_)));
+
+        let mut corrupted = bytes.clone();
+        corrupted[1..5].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(rejected(&corrupted));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 99;
+        assert!(rejected(&wrong_version));
+
+        for cut in [0, 1, 3, bytes.len() - 1] {
+            assert!(rejected(&bytes[..cut]));
+        }
+    }
+
+    #[test]
+    fn test_diff_and_cumsum() {
+        let v = Array1::from_vec(vec![2.0, 3.5, -1.0, 0.25, 4.0]);
+        let d = diff(&v);
+        assert_eq!(d.len(), 4);
+
+        let restored = cumsum(&d) + v[0];
+        for (r, x) in restored.iter().zip(v.iter().skip(1)) {
+            assert!((r - x).abs() < 1e-12);
+        }
+
+        assert!(diff(&Array1::from_elem(6, 1.7)).iter().all(|&x| x == 0.0));
+        assert!(diff(&Array1::from_elem(1, 1.7)).is_empty());
+    }
+
+    #[test]
+    fn test_downmix_methods() {
+        let v = Array1::from_vec(vec![1.0, -4.0, 2.0, 0.5, 0.5]);
+        assert_eq!(downmix(&v, DownmixMethod::Mean), 0.0);
+        assert_eq!(downmix(&v, DownmixMethod::Energy), 21.5);
+        assert_eq!(downmix(&v, DownmixMethod::DominantMagnitude), 4.0);
+        assert_eq!(downmix(&Array1::zeros(0), DownmixMethod::DominantMagnitude), 0.0);
+    }
+
+    #[test]
+    fn test_energy_and_entropy() {
+        let v = Array1::from_vec(vec![0.5, -0.3, 0.1, 0.2, 0.4]);
+        let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((energy(&v) - norm * norm).abs() < 1e-12);
+
+        let constant = Array1::from_elem(64, 0.7);
+        let spread = Array1::from_vec((0..64).map(|i| i as f64 / 64.0).collect());
+
+        assert_eq!(shannon_entropy(&constant), 0.0);
+        assert!(shannon_entropy(&spread) > shannon_entropy(&constant));
+        // Uniform over all bins reaches the maximum log2(bins)
+        assert!((shannon_entropy(&spread) - (ENTROPY_BINS as f64).log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_summary() {
+        let v = ndarray::arr1(&[2.0, -1.0, 4.0, 3.0, 0.0, -2.0]);
+        let s = summary(&v);
+
+        assert_eq!((s.len, s.min, s.max), (6, -2.0, 4.0));
+        assert!((s.mean - v.sum() / 6.0).abs() < 1e-12);
+        assert!((s.norm - energy(&v).sqrt()).abs() < 1e-12);
+        let variance = v.iter().map(|x| (x - s.mean).powi(2)).sum::<f64>() / 6.0;
+        assert!((s.std - variance.sqrt()).abs() < 1e-12);
+
+        assert_eq!(
+            format!("{:.2}", s),
+            "n=6 min=-2.00 max=4.00 mean=1.00 std=2.16 norm=5.83"
+        );
+        assert_eq!(summary(&Array1::zeros(0)), VectorSummary::default());
+    }
+
+    #[test]
+    fn test_format_vector_is_stable() {
+        let v = ndarray::arr1(&[1.0 / 3.0, -2.5, 1e-9, -1e-9, 0.125, f64::NAN, f64::NEG_INFINITY]);
+
+        let first = format_vector(&v, 3);
+        assert_eq!(first, "[0.333, -2.500, 0.000, 0.000, 0.125, NaN, -inf]");
+        for _ in 0..10 {
+            assert_eq!(format_vector(&v, 3).as_bytes(), first.as_bytes());
+        }
+
+        assert_eq!(format_vector(&v, 0), "[0, -2, 0, 0, 0, NaN, -inf]");
+        assert_eq!(format_vector(&ndarray::arr1(&[0.125, -0.0]), 6), "[0.125000, 0.000000]");
+        assert_eq!(format_vector(&Array1::zeros(0), 2), "[]");
+    }
+
+    #[test]
+    fn test_reverse_and_symmetry() {
+        let v = Array1::from_vec(vec![0.5, -0.3, 0.1, 0.2, 0.4]);
+        assert_eq!(reverse(&v), Array1::from_vec(vec![0.4, 0.2, 0.1, -0.3, 0.5]));
+        assert_eq!(reverse(&reverse(&v)), v);
+        assert!(!is_symmetric(&v, 1e-9));
+
+        let palindrome = Array1::from_vec(vec![0.3, -0.7, 1.2, -0.7, 0.3 + 1e-12]);
+        assert!(is_symmetric(&palindrome, 1e-9));
+        assert!(!is_symmetric(&palindrome, 0.0));
+        assert!(is_symmetric(&Array1::zeros(0), 0.0));
+    }
+
+    #[test]
+    fn test_estimate_shift_and_align() {
+        let sent = Array1::from_vec(vec![0.9, -0.4, 0.1, 0.7, -0.8, 0.3, -0.2]);
+        // Misframed by two: every component arrives two slots late
+        let received: OmegaVector = (0..sent.len()).map(|i| sent[(i + sent.len() - 2) % sent.len()]).collect();
+
+        let shift = estimate_shift(&sent, &received);
+        assert_eq!(shift, 2);
+        assert_eq!(align(&received, shift), sent);
+
+        assert_eq!(estimate_shift(&sent, &sent), 0);
+        assert_eq!(estimate_shift(&sent, &Array1::zeros(3)), 0);
+        assert!(align(&Array1::zeros(0), 4).is_empty());
+    }
+
+    #[test]
+    fn test_channel_count() {
+        assert_eq!(estimate_channel_count(1.0, 2.0, 0.1), 6);
+        assert_eq!(estimate_channel_count(1.0, 1.6, 0.1), 4);
+        assert_eq!(estimate_channel_count(1.0, 1.0, 0.1), 1);
+        assert_eq!(estimate_channel_count(2.0, 1.0, 0.1), 0);
+        assert_eq!(estimate_channel_count(1.0, 2.0, 0.0), 0);
+
+        let slots = allocate_frequencies(1.0, 2.0, 0.1);
+        assert_eq!(slots.len(), estimate_channel_count(1.0, 2.0, 0.1));
+        assert!(slots.iter().all(|&f| (1.0..=2.0 + 1e-9).contains(&f)));
+        for pair in slots.windows(2) {
+            // Adjacent slots are exactly outside each other's resonance band
+            assert!(pair[1] - pair[0] >= 2.0 * 0.1 - 1e-12);
+        }
+    }
+
     #[test]
     fn test_compute_dominant_frequency() {
         let v = Array1::from_vec(vec![1.0, -1.0, 1.0, -1.0, 1.0]);
@@ -112,4 +872,79 @@ mod tests {
         // Should detect high frequency due to alternating pattern
         assert!(freq > 0.0);
     }
+
+    #[test]
+    fn test_chunk_means_and_spread() {
+        let v = Array1::from_vec(vec![1.0, 2.0, 3.0, -1.0, 1.0, 0.0, 9.0]);
+        assert_eq!(chunk_means(&v, 3), Array1::from_vec(vec![2.0, 0.0]));
+        assert!(chunk_means(&v, 0).is_empty());
+
+        let levels = Array1::from_vec(vec![0.5, -0.25]);
+        let spread = spread_chunks(&levels, 4);
+        assert_eq!(spread.to_vec(), vec![0.5, 0.5, 0.5, 0.5, -0.25, -0.25, -0.25, -0.25]);
+        assert_eq!(chunk_means(&spread, 4), levels);
+
+        let shape = chunk_shape(&v, 3);
+        assert_eq!(shape.to_vec(), vec![-1.0, 0.0, 1.0, -1.0, 1.0, 0.0, 9.0]);
+        assert!(chunk_means(&shape, 3).iter().all(|&m| m == 0.0));
+        assert_eq!(chunk_shape(&spread, 4), Array1::<f64>::zeros(8));
+    }
+
+    #[test]
+    fn test_vectorize_with_dim() {
+        let data = b"sixteen-dim state";
+        for dimension in [1, 8, 16] {
+            let v = vectorize_with_dim(data, dimension).unwrap();
+            assert_eq!(v.len() % dimension, 0);
+            assert!(v.len() < LENGTH_PREFIX_BYTES + data.len() + dimension);
+            assert_eq!(devectorize(&v).unwrap(), data);
+        }
+        assert_eq!(vectorize_with_dim(data, VECTOR_CHUNK_DIM).unwrap(), vectorize(data).unwrap());
+        assert!(vectorize_with_dim(data, 0).is_err());
+    }
+
+    #[test]
+    fn test_npy_round_trip_exact() {
+        let vectors: Vec<OmegaVector> = (0..4)
+            .map(|k| Array1::from_vec((0..7).map(|i| (k * 7 + i) as f64 * 0.1 - 1.3).collect()))
+            .chain(std::iter::once(Array1::from_vec(vec![f64::MIN_POSITIVE, -0.0, 1e300, f64::EPSILON, 3.0, -2.5, 0.0])))
+            .collect();
+        let path = std::env::temp_dir().join(format!("omega-npy-{}.npy", std::process::id()));
+
+        save_npy(&path, &vectors).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let header_end = 10 + u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!(header_end % 64, 0);
+
+        let loaded = load_npy(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), vectors.len());
+        for (a, b) in loaded.iter().zip(vectors.iter()) {
+            let bits = |v: &OmegaVector| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(a), bits(b));
+        }
+
+        let ragged = vec![Array1::zeros(3), Array1::zeros(4)];
+        assert!(save_npy(&path, &ragged).is_err());
+    }
+
+    #[test]
+    fn test_npz_round_trip_exact() {
+        let captures: Vec<OmegaVector> = (0..3)
+            .map(|k| Array1::from_vec((0..5).map(|i| (k * 5 + i) as f64 / 3.0 - 2.0).collect()))
+            .collect();
+        let pilot = vec![Array1::from_vec(vec![f64::MIN_POSITIVE, -0.0, 1e300, f64::EPSILON])];
+        let path = std::env::temp_dir().join(format!("omega-npz-{}.npz", std::process::id()));
+
+        save_npz(&path, &[("captures", &captures), ("pilot", &pilot)]).unwrap();
+        let loaded = load_npz(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let bits = |vs: &[OmegaVector]| vs.iter().map(|v| v.iter().map(|x| x.to_bits()).collect()).collect::<Vec<Vec<_>>>();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].0, "captures");
+        assert_eq!(bits(&loaded[0].1), bits(&captures));
+        assert_eq!(loaded[1].0, "pilot");
+        assert_eq!(bits(&loaded[1].1), bits(&pilot));
+    }
 }